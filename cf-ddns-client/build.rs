@@ -0,0 +1,56 @@
+//! Captures details of the build that the client reports at run time, see `src/build_info.rs`.
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let target = env::var("TARGET").expect("Cargo sets TARGET for build scripts");
+    println!("cargo:rustc-env=CF_DDNS_TARGET={target}");
+
+    if let Some(hash) = git(&["rev-parse", "--short=12", "HEAD"]) {
+        println!("cargo:rustc-env=CF_DDNS_GIT_HASH={hash}");
+    }
+
+    // Rebuild when a commit is checked out or made, so that the hash stays current.
+    if let Some(dir) = git(&["rev-parse", "--absolute-git-dir"]) {
+        let dir = Path::new(&dir);
+        println!("cargo:rerun-if-changed={}", dir.join("HEAD").display());
+        println!(
+            "cargo:rerun-if-changed={}",
+            dir.join("refs/heads").display()
+        );
+    }
+
+    // SOURCE_DATE_EPOCH pins the date for reproducible builds.
+    let date = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse().ok())
+        .map_or_else(SystemTime::now, |epoch| {
+            UNIX_EPOCH + Duration::from_secs(epoch)
+        });
+    println!(
+        "cargo:rustc-env=CF_DDNS_BUILD_DATE={}",
+        humantime::format_rfc3339_seconds(date)
+    );
+
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(name, _)| {
+            let feature = name.strip_prefix("CARGO_FEATURE_")?;
+            Some(feature.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+    println!("cargo:rustc-env=CF_DDNS_FEATURES={}", features.join(","));
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
+
+/// The output of a git command, or `None` when building outside a repository, e.g. from a crates.io package.
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    output.status.success().then(|| stdout.trim().to_string())
+}
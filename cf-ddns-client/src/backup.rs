@@ -0,0 +1,167 @@
+//! Backing up the DNS records of a zone to JSON and planning their restoration.
+
+use std::fmt;
+
+use cf_ddns_cloudflare::{Record, Zone};
+use serde::{Deserialize, Serialize};
+
+/// A snapshot of every DNS record in a zone.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Backup {
+    /// When the backup was taken, in RFC 3339 format.
+    pub created: String,
+
+    /// The zone the records belong to.
+    pub zone: Zone,
+
+    /// The records in the zone.
+    pub records: Vec<Record>,
+}
+
+/// A change needed to bring a zone back to the state of a [`Backup`].
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    /// The record no longer exists and must be recreated.
+    Create(Record),
+
+    /// The record still exists but has been modified. Contains the current and the backed-up record.
+    Update(Box<Record>, Box<Record>),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Create(record) => write!(
+                f,
+                "+ {} {} {}",
+                record.name,
+                record.content.record_type(),
+                record.content
+            ),
+            Self::Update(current, backup) => {
+                write!(f, "~ {} {}", backup.name, backup.content.record_type())?;
+                if current.content != backup.content {
+                    write!(f, " {} → {}", current.content, backup.content)?;
+                }
+
+                if current.ttl != backup.ttl {
+                    write!(f, " ttl {} → {}", current.ttl, backup.ttl)?;
+                }
+
+                if current.proxied != backup.proxied {
+                    write!(f, " proxied {} → {}", current.proxied, backup.proxied)?;
+                }
+
+                if current.comment != backup.comment || current.tags != backup.tags {
+                    write!(f, " (comment or tags)")?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Plan the changes needed to restore the `backup` records given the `current` records of the zone.
+///
+/// Records are matched by ID, falling back to an identical name, type, and content for records that were deleted
+/// and recreated. Records that exist now but aren't in the backup are left alone.
+pub fn plan_restore(backup: &[Record], current: &[Record]) -> Vec<Change> {
+    backup
+        .iter()
+        .filter_map(|record| {
+            let same_id = current.iter().find(|c| c.id == record.id);
+            let same_content = || {
+                current
+                    .iter()
+                    .find(|c| c.name == record.name && c.content == record.content)
+            };
+
+            match same_id.or_else(same_content) {
+                Some(existing) if same_fields(existing, record) => None,
+                Some(existing) => {
+                    let mut backup = record.clone();
+                    backup.id.clone_from(&existing.id);
+                    Some(Change::Update(Box::new(existing.clone()), Box::new(backup)))
+                }
+                None => Some(Change::Create(record.clone())),
+            }
+        })
+        .collect()
+}
+
+/// Whether two records have the same user-editable fields, ignoring their IDs.
+fn same_fields(a: &Record, b: &Record) -> bool {
+    a.name == b.name
+        && a.content == b.content
+        && a.ttl == b.ttl
+        && a.proxied == b.proxied
+        && a.priority == b.priority
+        && a.comment == b.comment
+        && a.tags == b.tags
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use cf_ddns_cloudflare::RecordContent;
+
+    use super::*;
+    use crate::records;
+
+    fn record(id: &str, name: &str, last_octet: u8) -> Record {
+        let content = RecordContent::A {
+            content: Ipv4Addr::new(10, 0, 0, last_octet),
+        };
+
+        records::tests::record(id, name, content)
+    }
+
+    #[test]
+    fn unchanged_records_are_skipped() {
+        let records = [record("1", "a.example.com", 1)];
+        assert!(plan_restore(&records, &records).is_empty());
+    }
+
+    #[test]
+    fn deleted_records_are_created() {
+        let backup = [record("1", "a.example.com", 1)];
+        let plan = plan_restore(&backup, &[]);
+        assert_eq!(plan, [Change::Create(backup[0].clone())]);
+    }
+
+    #[test]
+    fn modified_records_are_updated() {
+        let backup = [record("1", "a.example.com", 1)];
+        let current = [record("1", "a.example.com", 2)];
+        let plan = plan_restore(&backup, &current);
+        assert_eq!(
+            plan,
+            [Change::Update(
+                Box::new(current[0].clone()),
+                Box::new(backup[0].clone())
+            )]
+        );
+        assert_eq!(plan[0].to_string(), "~ a.example.com A 10.0.0.2 → 10.0.0.1");
+    }
+
+    #[test]
+    fn recreated_records_match_by_content() {
+        let backup = [record("1", "a.example.com", 1)];
+        let mut current = [record("2", "a.example.com", 1)];
+        assert!(plan_restore(&backup, &current).is_empty());
+
+        current[0].ttl = 60;
+        let plan = plan_restore(&backup, &current);
+        let mut expected = backup[0].clone();
+        expected.id = "2".to_string();
+        assert_eq!(
+            plan,
+            [Change::Update(
+                Box::new(current[0].clone()),
+                Box::new(expected)
+            )]
+        );
+    }
+}
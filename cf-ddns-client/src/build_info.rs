@@ -0,0 +1,103 @@
+//! Details of how the client was built, captured by the build script, for `version` and bug reports.
+
+use std::fmt;
+
+use serde::Serialize;
+
+/// The version of the running client.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The target triple the client was built for, e.g. `x86_64-unknown-linux-gnu`.
+pub const TARGET: &str = env!("CF_DDNS_TARGET");
+
+/// The abbreviated hash of the commit the client was built from, unless it was built outside a git repository.
+pub const GIT_HASH: Option<&str> = option_env!("CF_DDNS_GIT_HASH");
+
+/// When the client was built, in RFC 3339 format, or `SOURCE_DATE_EPOCH` if it was set.
+pub const BUILD_DATE: &str = env!("CF_DDNS_BUILD_DATE");
+
+/// The Cargo features the client was built with, separated by commas.
+const FEATURES: &str = env!("CF_DDNS_FEATURES");
+
+/// The details of the build, printed by `version`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct BuildInfo {
+    /// The version, e.g. `0.2.0`.
+    pub version: &'static str,
+
+    /// The abbreviated hash of the commit, if known.
+    pub git_hash: Option<&'static str>,
+
+    /// When the client was built, in RFC 3339 format.
+    pub build_date: &'static str,
+
+    /// The target triple.
+    pub target: &'static str,
+
+    /// The enabled Cargo features.
+    pub features: Vec<&'static str>,
+}
+
+impl BuildInfo {
+    /// The details of the running client's build.
+    pub fn current() -> Self {
+        Self {
+            version: VERSION,
+            git_hash: GIT_HASH,
+            build_date: BUILD_DATE,
+            target: TARGET,
+            features: FEATURES
+                .split(',')
+                .filter(|feature| !feature.is_empty())
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    /// Formats like `cf-ddns-client 0.2.0 (1a2b3c4d5e6f, built 2024-11-30T12:00:00Z for x86_64-unknown-linux-gnu)`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cf-ddns-client {} (", self.version)?;
+        if let Some(hash) = self.git_hash {
+            write!(f, "{hash}, ")?;
+        }
+
+        write!(f, "built {} for {}", self.build_date, self.target)?;
+        if !self.features.is_empty() {
+            write!(f, " with {}", self.features.join(", "))?;
+        }
+
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_build_info() {
+        let info = BuildInfo {
+            version: "0.2.0",
+            git_hash: Some("1a2b3c4d5e6f"),
+            build_date: "2024-11-30T12:00:00Z",
+            target: "x86_64-unknown-linux-gnu",
+            features: Vec::new(),
+        };
+
+        assert_eq!(
+            info.to_string(),
+            "cf-ddns-client 0.2.0 (1a2b3c4d5e6f, built 2024-11-30T12:00:00Z for x86_64-unknown-linux-gnu)"
+        );
+        assert_eq!(
+            serde_json::to_value(&info).unwrap(),
+            serde_json::json!({
+                "version": "0.2.0",
+                "git_hash": "1a2b3c4d5e6f",
+                "build_date": "2024-11-30T12:00:00Z",
+                "target": "x86_64-unknown-linux-gnu",
+                "features": [],
+            })
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! A persistent cache of resolved zone and record IDs.
+//!
+//! Looking up the zone and record by name costs two Cloudflare API calls per run. The cache stores the results keyed
+//! by name so that subsequent runs can skip them, until the entries expire or the IDs turn out to be stale.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use cf_ddns_cloudflare::Record;
+use serde::{Deserialize, Serialize};
+
+/// The file name of the cache within the [cache directory](crate::paths::cache_dir).
+pub const FILE_NAME: &str = "ids.json";
+
+/// Held while a run saves its changes to the cache, for runs updating other records at the same time.
+static SAVING: Mutex<()> = Mutex::new(());
+
+/// A cached value and when it was cached.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Cached<T> {
+    value: T,
+
+    /// Seconds since the Unix epoch.
+    cached_at: u64,
+}
+
+/// The zone and record IDs resolved by previous runs.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct IdCache {
+    /// Zone IDs, keyed by zone name.
+    #[serde(default)]
+    zones: BTreeMap<String, Cached<String>>,
+
+    /// The records selected for update, keyed by zone ID, record name, and record type.
+    #[serde(default)]
+    records: BTreeMap<String, Cached<Vec<Record>>>,
+
+    #[serde(skip)]
+    ttl: Duration,
+
+    /// The names of the zones set or removed since the cache was loaded.
+    #[serde(skip)]
+    changed_zones: BTreeSet<String>,
+
+    /// The keys of the records set or removed since the cache was loaded.
+    #[serde(skip)]
+    changed_records: BTreeSet<String>,
+}
+
+impl IdCache {
+    /// Load the cache from `path`, whose entries are valid for `ttl`. A missing or unreadable cache is empty.
+    pub fn load(path: &Path, ttl: Duration) -> Self {
+        let mut cache: Self = fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+
+        cache.ttl = ttl;
+        cache
+    }
+
+    /// Save the cache to `path`, creating its parent directories if needed.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, serde_json::to_string(self)?)
+    }
+
+    /// Save the entries set or removed since the cache was loaded to `path`, keeping those saved there since by runs
+    /// updating other records at the same time.
+    pub fn save_changes(&self, path: &Path) -> io::Result<()> {
+        let _saving = SAVING.lock().unwrap_or_else(|e| e.into_inner());
+        let mut saved = Self::load(path, self.ttl);
+        saved.merge_changes(self);
+        saved.save(path)
+    }
+
+    fn merge_changes(&mut self, other: &Self) {
+        for name in &other.changed_zones {
+            match other.zones.get(name) {
+                Some(cached) => self.zones.insert(name.clone(), cached.clone()),
+                None => self.zones.remove(name),
+            };
+        }
+
+        for key in &other.changed_records {
+            match other.records.get(key) {
+                Some(cached) => self.records.insert(key.clone(), cached.clone()),
+                None => self.records.remove(key),
+            };
+        }
+    }
+
+    /// The cached ID of the zone `name`, if any.
+    pub fn zone_id(&self, name: &str) -> Option<&str> {
+        self.zones
+            .get(name)
+            .filter(|cached| self.is_fresh(cached))
+            .map(|cached| cached.value.as_str())
+    }
+
+    /// Cache the ID of the zone `name`.
+    pub fn set_zone_id(&mut self, name: &str, id: &str) {
+        self.zones.insert(name.to_string(), cached(id.to_string()));
+        self.changed_zones.insert(name.to_string());
+    }
+
+    /// Forget the ID of the zone `name` and any records cached for it.
+    pub fn remove_zone(&mut self, name: &str) {
+        if let Some(cached) = self.zones.remove(name) {
+            let prefix = format!("{} ", cached.value);
+            let removed = self.records.keys().filter(|key| key.starts_with(&prefix));
+            self.changed_records.extend(removed.cloned());
+            self.records.retain(|key, _| !key.starts_with(&prefix));
+        }
+
+        self.changed_zones.insert(name.to_string());
+    }
+
+    /// The cached records named `name` of type `record_type` in the zone `zone_id`, if any.
+    pub fn records(&self, zone_id: &str, name: &str, record_type: &str) -> Option<&[Record]> {
+        self.records
+            .get(&record_key(zone_id, name, record_type))
+            .filter(|cached| self.is_fresh(cached))
+            .map(|cached| cached.value.as_slice())
+    }
+
+    /// Cache the records named `name` of type `record_type` in the zone `zone_id`.
+    pub fn set_records(
+        &mut self,
+        zone_id: &str,
+        name: &str,
+        record_type: &str,
+        records: Vec<Record>,
+    ) {
+        let key = record_key(zone_id, name, record_type);
+        self.records.insert(key.clone(), cached(records));
+        self.changed_records.insert(key);
+    }
+
+    /// Forget the records named `name` of type `record_type` in the zone `zone_id`.
+    pub fn remove_records(&mut self, zone_id: &str, name: &str, record_type: &str) {
+        let key = record_key(zone_id, name, record_type);
+        self.records.remove(&key);
+        self.changed_records.insert(key);
+    }
+
+    fn is_fresh<T>(&self, cached: &Cached<T>) -> bool {
+        now().saturating_sub(cached.cached_at) < self.ttl.as_secs()
+    }
+}
+
+/// The default path of the cache, if a cache directory can be determined.
+pub fn default_path() -> Option<PathBuf> {
+    crate::paths::cache_dir().map(|dir| dir.join(FILE_NAME))
+}
+
+fn record_key(zone_id: &str, name: &str, record_type: &str) -> String {
+    format!("{zone_id} {name} {record_type}")
+}
+
+fn cached<T>(value: T) -> Cached<T> {
+    Cached {
+        value,
+        cached_at: now(),
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(86400);
+
+    #[test]
+    fn zone_ids_expire() {
+        let mut cache = IdCache {
+            ttl: DAY,
+            ..Default::default()
+        };
+
+        cache.set_zone_id("example.com", "z");
+        assert_eq!(cache.zone_id("example.com"), Some("z"));
+
+        cache.ttl = Duration::ZERO;
+        assert_eq!(cache.zone_id("example.com"), None);
+    }
+
+    #[test]
+    fn removing_zone_removes_its_records() {
+        let mut cache = IdCache {
+            ttl: DAY,
+            ..Default::default()
+        };
+
+        cache.set_zone_id("example.com", "z");
+        cache.set_records("z", "home.example.com", "A", Vec::new());
+        cache.set_records("y", "home.example.org", "A", Vec::new());
+        cache.remove_zone("example.com");
+        assert_eq!(cache.zone_id("example.com"), None);
+        assert!(cache.records("z", "home.example.com", "A").is_none());
+        assert!(cache.records("y", "home.example.org", "A").is_some());
+    }
+
+    #[test]
+    fn merges_only_changes() {
+        let mut saved = IdCache {
+            ttl: DAY,
+            ..Default::default()
+        };
+        saved.set_zone_id("example.com", "z");
+        saved.set_zone_id("example.org", "y");
+        let json = serde_json::to_string(&saved).unwrap();
+
+        let mut other: IdCache = serde_json::from_str(&json).unwrap();
+        other.ttl = DAY;
+        other.remove_zone("example.org");
+        other.set_records("z", "home.example.com", "A", Vec::new());
+
+        saved.set_zone_id("example.net", "x");
+        saved.merge_changes(&other);
+        assert_eq!(saved.zone_id("example.com"), Some("z"));
+        assert_eq!(saved.zone_id("example.net"), Some("x"));
+        assert_eq!(saved.zone_id("example.org"), None);
+        assert!(saved.records("z", "home.example.com", "A").is_some());
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let mut cache = IdCache::default();
+        cache.set_zone_id("example.com", "z");
+        let json = serde_json::to_string(&cache).unwrap();
+        let mut loaded: IdCache = serde_json::from_str(&json).unwrap();
+        loaded.ttl = DAY;
+        assert_eq!(loaded.zone_id("example.com"), Some("z"));
+    }
+}
@@ -0,0 +1,148 @@
+//! Coloring the outcome of an update on the terminal: green for changes, yellow for no-ops, and red for errors.
+//!
+//! Colors are used on a terminal unless the `NO_COLOR` environment variable is set, see <https://no-color.org>, or
+//! the terminal is dumb. `--color always` and `--color never` override that.
+
+use std::env;
+use std::fmt;
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static STDOUT: AtomicBool = AtomicBool::new(false);
+static STDERR: AtomicBool = AtomicBool::new(false);
+
+/// When to color the output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// When writing to a terminal, unless `NO_COLOR` is set or `TERM` is `dumb`.
+    #[default]
+    Auto,
+
+    /// Always, even when writing to a file or pipe.
+    Always,
+
+    /// Never.
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether to color a stream that is a terminal or not, given whether `NO_COLOR` is set and `TERM` is `dumb`.
+    pub fn enabled(self, is_terminal: bool, no_color: bool, dumb: bool) -> bool {
+        match self {
+            Self::Auto => is_terminal && !no_color && !dumb,
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+/// Decide whether stdout and stderr are colored, according to `choice` and the environment.
+pub fn init(choice: ColorChoice) {
+    let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    let dumb = env::var_os("TERM").is_some_and(|term| term == "dumb");
+    let enabled = |is_terminal| choice.enabled(is_terminal, no_color, dumb);
+    STDOUT.store(enabled(io::stdout().is_terminal()), Ordering::Relaxed);
+    STDERR.store(enabled(io::stderr().is_terminal()), Ordering::Relaxed);
+}
+
+/// The stream a [`Painted`] value is written to, since only one of them may be a terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stream {
+    /// Standard output.
+    Stdout,
+
+    /// Standard error.
+    Stderr,
+}
+
+impl Stream {
+    /// Whether the stream is colored, as decided by [`init`].
+    pub fn is_colored(self) -> bool {
+        match self {
+            Self::Stdout => STDOUT.load(Ordering::Relaxed),
+            Self::Stderr => STDERR.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The kind of outcome a message reports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    /// A record was changed, in green.
+    Changed,
+
+    /// Nothing needed changing, in yellow.
+    Unchanged,
+
+    /// Something failed, in red.
+    Error,
+}
+
+impl Style {
+    fn code(self) -> &'static str {
+        match self {
+            Self::Changed => "32",
+            Self::Unchanged => "33",
+            Self::Error => "31",
+        }
+    }
+}
+
+/// A value displayed in a [`Style`] if its [`Stream`] is colored.
+pub struct Painted<T> {
+    value: T,
+    style: Style,
+    colored: bool,
+}
+
+impl<T: fmt::Display> fmt::Display for Painted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.colored {
+            write!(f, "\x1b[{}m{}\x1b[0m", self.style.code(), self.value)
+        } else {
+            self.value.fmt(f)
+        }
+    }
+}
+
+/// Display `value` in `style` when written to `stream`.
+pub fn paint<T: fmt::Display>(style: Style, stream: Stream, value: T) -> Painted<T> {
+    Painted {
+        value,
+        style,
+        colored: stream.is_colored(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chooses_when_to_color() {
+        assert!(ColorChoice::Auto.enabled(true, false, false));
+        assert!(!ColorChoice::Auto.enabled(false, false, false));
+        assert!(!ColorChoice::Auto.enabled(true, true, false));
+        assert!(!ColorChoice::Auto.enabled(true, false, true));
+        assert!(ColorChoice::Always.enabled(false, true, true));
+        assert!(!ColorChoice::Never.enabled(true, false, false));
+    }
+
+    #[test]
+    fn paints_only_when_colored() {
+        let painted = |colored| Painted {
+            value: "home.example.com => 203.0.113.7",
+            style: Style::Changed,
+            colored,
+        };
+
+        assert_eq!(
+            painted(true).to_string(),
+            "\x1b[32mhome.example.com => 203.0.113.7\x1b[0m"
+        );
+        assert_eq!(
+            painted(false).to_string(),
+            "home.example.com => 203.0.113.7"
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! Compatibility with the environment variables of other dynamic DNS clients, selected with `--compat`, so that their
+//! compose files keep working when swapping the image for this client.
+//!
+//! The variables are translated into the `CF_DDNS_*` variables of the corresponding options before the command line
+//! is parsed. Variables that are already set take precedence.
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// A client whose environment variables are recognized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Compat {
+    /// The `oznu/cloudflare-ddns` Docker image: `API_KEY`, `ZONE`, `SUBDOMAIN`, `PROXIED`, `RRTYPE`, and their
+    /// `_FILE` variants.
+    Oznu,
+
+    /// The unit file of earlier versions of the Debian package: `ZONE_NAME` and `RECORD_NAME`, set by its overrides.
+    Debian,
+}
+
+/// The `CF_DDNS_*` variables translated from another client's, and the notes about those without an equivalent.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Translated {
+    /// The variables to set, with their values.
+    pub vars: Vec<(&'static str, String)>,
+
+    /// What isn't translated, and why.
+    pub notes: Vec<String>,
+}
+
+/// Errors that can occur when translating another client's variables.
+#[derive(Debug)]
+pub enum CompatError {
+    /// A `_FILE` variable names a file that could not be read. Contains the path of the file.
+    Read(PathBuf, io::Error),
+
+    /// `EMAIL` is set, so `API_KEY` is a global API key, which the client doesn't support.
+    GlobalApiKey,
+}
+
+impl fmt::Display for CompatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Read(path, err) => write!(f, "Could not read {}: {err}", path.display()),
+            Self::GlobalApiKey => write!(
+                f,
+                "EMAIL is set, so API_KEY is a global API key, which isn't supported. Create an API token with the \
+                 Zone / DNS / Edit permission, set API_KEY to it, and unset EMAIL."
+            ),
+        }
+    }
+}
+
+impl error::Error for CompatError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Read(_, err) => Some(err),
+            Self::GlobalApiKey => None,
+        }
+    }
+}
+
+impl Compat {
+    /// Translate the variables looked up with `var`.
+    pub fn translate(
+        self,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Translated, CompatError> {
+        match self {
+            Self::Oznu => oznu(var),
+            Self::Debian => Ok(debian(var)),
+        }
+    }
+}
+
+/// Translate the variables that overrides of the earlier unit file passed to `--zone-name` and `--record-name`.
+fn debian(var: impl Fn(&str) -> Option<String>) -> Translated {
+    let vars = [
+        ("ZONE_NAME", "CF_DDNS_ZONE_NAME"),
+        ("RECORD_NAME", "CF_DDNS_RECORD_NAME"),
+    ];
+
+    Translated {
+        vars: vars
+            .into_iter()
+            .filter_map(|(old, new)| {
+                var(old)
+                    .filter(|value| !value.is_empty())
+                    .map(|value| (new, value))
+            })
+            .collect(),
+        notes: Vec::new(),
+    }
+}
+
+/// Translate the variables of `oznu/cloudflare-ddns`, see <https://github.com/oznu/docker-cloudflare-ddns>.
+fn oznu(var: impl Fn(&str) -> Option<String>) -> Result<Translated, CompatError> {
+    let var = |name: &str| var(name).filter(|value| !value.is_empty());
+    let from_file = |name: &str| -> Result<Option<String>, CompatError> {
+        match var(&format!("{name}_FILE")) {
+            Some(path) => fs::read_to_string(&path)
+                .map(|value| Some(value.trim().to_string()))
+                .map_err(|err| CompatError::Read(path.into(), err)),
+            None => Ok(var(name)),
+        }
+    };
+
+    if var("EMAIL").is_some() {
+        return Err(CompatError::GlobalApiKey);
+    }
+
+    let mut translated = Translated::default();
+    match (var("API_KEY_FILE"), var("API_KEY")) {
+        (Some(path), _) => translated.vars.push(("CF_DDNS_TOKEN_FILE", path)),
+        (None, Some(token)) => translated.vars.push(("CF_DDNS_TOKEN", token)),
+        (None, None) => {}
+    }
+
+    if let Some(zone) = from_file("ZONE")? {
+        translated.vars.push(("CF_DDNS_ZONE_NAME", zone));
+        let record = from_file("SUBDOMAIN")?.unwrap_or_else(|| crate::records::APEX.to_string());
+        translated.vars.push(("CF_DDNS_RECORD_NAME", record));
+    }
+
+    let ipv6 = var("RRTYPE").is_some_and(|rrtype| rrtype.eq_ignore_ascii_case("AAAA"));
+    if ipv6 {
+        translated
+            .vars
+            .push(("CF_DDNS_RESOLVE_FAMILY", "6".to_string()));
+    }
+
+    match (var("CUSTOM_LOOKUP_CMD"), var("INTERFACE")) {
+        (Some(command), _) => translated
+            .vars
+            .push(("CF_DDNS_SOURCE", format!("exec:{command}"))),
+        (None, Some(interface)) if ipv6 => translated
+            .vars
+            .push(("CF_DDNS_SOURCE", format!("interface:{interface}"))),
+        (None, Some(_)) => translated.notes.push(
+            "INTERFACE is ignored, since only the IPv6 address of an interface can be read."
+                .to_string(),
+        ),
+        (None, None) => {}
+    }
+
+    if var("PROXIED").is_some() {
+        translated.notes.push(
+            "PROXIED is ignored: the record keeps whether it's proxied, so set that in the Cloudflare dashboard."
+                .to_string(),
+        );
+    }
+
+    if var("DELETE_ON_STOP").is_some_and(|value| value.eq_ignore_ascii_case("true")) {
+        translated
+            .notes
+            .push("DELETE_ON_STOP is ignored: the record is never deleted.".to_string());
+    }
+
+    if var("DNS_SERVER").is_some() {
+        translated.notes.push(
+            "DNS_SERVER is ignored: the address is discovered with --source, worker by default."
+                .to_string(),
+        );
+    }
+
+    Ok(translated)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn translate(vars: &[(&str, &str)]) -> Result<Translated, CompatError> {
+        let vars: BTreeMap<_, _> = vars.iter().copied().collect();
+        Compat::Oznu.translate(|name| vars.get(name).map(|value| value.to_string()))
+    }
+
+    #[test]
+    fn translates_oznu() {
+        let translated = translate(&[
+            ("API_KEY", "secret"),
+            ("ZONE", "example.com"),
+            ("SUBDOMAIN", "home"),
+            ("PROXIED", "true"),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            translated.vars,
+            [
+                ("CF_DDNS_TOKEN", "secret".to_string()),
+                ("CF_DDNS_ZONE_NAME", "example.com".to_string()),
+                ("CF_DDNS_RECORD_NAME", "home".to_string()),
+            ]
+        );
+        assert_eq!(translated.notes.len(), 1);
+
+        let translated = translate(&[
+            ("API_KEY_FILE", "/run/secrets/token"),
+            ("ZONE", "example.com"),
+            ("SUBDOMAIN", ""),
+            ("RRTYPE", "AAAA"),
+            ("INTERFACE", "eth0"),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            translated.vars,
+            [
+                ("CF_DDNS_TOKEN_FILE", "/run/secrets/token".to_string()),
+                ("CF_DDNS_ZONE_NAME", "example.com".to_string()),
+                ("CF_DDNS_RECORD_NAME", "@".to_string()),
+                ("CF_DDNS_RESOLVE_FAMILY", "6".to_string()),
+                ("CF_DDNS_SOURCE", "interface:eth0".to_string()),
+            ]
+        );
+        assert!(translated.notes.is_empty());
+
+        assert!(matches!(
+            translate(&[("API_KEY", "key"), ("EMAIL", "me@example.com")]),
+            Err(CompatError::GlobalApiKey)
+        ));
+    }
+
+    #[test]
+    fn translates_debian() {
+        let vars: BTreeMap<_, _> = [("ZONE_NAME", "example.com"), ("RECORD_NAME", "home")].into();
+        let translated = Compat::Debian
+            .translate(|name| vars.get(name).map(|value| value.to_string()))
+            .unwrap();
+
+        assert_eq!(
+            translated.vars,
+            [
+                ("CF_DDNS_ZONE_NAME", "example.com".to_string()),
+                ("CF_DDNS_RECORD_NAME", "home".to_string()),
+            ]
+        );
+        assert!(translated.notes.is_empty());
+
+        let translated = Compat::Debian.translate(|_| None).unwrap();
+        assert!(translated.vars.is_empty());
+    }
+}
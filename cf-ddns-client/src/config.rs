@@ -0,0 +1,1214 @@
+//! A configuration file describing any number of zones and records to keep up to date in one run.
+//!
+//! The file is TOML, or YAML if its extension is `.yaml` or `.yml`. Keys are named like the command line options
+//! they correspond to, e.g. `token-file` or `ipv6-suffix`:
+//!
+//! ```toml
+//! token-file = "/etc/cf-ddns/token"
+//!
+//! [[zones]]
+//! name = "example.com"
+//!
+//! [[zones.records]]
+//! name = "home"
+//!
+//! [[zones.records]]
+//! name = "nas"
+//! ipv6-suffix = "::1:2:3:4"
+//! ```
+//!
+//! Any other command line option can be given in the `[options]` table, where it applies to every record unless
+//! overridden on the command line or by an environment variable:
+//!
+//! ```toml
+//! [options]
+//! source = ["upnp", "worker"]
+//! min-interval = "5m"
+//! ```
+//!
+//! Zones managed with different accounts or tokens can be grouped into named [profiles](Profile), of which one is
+//! selected per run:
+//!
+//! ```toml
+//! [profiles.work]
+//! token-file = "/etc/cf-ddns/work-token"
+//! zones = [{ name = "example.org", records = [{ name = "vpn" }] }]
+//! ```
+//!
+//! A zone can also have a token of its own, so that one run updates the zones of several accounts:
+//!
+//! ```toml
+//! [[zones]]
+//! name = "example.net"
+//! token-file = "/etc/cf-ddns/other-token"
+//! records = [{ name = "home" }]
+//! ```
+
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::net::{IpAddr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+
+use cf_ddns_cloudflare::record::Record;
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::records::{self, Discover, OnMultiple, APEX};
+use crate::source::{Family, SourceKind};
+use crate::token::TokenSource;
+
+/// The file name of the configuration within the [config directory](crate::paths::config_dir).
+pub const FILE_NAME: &str = "config.toml";
+
+/// The zones and records to update, and the settings shared by all of them.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// The API token, used unless one is given on the command line.
+    pub token: Option<String>,
+
+    /// A file containing the API token, used unless one is given on the command line.
+    pub token_file: Option<PathBuf>,
+
+    /// A command printing the API token, used unless one is given on the command line.
+    pub token_command: Option<String>,
+
+    /// A field of a secret in HashiCorp Vault holding the API token, like `secret/data/cloudflare#token`, used
+    /// unless one is given on the command line.
+    #[cfg(feature = "vault")]
+    pub token_vault: Option<String>,
+
+    /// The zones to update.
+    #[serde(default)]
+    pub zones: Vec<ZoneConfig>,
+
+    /// The profile used when none is selected, instead of the zones above.
+    pub default_profile: Option<String>,
+
+    /// Named sets of zones, each with its own token, of which one is selected per run.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+
+    /// Command line options, keyed by their long name without the leading `--`, e.g. `min-interval`.
+    #[serde(default)]
+    pub options: BTreeMap<String, OptionValue>,
+}
+
+/// The value of an [option](Config::options), written as on the command line.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum OptionValue {
+    /// A single value.
+    One(Scalar),
+
+    /// Several values, as for options that can be given several times.
+    Many(Vec<Scalar>),
+}
+
+impl OptionValue {
+    /// The values as they would be written on the command line.
+    pub fn values(&self) -> Vec<String> {
+        match self {
+            Self::One(value) => vec![value.to_string()],
+            Self::Many(values) => values.iter().map(Scalar::to_string).collect(),
+        }
+    }
+}
+
+/// A single value of an [option](Config::options).
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Scalar {
+    /// A flag's value.
+    Bool(bool),
+
+    /// A whole number.
+    Integer(i64),
+
+    /// A fractional number.
+    Float(f64),
+
+    /// Any other value.
+    String(String),
+}
+
+impl fmt::Display for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Integer(value) => write!(f, "{value}"),
+            Self::Float(value) => write!(f, "{value}"),
+            Self::String(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// A named set of zones to update, e.g. those of one Cloudflare account.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Profile {
+    /// The API token, used unless one is given on the command line. Defaults to the token outside the profiles.
+    pub token: Option<String>,
+
+    /// A file containing the API token, used unless one is given on the command line. Defaults to the token file
+    /// outside the profiles.
+    pub token_file: Option<PathBuf>,
+
+    /// A command printing the API token, used unless one is given on the command line. Defaults to the token command
+    /// outside the profiles.
+    pub token_command: Option<String>,
+
+    /// A field of a secret in HashiCorp Vault holding the API token, used unless one is given on the command line.
+    /// Defaults to the one outside the profiles.
+    #[cfg(feature = "vault")]
+    pub token_vault: Option<String>,
+
+    /// The zones to update.
+    #[serde(default)]
+    pub zones: Vec<ZoneConfig>,
+}
+
+/// A zone and the records to update in it.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ZoneConfig {
+    /// The name of the zone.
+    pub name: String,
+
+    /// The ID of the zone, if known, to avoid looking it up by name.
+    pub id: Option<String>,
+
+    /// The API token of the zone, for a zone of another account. Defaults to the token of the profile.
+    pub token: Option<String>,
+
+    /// A file containing the API token of the zone. Defaults to the token of the profile.
+    pub token_file: Option<PathBuf>,
+
+    /// A command printing the API token of the zone. Defaults to the token of the profile.
+    pub token_command: Option<String>,
+
+    /// A field of a secret in HashiCorp Vault holding the API token of the zone. Defaults to the token of the
+    /// profile.
+    #[cfg(feature = "vault")]
+    pub token_vault: Option<String>,
+
+    /// The records to update.
+    #[serde(default)]
+    pub records: Vec<RecordConfig>,
+}
+
+/// A record, or a set of discovered records, to update, with the settings overriding those of the command line.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RecordConfig {
+    /// The name of the record, relative to the zone.
+    pub name: Option<String>,
+
+    /// The ID of the record, instead of looking it up from the name.
+    pub id: Option<String>,
+
+    /// Update every `A` or `AAAA` record carrying this tag instead of naming a record.
+    pub discover_tag: Option<String>,
+
+    /// Update every `A` or `AAAA` record whose comment contains this text instead of naming a record.
+    pub discover_comment: Option<String>,
+
+    /// Publish these addresses instead of discovering them.
+    #[serde(default)]
+    pub ip: Vec<IpAddr>,
+
+    /// Where to get the IP address from, written as on the command line.
+    pub source: Option<Vec<SourceKind>>,
+
+    /// Contact worker and `url:` sources only over this family.
+    pub resolve_family: Option<Family>,
+
+    /// The host suffix combined with the discovered IPv6 prefix.
+    pub ipv6_suffix: Option<Ipv6Addr>,
+
+    /// The length of the prefix kept from the discovered IPv6 address.
+    pub ipv6_prefix_length: Option<u8>,
+
+    /// Publish addresses that aren't reachable from the internet.
+    pub allow_non_global: Option<bool>,
+
+    /// What to do when several records of the address's type share the name.
+    pub on_multiple: Option<OnMultiple>,
+
+    /// Refuse to modify the record unless it's marked as managed by cf-ddns.
+    pub require_managed: Option<bool>,
+
+    /// Mark the record as managed by cf-ddns when updating it.
+    pub adopt: Option<bool>,
+
+    /// Make the records of the name have exactly the discovered addresses.
+    pub reconcile: Option<bool>,
+
+    /// A command to run when the record is updated.
+    pub on_change: Option<String>,
+
+    /// A command to run when updating the record fails.
+    pub on_failure: Option<String>,
+
+    /// A command to run before updating the record, which isn't updated if it fails.
+    pub pre_update: Option<String>,
+}
+
+impl RecordConfig {
+    /// The settings the record overrides, keyed by the name of their command line option, with their values as
+    /// they would be written on the command line.
+    pub fn settings(&self) -> Vec<(&'static str, String)> {
+        let join = |values: Vec<String>| values.join(",");
+        [
+            (
+                "ip",
+                (!self.ip.is_empty())
+                    .then(|| join(self.ip.iter().map(IpAddr::to_string).collect())),
+            ),
+            (
+                "source",
+                self.source
+                    .as_ref()
+                    .map(|source| join(source.iter().map(SourceKind::to_string).collect())),
+            ),
+            (
+                "resolve-family",
+                self.resolve_family.map(|family| match family {
+                    Family::V4 => "4".to_string(),
+                    Family::V6 => "6".to_string(),
+                }),
+            ),
+            (
+                "ipv6-suffix",
+                self.ipv6_suffix.map(|suffix| suffix.to_string()),
+            ),
+            (
+                "ipv6-prefix-length",
+                self.ipv6_prefix_length.map(|length| length.to_string()),
+            ),
+            (
+                "allow-non-global",
+                self.allow_non_global.map(|allow| allow.to_string()),
+            ),
+            (
+                "on-multiple",
+                self.on_multiple
+                    .and_then(|on_multiple| on_multiple.to_possible_value())
+                    .map(|value| value.get_name().to_string()),
+            ),
+            (
+                "require-managed",
+                self.require_managed.map(|require| require.to_string()),
+            ),
+            ("adopt", self.adopt.map(|adopt| adopt.to_string())),
+            (
+                "reconcile",
+                self.reconcile.map(|reconcile| reconcile.to_string()),
+            ),
+            ("on-change", self.on_change.clone()),
+            ("on-failure", self.on_failure.clone()),
+            ("pre-update", self.pre_update.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(option, value)| value.map(|value| (option, value)))
+        .collect()
+    }
+
+    /// Whether `record` of the zone `zone_name` is one this describes, by its name, its ID, or discovery.
+    pub fn declares(&self, record: &Record, zone_name: &str) -> bool {
+        let discover = [
+            self.discover_tag.clone().map(Discover::Tag),
+            self.discover_comment.clone().map(Discover::Comment),
+        ];
+
+        self.name
+            .as_ref()
+            .is_some_and(|name| records::fqdn(name, zone_name) == record.name)
+            || self.id.as_ref() == Some(&record.id)
+            || discover
+                .iter()
+                .flatten()
+                .any(|discover| discover.matches(record))
+    }
+
+    /// A description of the record for messages, like the record name on the command line.
+    pub fn describe(&self) -> String {
+        match (
+            &self.name,
+            &self.id,
+            &self.discover_tag,
+            &self.discover_comment,
+        ) {
+            (Some(name), _, _, _) => name.clone(),
+            (None, Some(id), _, _) => format!("id:{id}"),
+            (None, None, Some(tag), _) => format!("tag:{tag}"),
+            (None, None, None, Some(comment)) => format!("comment:{comment}"),
+            (None, None, None, None) => String::new(),
+        }
+    }
+}
+
+/// Errors that can occur when loading a configuration file.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file could not be read.
+    Read(io::Error),
+
+    /// The file is not valid TOML, or doesn't describe a configuration.
+    Toml(toml::de::Error),
+
+    /// The file is not valid YAML, or doesn't describe a configuration.
+    Yaml(serde_yaml::Error),
+
+    /// The configuration is well-formed but inconsistent. Contains a description of the problem.
+    Invalid(String),
+
+    /// The selected profile doesn't exist. Contains its name and the names of the profiles that do.
+    UnknownProfile(String, Vec<String>),
+
+    /// The file is encrypted, and could not be decrypted.
+    #[cfg(feature = "encrypted-config")]
+    Decrypt(crate::encrypted::DecryptError),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Read(e) => write!(f, "Failed to read the configuration: {e}."),
+            Self::Toml(e) => write!(f, "Invalid TOML configuration: {e}"),
+            Self::Yaml(e) => write!(f, "Invalid YAML configuration: {e}."),
+            Self::Invalid(problem) => write!(f, "Invalid configuration: {problem}."),
+            Self::UnknownProfile(name, profiles) if profiles.is_empty() => {
+                write!(f, "There is no profile {name}, the configuration has none.")
+            }
+            Self::UnknownProfile(name, profiles) => write!(
+                f,
+                "There is no profile {name}, expected one of {}.",
+                profiles.join(", ")
+            ),
+            #[cfg(feature = "encrypted-config")]
+            Self::Decrypt(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Read(e) => Some(e),
+            Self::Toml(e) => Some(e),
+            Self::Yaml(e) => Some(e),
+            Self::Invalid(_) | Self::UnknownProfile(..) => None,
+            #[cfg(feature = "encrypted-config")]
+            Self::Decrypt(e) => Some(e),
+        }
+    }
+}
+
+impl Config {
+    /// Load and validate the configuration at `path`, which is YAML if its extension is `.yaml` or `.yml` and TOML
+    /// otherwise. With the `encrypted-config` feature, a file encrypted with age or sops is decrypted first, and a
+    /// `.age` extension is ignored.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let (text, yaml) = read(path)?;
+
+        let config = if yaml {
+            Self::from_yaml(&text)?
+        } else {
+            Self::from_toml(&text)?
+        };
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parse a TOML configuration, without validating it.
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        toml::from_str(text).map_err(ConfigError::Toml)
+    }
+
+    /// Parse a YAML configuration, without validating it.
+    pub fn from_yaml(text: &str) -> Result<Self, ConfigError> {
+        serde_yaml::from_str(text).map_err(ConfigError::Yaml)
+    }
+
+    /// The profile named `name`, or the default profile if `None`, or else the zones outside the profiles.
+    ///
+    /// A profile without a token of its own uses the one outside the profiles.
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile, ConfigError> {
+        let Some(name) = name.or(self.default_profile.as_deref()) else {
+            if self.zones.is_empty() && !self.profiles.is_empty() {
+                return Err(ConfigError::Invalid(format!(
+                    "there are no zones outside the profiles, select one of {} with --profile",
+                    self.profile_names().join(", ")
+                )));
+            }
+
+            return Ok(self.unnamed());
+        };
+
+        let Some(profile) = self.profiles.get(name) else {
+            return Err(ConfigError::UnknownProfile(
+                name.to_string(),
+                self.profile_names(),
+            ));
+        };
+
+        let mut profile = profile.clone();
+        if profile.token_source().is_none() {
+            profile.token = self.token.clone();
+            profile.token_file = self.token_file.clone();
+            profile.token_command = self.token_command.clone();
+            #[cfg(feature = "vault")]
+            {
+                profile.token_vault = self.token_vault.clone();
+            }
+        }
+
+        Ok(profile)
+    }
+
+    /// Check that the zones outside the profiles and each profile are [valid](Profile::validate), and that the
+    /// default profile exists.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !self.zones.is_empty() || self.profiles.is_empty() {
+            self.unnamed().validate().map_err(ConfigError::Invalid)?;
+        } else {
+            check_token(&self.unnamed()).map_err(ConfigError::Invalid)?;
+        }
+
+        for (name, profile) in &self.profiles {
+            profile
+                .validate()
+                .map_err(|problem| ConfigError::Invalid(format!("profile {name}: {problem}")))?;
+        }
+
+        match &self.default_profile {
+            Some(name) if !self.profiles.contains_key(name) => Err(ConfigError::UnknownProfile(
+                name.clone(),
+                self.profile_names(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Every profile with its name, as [selected](Self::profile), led by the zones outside the profiles, unnamed, if
+    /// there are any.
+    pub fn all_profiles(&self) -> Vec<(Option<&str>, Profile)> {
+        let unnamed = (!self.zones.is_empty()).then(|| (None, self.unnamed()));
+        let named = self.profiles.keys().filter_map(|name| {
+            self.profile(Some(name))
+                .ok()
+                .map(|profile| (Some(name.as_str()), profile))
+        });
+
+        unnamed.into_iter().chain(named).collect()
+    }
+
+    fn unnamed(&self) -> Profile {
+        Profile {
+            token: self.token.clone(),
+            token_file: self.token_file.clone(),
+            token_command: self.token_command.clone(),
+            #[cfg(feature = "vault")]
+            token_vault: self.token_vault.clone(),
+            zones: self.zones.clone(),
+        }
+    }
+
+    fn profile_names(&self) -> Vec<String> {
+        self.profiles.keys().cloned().collect()
+    }
+}
+
+impl Profile {
+    /// Where the profile's API token is kept, if it has one.
+    pub fn token_source(&self) -> Option<TokenSource> {
+        match (&self.token, &self.token_file, &self.token_command) {
+            (Some(token), _, _) => Some(TokenSource::Literal(token.clone())),
+            (None, Some(path), _) => Some(TokenSource::File(path.clone())),
+            (None, None, Some(command)) => Some(TokenSource::Command(command.clone())),
+            #[cfg(feature = "vault")]
+            (None, None, None) => self
+                .token_vault
+                .as_deref()
+                .map(|path| TokenSource::Vault(path.into())),
+            #[cfg(not(feature = "vault"))]
+            (None, None, None) => None,
+        }
+    }
+
+    /// Whether `record` of the zone `zone_name` is [declared](RecordConfig::declares) by any record of that zone.
+    pub fn declares(&self, record: &Record, zone_name: &str) -> bool {
+        let zone_name = records::normalize(zone_name);
+        self.zones
+            .iter()
+            .filter(|zone| records::normalize(&zone.name) == zone_name)
+            .flat_map(|zone| &zone.records)
+            .any(|declared| declared.declares(record, &zone_name))
+    }
+
+    /// Check that the profile describes at least one record, and that each record is identified in exactly one way,
+    /// as the command line requires. Returns a description of the first problem.
+    pub fn validate(&self) -> Result<(), String> {
+        check_token(self)?;
+
+        if self.zones.iter().all(|zone| zone.records.is_empty()) {
+            return Err("no records to update".to_string());
+        }
+
+        for zone in &self.zones {
+            check_name(&zone.name).map_err(|problem| format!("zone {}: {problem}", zone.name))?;
+            if let Some(token) = zone.token_profile() {
+                check_token(&token).map_err(|problem| format!("zone {}: {problem}", zone.name))?;
+            }
+
+            for (i, record) in zone.records.iter().enumerate() {
+                let named = record.name.is_some() || record.id.is_some();
+                let discovered = [&record.discover_tag, &record.discover_comment]
+                    .iter()
+                    .filter(|discover| discover.is_some())
+                    .count();
+
+                let problem = match (named, discovered) {
+                    (false, 0) => "has no name, id, discover-tag, or discover-comment".to_string(),
+                    (true, 1..) | (false, 2..) => {
+                        "may only have one of a name or id, discover-tag, and discover-comment"
+                            .to_string()
+                    }
+                    (false, 1) if record.reconcile == Some(true) => {
+                        "can't reconcile discovered records".to_string()
+                    }
+                    _ if record.reconcile == Some(true) && record.name.is_none() => {
+                        "needs a name to reconcile".to_string()
+                    }
+                    _ => match check_record(record) {
+                        Ok(()) => continue,
+                        Err(problem) => problem,
+                    },
+                };
+
+                return Err(format!("record {} of zone {} {problem}", i + 1, zone.name));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ZoneConfig {
+    /// The zone's own token, as a profile without zones, if it has one.
+    pub fn token_profile(&self) -> Option<Profile> {
+        let profile = Profile {
+            token: self.token.clone(),
+            token_file: self.token_file.clone(),
+            token_command: self.token_command.clone(),
+            #[cfg(feature = "vault")]
+            token_vault: self.token_vault.clone(),
+            zones: Vec::new(),
+        };
+
+        profile.token_source().map(|_| profile)
+    }
+}
+
+/// The text of the configuration at `path`, and whether it's YAML.
+#[cfg(not(feature = "encrypted-config"))]
+fn read(path: &Path) -> Result<(String, bool), ConfigError> {
+    let text = fs::read_to_string(path).map_err(ConfigError::Read)?;
+    Ok((text, is_yaml(path)))
+}
+
+/// The text of the configuration at `path`, decrypted if it's encrypted, and whether it's YAML.
+#[cfg(feature = "encrypted-config")]
+fn read(path: &Path) -> Result<(String, bool), ConfigError> {
+    use crate::encrypted;
+
+    let contents = fs::read(path).map_err(ConfigError::Read)?;
+    if !encrypted::is_encrypted(&contents) {
+        let text = String::from_utf8(contents)
+            .map_err(|e| ConfigError::Read(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+        return Ok((text, is_yaml(path)));
+    }
+
+    let decrypted = encrypted::decrypt(&contents).map_err(ConfigError::Decrypt)?;
+    // `config.yaml.age` is YAML.
+    let yaml = decrypted.yaml || is_yaml(path) || is_yaml(&path.with_extension(""));
+    Ok((decrypted.text, yaml))
+}
+
+fn is_yaml(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "yaml" || extension == "yml")
+}
+
+/// Check that at most one of the token, token file, token command, and Vault secret of `profile` is given.
+fn check_token(profile: &Profile) -> Result<(), String> {
+    let given = u8::from(profile.token.is_some())
+        + u8::from(profile.token_file.is_some())
+        + u8::from(profile.token_command.is_some());
+    #[cfg(feature = "vault")]
+    let given = given + u8::from(profile.token_vault.is_some());
+
+    match given {
+        0 | 1 => Ok(()),
+        _ if cfg!(feature = "vault") => Err(
+            "only one of token, token-file, token-command, and token-vault may be given"
+                .to_string(),
+        ),
+        _ => Err("only one of token, token-file, and token-command may be given".to_string()),
+    }
+}
+
+/// Check a record's name and addresses, returning a description of the first problem.
+fn check_record(record: &RecordConfig) -> Result<(), String> {
+    if let Some(name) = record.name.as_deref().filter(|&name| name != APEX) {
+        check_name(&records::normalize(name))
+            .map_err(|problem| format!("has an invalid name: {problem}"))?;
+    }
+
+    for &ip in &record.ip {
+        crate::ip::validate(ip)
+            .map_err(|_| format!("has the unspecified, loopback, or multicast address {ip}"))?;
+    }
+
+    Ok(())
+}
+
+/// Check that `name` is a valid DNS name, returning a description of the problem if not.
+///
+/// Names are dot-separated labels of up to 63 letters, digits, hyphens, and underscores, not starting or ending with
+/// a hyphen, or `*` for the first label of a wildcard.
+fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("the name is empty".to_string());
+    }
+
+    if name.len() > 253 {
+        return Err(format!("{name} is longer than 253 characters"));
+    }
+
+    for (i, label) in name.split('.').enumerate() {
+        let valid = match label {
+            "" => return Err(format!("{name} has an empty label")),
+            "*" => i == 0,
+            _ if label.len() > 63 => return Err(format!("{label} is longer than 63 characters")),
+            _ => {
+                !label.starts_with('-')
+                    && !label.ends_with('-')
+                    && label
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            }
+        };
+
+        if !valid {
+            return Err(format!("{label} is not a valid label"));
+        }
+    }
+
+    Ok(())
+}
+
+/// The values filled into a [starter configuration](Starter::render). Anything not given is left as a commented
+/// example.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Starter {
+    /// The name of the zone.
+    pub zone_name: Option<String>,
+
+    /// The names of the records in the zone.
+    pub record_names: Vec<String>,
+
+    /// The API token.
+    pub token: Option<String>,
+
+    /// A file containing the API token.
+    pub token_file: Option<PathBuf>,
+
+    /// Where to get the IP address from.
+    pub source: Vec<SourceKind>,
+}
+
+impl Starter {
+    /// A commented TOML configuration explaining the common settings, with the given values filled in.
+    pub fn render(&self) -> String {
+        let quote = |value: &str| toml::Value::String(value.to_string()).to_string();
+        let mut text = String::from(
+            "# The cf-ddns configuration. Each option takes its value from the command line, else its CF_DDNS_*\n\
+             # environment variable, else this file, else its default.\n\n\
+             # The API token, or a file containing it. Give only one.\n",
+        );
+
+        match (&self.token, &self.token_file) {
+            (Some(token), _) => text += &format!("token = {}\n", quote(token)),
+            (None, Some(path)) => {
+                text += &format!("token-file = {}\n", quote(&path.display().to_string()))
+            }
+            (None, None) => text += "# token-file = \"/etc/cf-ddns/token\"\n",
+        }
+
+        text += "\n# Options applying to every record, named like the command line options without the leading --.\n\
+                 [options]\n";
+        match self.source.as_slice() {
+            [] => text += "# source = [\"worker\"]\n",
+            source => {
+                let source: Vec<String> =
+                    source.iter().map(|kind| quote(&kind.to_string())).collect();
+                text += &format!("source = [{}]\n", source.join(", "));
+            }
+        }
+
+        text += "# min-interval = \"5m\"\n\n\
+                 # The zones and the records to update in each. Records can override any of ip, source, resolve-family,\n\
+                 # ipv6-suffix, ipv6-prefix-length, allow-non-global, on-multiple, require-managed, adopt, reconcile,\n\
+                 # on-change, on-failure, and pre-update.\n";
+        let records = match self.record_names.as_slice() {
+            [] => vec![None],
+            names => names.iter().map(Some).collect(),
+        };
+
+        let comment = match &self.zone_name {
+            Some(_) => "",
+            None => "# ",
+        };
+
+        let zone = self.zone_name.as_deref().unwrap_or("example.com");
+        text += &format!("{comment}[[zones]]\n{comment}name = {}\n", quote(zone));
+        for name in records {
+            let (comment, name) = match name {
+                Some(name) if self.zone_name.is_some() => ("", name.as_str()),
+                Some(name) => ("# ", name.as_str()),
+                None => ("# ", "home"),
+            };
+
+            text += &format!(
+                "\n{comment}[[zones.records]]\n{comment}name = {}\n",
+                quote(name)
+            );
+        }
+
+        text += "# ipv6-suffix = \"::1:2:3:4\"\n";
+        text
+    }
+}
+
+/// The default path of the configuration, if a config directory can be determined.
+pub fn default_path() -> Option<PathBuf> {
+    crate::paths::config_dir().map(|dir| dir.join(FILE_NAME))
+}
+
+/// Write `contents` to a new file at `path` that only the user may read, creating its directory, or replace the
+/// file if `force` is set.
+pub fn write_private(path: &Path, contents: &str, force: bool) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true);
+    if force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use cf_ddns_cloudflare::RecordContent;
+
+    use super::*;
+
+    #[test]
+    fn parses_toml() {
+        let config = Config::from_toml(
+            r#"
+            token-file = "/etc/cf-ddns/token"
+
+            [[zones]]
+            name = "example.com"
+
+            [[zones.records]]
+            name = "home"
+            source = ["upnp", "url:https://icanhazip.com"]
+            resolve-family = 4
+
+            [[zones.records]]
+            name = "nas"
+            ipv6-suffix = "::1:2:3:4"
+            on-multiple = "all"
+
+            [[zones]]
+            name = "example.org"
+            id = "abc"
+            records = [{ discover-tag = "ddns", ip = ["203.0.113.7"] }]
+            "#,
+        )
+        .unwrap();
+
+        assert!(config.validate().is_ok());
+        assert_eq!(config.token_file, Some(PathBuf::from("/etc/cf-ddns/token")));
+        assert_eq!(config.zones.len(), 2);
+
+        let home = &config.zones[0].records[0];
+        assert_eq!(
+            home.source,
+            Some(vec![
+                SourceKind::Upnp,
+                SourceKind::Url("https://icanhazip.com".to_string())
+            ])
+        );
+        assert_eq!(home.resolve_family, Some(Family::V4));
+
+        let nas = &config.zones[0].records[1];
+        assert_eq!(nas.ipv6_suffix, Some(Ipv6Addr::new(0, 0, 0, 0, 1, 2, 3, 4)));
+        assert_eq!(nas.on_multiple, Some(OnMultiple::All));
+
+        let discovered = &config.zones[1].records[0];
+        assert_eq!(config.zones[1].id.as_deref(), Some("abc"));
+        assert_eq!(discovered.describe(), "tag:ddns");
+        assert_eq!(discovered.ip, [IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7))]);
+    }
+
+    #[test]
+    fn parses_yaml() {
+        let config = Config::from_yaml(
+            r#"
+            token: secret
+            zones:
+              - name: example.com
+                records:
+                  - name: home
+                    resolve-family: "6"
+                    reconcile: true
+            "#,
+        )
+        .unwrap();
+
+        assert!(config.validate().is_ok());
+        assert_eq!(config.token.as_deref(), Some("secret"));
+        assert_eq!(config.zones[0].records[0].resolve_family, Some(Family::V6));
+        assert_eq!(config.zones[0].records[0].reconcile, Some(true));
+    }
+
+    #[test]
+    fn parses_options() {
+        let config = Config::from_toml(
+            r#"
+            [options]
+            source = ["upnp", "worker"]
+            min-interval = "5m"
+            retries = 3
+            dry-run = true
+            "#,
+        )
+        .unwrap();
+
+        let values = |option: &str| config.options[option].values();
+        assert_eq!(values("source"), ["upnp", "worker"]);
+        assert_eq!(values("min-interval"), ["5m"]);
+        assert_eq!(values("retries"), ["3"]);
+        assert_eq!(values("dry-run"), ["true"]);
+
+        let record = RecordConfig {
+            name: Some("home".to_string()),
+            source: Some(vec![SourceKind::Upnp, SourceKind::Worker]),
+            resolve_family: Some(Family::V6),
+            on_multiple: Some(OnMultiple::All),
+            ..RecordConfig::default()
+        };
+        assert_eq!(
+            record.settings(),
+            [
+                ("source", "upnp,worker".to_string()),
+                ("resolve-family", "6".to_string()),
+                ("on-multiple", "all".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_starters() {
+        let empty = Config::from_toml(&Starter::default().render()).unwrap();
+        assert_eq!(empty, Config::default());
+
+        let starter = Starter {
+            zone_name: Some("example.com".to_string()),
+            record_names: vec!["home".to_string(), "nas".to_string()],
+            token_file: Some(PathBuf::from("/etc/cf-ddns/token")),
+            source: vec![SourceKind::Upnp, SourceKind::Worker],
+            ..Starter::default()
+        };
+
+        let config = Config::from_toml(&starter.render()).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.token_file, starter.token_file);
+        assert_eq!(config.zones[0].name, "example.com");
+        let names: Vec<_> = config.zones[0]
+            .records
+            .iter()
+            .map(RecordConfig::describe)
+            .collect();
+        assert_eq!(names, ["home", "nas"]);
+        assert_eq!(config.options["source"].values(), ["upnp", "worker"]);
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(matches!(
+            Config::from_toml("[[zones]]\nname = \"example.com\"\nrecrods = []\n"),
+            Err(ConfigError::Toml(_))
+        ));
+    }
+
+    #[test]
+    fn validates_records() {
+        let zone = |records| Config {
+            zones: vec![ZoneConfig {
+                name: "example.com".to_string(),
+                records,
+                ..ZoneConfig::default()
+            }],
+            ..Config::default()
+        };
+
+        assert!(matches!(
+            zone(vec![]).validate(),
+            Err(ConfigError::Invalid(_))
+        ));
+        assert!(matches!(
+            zone(vec![RecordConfig::default()]).validate(),
+            Err(ConfigError::Invalid(_))
+        ));
+
+        let both = RecordConfig {
+            name: Some("home".to_string()),
+            discover_tag: Some("ddns".to_string()),
+            ..RecordConfig::default()
+        };
+        assert!(matches!(
+            zone(vec![both]).validate(),
+            Err(ConfigError::Invalid(_))
+        ));
+
+        let reconcile = RecordConfig {
+            id: Some("abc".to_string()),
+            reconcile: Some(true),
+            ..RecordConfig::default()
+        };
+        assert!(matches!(
+            zone(vec![reconcile]).validate(),
+            Err(ConfigError::Invalid(_))
+        ));
+
+        let discovered = RecordConfig {
+            discover_tag: Some("ddns".to_string()),
+            reconcile: Some(true),
+            ..RecordConfig::default()
+        };
+        assert!(matches!(
+            zone(vec![discovered]).validate(),
+            Err(ConfigError::Invalid(problem)) if problem.ends_with("can't reconcile discovered records")
+        ));
+
+        let named = RecordConfig {
+            name: Some("home".to_string()),
+            id: Some("abc".to_string()),
+            ..RecordConfig::default()
+        };
+        assert!(zone(vec![named]).validate().is_ok());
+    }
+
+    #[test]
+    fn validates_names_and_addresses() {
+        assert!(check_name("example.com").is_ok());
+        assert!(check_name("*.home.example.com").is_ok());
+        assert!(check_name("_acme-challenge.example.com").is_ok());
+        assert!(check_name("example..com").is_err());
+        assert!(check_name("example.com.").is_err());
+        assert!(check_name("-home.example.com").is_err());
+        assert!(check_name("home.*.example.com").is_err());
+        assert!(check_name("home page").is_err());
+        assert!(check_name(&format!("{}.com", "a".repeat(64))).is_err());
+
+        let record = |name: &str, ip: &[IpAddr]| {
+            let config = Config {
+                zones: vec![ZoneConfig {
+                    name: "example.com".to_string(),
+                    records: vec![RecordConfig {
+                        name: Some(name.to_string()),
+                        ip: ip.to_vec(),
+                        ..RecordConfig::default()
+                    }],
+                    ..ZoneConfig::default()
+                }],
+                ..Config::default()
+            };
+
+            config.validate()
+        };
+
+        assert!(record("@", &[]).is_ok());
+        assert!(record("home", &[IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7))]).is_ok());
+        assert!(record("home_", &[]).is_ok());
+        assert!(record("home!", &[]).is_err());
+        assert!(record("home", &[IpAddr::V4(Ipv4Addr::LOCALHOST)]).is_err());
+    }
+
+    #[test]
+    fn selects_profiles() {
+        let config = Config::from_toml(
+            r#"
+            token-file = "/etc/cf-ddns/token"
+            default-profile = "personal"
+
+            [profiles.personal]
+            zones = [{ name = "example.com", records = [{ name = "home" }] }]
+
+            [profiles.work]
+            token = "secret"
+            zones = [{ name = "example.org", records = [{ name = "vpn" }] }]
+            "#,
+        )
+        .unwrap();
+
+        assert!(config.validate().is_ok());
+
+        let personal = config.profile(None).unwrap();
+        assert_eq!(personal.zones[0].name, "example.com");
+        assert_eq!(
+            personal.token_file,
+            Some(PathBuf::from("/etc/cf-ddns/token"))
+        );
+
+        let work = config.profile(Some("work")).unwrap();
+        assert_eq!(work.zones[0].name, "example.org");
+        assert_eq!(work.token.as_deref(), Some("secret"));
+        assert_eq!(work.token_file, None);
+
+        assert!(matches!(
+            config.profile(Some("home")),
+            Err(ConfigError::UnknownProfile(name, profiles)) if name == "home" && profiles == ["personal", "work"]
+        ));
+
+        let names: Vec<_> = config
+            .all_profiles()
+            .into_iter()
+            .map(|(name, profile)| (name, profile.zones[0].name.clone()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                (Some("personal"), "example.com".to_string()),
+                (Some("work"), "example.org".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn declares_records() {
+        let config = Config::from_toml(
+            r#"
+            [[zones]]
+            name = "Example.com."
+            records = [{ name = "home" }, { id = "abc" }, { discover-tag = "ddns" }]
+            "#,
+        )
+        .unwrap();
+        let profile = config.profile(None).unwrap();
+
+        let content = RecordContent::A {
+            content: Ipv4Addr::new(203, 0, 113, 7),
+        };
+        let home = records::tests::record("1", "home.example.com", content.clone());
+        let by_id = records::tests::record("abc", "nas.example.com", content.clone());
+        let tagged = Record {
+            tags: vec!["ddns:vpn".to_string()],
+            ..records::tests::record("2", "vpn.example.com", content.clone())
+        };
+        let other = records::tests::record("3", "old.example.com", content);
+
+        assert!(profile.declares(&home, "example.com"));
+        assert!(profile.declares(&by_id, "example.com"));
+        assert!(profile.declares(&tagged, "example.com"));
+        assert!(!profile.declares(&other, "example.com"));
+        assert!(!profile.declares(&home, "example.org"));
+    }
+
+    #[test]
+    fn zones_take_their_own_tokens() {
+        let config = Config::from_toml(
+            r#"
+            token-file = "/etc/cf-ddns/token"
+
+            [[zones]]
+            name = "example.com"
+            records = [{ name = "home" }]
+
+            [[zones]]
+            name = "example.net"
+            token-command = "pass show cloudflare/other"
+            records = [{ name = "home" }]
+            "#,
+        )
+        .unwrap();
+
+        assert!(config.validate().is_ok());
+        assert_eq!(config.zones[0].token_profile(), None);
+        assert_eq!(
+            config.zones[1]
+                .token_profile()
+                .and_then(|token| token.token_source()),
+            Some(TokenSource::Command(
+                "pass show cloudflare/other".to_string()
+            ))
+        );
+
+        let mut both = config.clone();
+        both.zones[1].token = Some("secret".to_string());
+        assert!(
+            matches!(both.validate(), Err(ConfigError::Invalid(problem)) if problem.starts_with("zone example.net"))
+        );
+    }
+
+    #[test]
+    fn requires_a_profile_without_unnamed_zones() {
+        let config = Config::from_toml(
+            r#"
+            [[profiles.work.zones]]
+            name = "example.org"
+            records = [{ name = "vpn" }]
+            "#,
+        )
+        .unwrap();
+
+        assert!(config.validate().is_ok());
+        assert!(matches!(config.profile(None), Err(ConfigError::Invalid(_))));
+        assert!(config.profile(Some("work")).is_ok());
+
+        let config = Config {
+            default_profile: Some("home".to_string()),
+            ..config
+        };
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::UnknownProfile(..))
+        ));
+    }
+}
@@ -0,0 +1,68 @@
+//! Holding back what a run prints on stdout until it's known whether a record changed, for `--quiet-unless-changed`.
+//!
+//! cron emails the output of every run that prints anything, so a run that changed nothing and failed at nothing
+//! stays silent.
+
+use std::sync::Mutex;
+
+static HELD: Mutex<Option<Held>> = Mutex::new(None);
+
+#[derive(Debug, Default)]
+struct Held {
+    lines: Vec<String>,
+    changed: bool,
+}
+
+/// Start holding back the lines printed with [`println`].
+pub fn hold() {
+    *lock() = Some(Held::default());
+}
+
+/// Print `line` on stdout, or hold it back until [`release`].
+pub fn println(line: String) {
+    match lock().as_mut() {
+        Some(held) => held.lines.push(line),
+        None => std::println!("{line}"),
+    }
+}
+
+/// Note that a record changed, so that the lines held back are printed.
+pub fn changed() {
+    if let Some(held) = lock().as_mut() {
+        held.changed = true;
+    }
+}
+
+/// Stop holding back lines, and return those held back if a record changed or the run `failed`.
+pub fn release(failed: bool) -> Vec<String> {
+    match lock().take() {
+        Some(held) if held.changed || failed => held.lines,
+        _ => Vec::new(),
+    }
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Held>> {
+    // A panic while holding the lock leaves consistent lines behind.
+    HELD.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prints_only_changes_and_failures() {
+        hold();
+        println("home.example.com already has 203.0.113.7, no change".to_string());
+        assert!(release(false).is_empty());
+
+        hold();
+        println("home.example.com already has 203.0.113.7, no change".to_string());
+        assert_eq!(release(true).len(), 1);
+
+        hold();
+        println("nas.example.com => 203.0.113.8".to_string());
+        changed();
+        assert_eq!(release(false), ["nas.example.com => 203.0.113.8"]);
+    }
+}
@@ -0,0 +1,67 @@
+//! Updating repeatedly in one long-running process, for `daemon`, instead of relying on a cron job or systemd timer.
+
+use std::thread;
+use std::time::Duration;
+
+use crate::retry;
+
+/// When to run the updates: every `interval`, plus a random delay of up to `jitter`, so that many clients started at
+/// the same time, e.g. after a power cut, don't all ask the workers and Cloudflare at once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Schedule {
+    /// The least time between the end of an update and the start of the next.
+    pub interval: Duration,
+
+    /// The most time added randomly to each interval.
+    pub jitter: Duration,
+}
+
+impl Schedule {
+    /// Run `update` now, then again after each delay, forever.
+    pub fn run(&self, mut update: impl FnMut()) -> ! {
+        loop {
+            update();
+            let delay = self.delay(retry::random());
+            tracing::info!(
+                "Updating again in {}",
+                humantime::format_duration(Duration::from_secs(delay.as_secs()))
+            );
+            thread::sleep(delay);
+        }
+    }
+
+    /// The delay before the next update, adding between no and all of the jitter depending on `random`.
+    fn delay(&self, random: u64) -> Duration {
+        let fraction = (random % 1001) as f64 / 1000.0;
+        self.interval + self.jitter.mul_f64(fraction)
+    }
+}
+
+/// Parse the interval between updates, which must not be zero.
+pub fn parse_interval(interval: &str) -> Result<Duration, String> {
+    match humantime::parse_duration(interval).map_err(|e| e.to_string())? {
+        Duration::ZERO => Err("the interval must be longer than zero".to_string()),
+        interval => Ok(interval),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_by_up_to_the_jitter() {
+        let schedule = Schedule {
+            interval: Duration::from_secs(300),
+            jitter: Duration::from_secs(60),
+        };
+
+        assert_eq!(schedule.delay(0), Duration::from_secs(300));
+        assert_eq!(schedule.delay(500), Duration::from_secs(330));
+        assert_eq!(schedule.delay(1000), Duration::from_secs(360));
+        assert!(schedule.delay(u64::MAX) <= Duration::from_secs(360));
+
+        assert_eq!(parse_interval("5m"), Ok(Duration::from_secs(300)));
+        assert!(parse_interval("0s").is_err());
+    }
+}
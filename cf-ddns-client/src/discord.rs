@@ -0,0 +1,107 @@
+//! Notifying a Discord channel through a webhook, for `--discord-webhook`.
+//!
+//! Each notification is an embed with the record, its old and new IP address, and the outcome, colored green when the
+//! record was updated and red when it failed to be.
+
+use serde_json::{json, Value};
+
+use crate::history::Event;
+use crate::http::Settings;
+use crate::notify::{self, Notifier, NotifyError};
+use crate::state::Outcome;
+
+/// The color of the embed of an updated record.
+const GREEN: u32 = 0x57f287;
+
+/// The color of the embed of a record that wasn't updated.
+const GREY: u32 = 0x95a5a6;
+
+/// The color of the embed of a record that failed to be updated.
+const RED: u32 = 0xed4245;
+
+/// A webhook of a Discord channel, like `https://discord.com/api/webhooks/<ID>/<TOKEN>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Discord {
+    url: String,
+}
+
+impl Discord {
+    /// The webhook at `url`.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self { url: url.into() }
+    }
+
+    /// The message posted for `event`.
+    fn message(&self, event: &Event) -> Value {
+        let color = match event.result {
+            Outcome::Updated => GREEN,
+            Outcome::Unchanged => GREY,
+            Outcome::Failed => RED,
+        };
+
+        let old_ip = event
+            .old_ip
+            .map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+
+        json!({
+            "embeds": [{
+                "title": notify::summary(event),
+                "color": color,
+                "timestamp": event.time,
+                "fields": [
+                    { "name": "Record", "value": event.records },
+                    { "name": "Old IP", "value": old_ip, "inline": true },
+                    { "name": "New IP", "value": event.new_ip.to_string(), "inline": true },
+                    { "name": "Outcome", "value": event.result.to_string(), "inline": true },
+                ],
+            }],
+        })
+    }
+}
+
+impl Notifier for Discord {
+    fn name(&self) -> &str {
+        "Discord"
+    }
+
+    fn wants(&self, outcome: Outcome) -> bool {
+        outcome != Outcome::Unchanged
+    }
+
+    fn send(&self, event: &Event, settings: &Settings) -> Result<(), NotifyError> {
+        let response = settings
+            .builder()
+            .build()?
+            .post(&self.url)
+            .json(&self.message(event))
+            .send()?;
+
+        notify::check(response, "message")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::*;
+
+    #[test]
+    fn builds_embeds() {
+        let old_ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 6));
+        let new_ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+        let discord = Discord::new("https://discord.com/api/webhooks/1/abc");
+
+        let event = Event::new("example.com/home/A", Some(old_ip), new_ip, Outcome::Updated);
+        let embed = &discord.message(&event)["embeds"][0];
+        assert_eq!(embed["color"], GREEN);
+        assert_eq!(embed["fields"][1]["value"], "203.0.113.6");
+        assert_eq!(embed["fields"][2]["value"], "203.0.113.7");
+
+        let event = Event::new("example.com/home/A", None, new_ip, Outcome::Failed);
+        let embed = &discord.message(&event)["embeds"][0];
+        assert_eq!(embed["color"], RED);
+        assert_eq!(embed["fields"][1]["value"], "unknown");
+        assert_eq!(embed["fields"][3]["value"], "failed");
+    }
+}
@@ -0,0 +1,353 @@
+//! Discovering the IP address with DNS queries to resolvers that answer with the address of the client.
+//!
+//! [OpenDNS](https://www.opendns.com/) answers `A` and `AAAA` queries for `myip.opendns.com` with the client's
+//! address, and [Cloudflare](https://1.1.1.1/) answers `CH TXT` queries for `whoami.cloudflare` with it. A single
+//! UDP round trip is enough, so this keeps working when HTTP is unavailable.
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::source::{Family, IpSource, SourceError};
+
+/// How long to wait for the resolver to answer.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The largest DNS message sent over UDP without EDNS.
+const MAX_MESSAGE: usize = 512;
+
+const TYPE_A: u16 = 1;
+const TYPE_TXT: u16 = 16;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+const CLASS_CH: u16 = 3;
+
+/// Resolvers that answer with the address of the client.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Provider {
+    /// `myip.opendns.com` queried against `resolver1.opendns.com`.
+    OpenDns,
+
+    /// `whoami.cloudflare` queried with class `CH` against `one.one.one.one`.
+    Cloudflare,
+}
+
+impl Provider {
+    /// The address of the resolver to query for an address of `family`.
+    fn resolver(self, family: Family) -> SocketAddr {
+        let ip = match (self, family) {
+            (Self::OpenDns, Family::V4) => IpAddr::V4(Ipv4Addr::new(208, 67, 222, 222)),
+            (Self::OpenDns, Family::V6) => {
+                IpAddr::V6(Ipv6Addr::new(0x2620, 0x119, 0x35, 0, 0, 0, 0, 0x35))
+            }
+            (Self::Cloudflare, Family::V4) => IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+            (Self::Cloudflare, Family::V6) => {
+                IpAddr::V6(Ipv6Addr::new(0x2606, 0x4700, 0x4700, 0, 0, 0, 0, 0x1111))
+            }
+        };
+
+        SocketAddr::new(ip, 53)
+    }
+
+    /// The name, type, and class to query for an address of `family`.
+    fn question(self, family: Family) -> (&'static str, u16, u16) {
+        match (self, family) {
+            (Self::OpenDns, Family::V4) => ("myip.opendns.com", TYPE_A, CLASS_IN),
+            (Self::OpenDns, Family::V6) => ("myip.opendns.com", TYPE_AAAA, CLASS_IN),
+            (Self::Cloudflare, _) => ("whoami.cloudflare", TYPE_TXT, CLASS_CH),
+        }
+    }
+}
+
+/// Errors that can occur when discovering the IP address with DNS.
+#[derive(Debug)]
+pub enum DnsError {
+    /// The query could not be sent or no answer was received in time.
+    Io(io::Error),
+
+    /// The answer was not a valid DNS response to the query.
+    Malformed,
+
+    /// The resolver answered with an error. Contains the response code.
+    ResponseCode(u8),
+
+    /// The answer did not contain an IP address.
+    NoAddress,
+}
+
+impl fmt::Display for DnsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                write!(
+                    f,
+                    "The resolver did not answer within {} seconds.",
+                    TIMEOUT.as_secs()
+                )
+            }
+            Self::Io(e) => write!(f, "Failed to query the resolver: {e}."),
+            Self::Malformed => write!(f, "The resolver sent a malformed response."),
+            Self::ResponseCode(code) => write!(f, "The resolver answered with error code {code}."),
+            Self::NoAddress => write!(f, "The resolver's answer did not contain an IP address."),
+        }
+    }
+}
+
+impl error::Error for DnsError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Malformed | Self::ResponseCode(_) | Self::NoAddress => None,
+        }
+    }
+}
+
+/// An [`IpSource`] that asks a DNS resolver for the client's address.
+pub struct DnsSource {
+    provider: Provider,
+    family: Family,
+}
+
+impl DnsSource {
+    /// Create a source asking `provider` for an address of `family`.
+    pub fn new(provider: Provider, family: Family) -> Self {
+        Self { provider, family }
+    }
+
+    fn query(&self) -> Result<IpAddr, DnsError> {
+        let resolver = self.provider.resolver(self.family);
+        let (name, qtype, qclass) = self.provider.question(self.family);
+        let id = query_id();
+        let bind: SocketAddr = match resolver {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+
+        let socket = UdpSocket::bind(bind).map_err(DnsError::Io)?;
+        socket
+            .set_read_timeout(Some(TIMEOUT))
+            .map_err(DnsError::Io)?;
+        socket.connect(resolver).map_err(DnsError::Io)?;
+        socket
+            .send(&encode_query(id, name, qtype, qclass))
+            .map_err(DnsError::Io)?;
+
+        let mut buf = [0; MAX_MESSAGE];
+        let len = socket.recv(&mut buf).map_err(DnsError::Io)?;
+        parse_response(&buf[..len], id, qtype)
+    }
+}
+
+impl IpSource for DnsSource {
+    fn name(&self) -> &str {
+        match self.provider {
+            Provider::OpenDns => "opendns",
+            Provider::Cloudflare => "cloudflare-dns",
+        }
+    }
+
+    fn get_ip(&self) -> Result<IpAddr, SourceError> {
+        Ok(self.query()?)
+    }
+}
+
+/// A query ID that is unlikely to repeat between runs.
+fn query_id() -> u16 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.subsec_nanos());
+
+    (nanos ^ (nanos >> 16)) as u16
+}
+
+/// Encode a recursive query for `name` with a single question.
+fn encode_query(id: u16, name: &str, qtype: u16, qclass: u16) -> Vec<u8> {
+    let mut message = Vec::with_capacity(MAX_MESSAGE);
+    message.extend_from_slice(&id.to_be_bytes());
+    // Flags: a standard query with recursion desired.
+    message.extend_from_slice(&[0x01, 0x00]);
+    // One question, no answers, authority, or additional records.
+    message.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        message.push(label.len() as u8);
+        message.extend_from_slice(label.as_bytes());
+    }
+
+    message.push(0);
+    message.extend_from_slice(&qtype.to_be_bytes());
+    message.extend_from_slice(&qclass.to_be_bytes());
+    message
+}
+
+/// Parse the response to the query `id`, returning the first address in an answer of type `qtype`.
+fn parse_response(message: &[u8], id: u16, qtype: u16) -> Result<IpAddr, DnsError> {
+    let header = message.get(..12).ok_or(DnsError::Malformed)?;
+    let is_response = header[2] & 0x80 != 0;
+    if u16::from_be_bytes([header[0], header[1]]) != id || !is_response {
+        return Err(DnsError::Malformed);
+    }
+
+    let rcode = header[3] & 0x0f;
+    if rcode != 0 {
+        return Err(DnsError::ResponseCode(rcode));
+    }
+
+    let questions = u16::from_be_bytes([header[4], header[5]]);
+    let answers = u16::from_be_bytes([header[6], header[7]]);
+    let mut offset = 12;
+    for _ in 0..questions {
+        offset = skip_name(message, offset)? + 4;
+    }
+
+    for _ in 0..answers {
+        offset = skip_name(message, offset)?;
+        let fixed = message
+            .get(offset..offset + 10)
+            .ok_or(DnsError::Malformed)?;
+
+        let rtype = u16::from_be_bytes([fixed[0], fixed[1]]);
+        let rdlength = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+        offset += 10;
+        let rdata = message
+            .get(offset..offset + rdlength)
+            .ok_or(DnsError::Malformed)?;
+
+        offset += rdlength;
+        if rtype != qtype {
+            continue;
+        }
+
+        let ip = match rtype {
+            TYPE_A => <[u8; 4]>::try_from(rdata).ok().map(IpAddr::from),
+            TYPE_AAAA => <[u8; 16]>::try_from(rdata).ok().map(IpAddr::from),
+            TYPE_TXT => parse_txt(rdata),
+            _ => None,
+        };
+
+        if let Some(ip) = ip {
+            return Ok(ip);
+        }
+    }
+
+    Err(DnsError::NoAddress)
+}
+
+/// Parse the first character string of TXT record data as an IP address.
+fn parse_txt(rdata: &[u8]) -> Option<IpAddr> {
+    let (&len, rest) = rdata.split_first()?;
+    let text = std::str::from_utf8(rest.get(..len as usize)?).ok()?;
+    text.trim_matches('"').parse().ok()
+}
+
+/// Skip the possibly compressed name at `offset`, returning the offset of the data that follows it.
+fn skip_name(message: &[u8], mut offset: usize) -> Result<usize, DnsError> {
+    loop {
+        let len = *message.get(offset).ok_or(DnsError::Malformed)?;
+        match len {
+            0 => return Ok(offset + 1),
+            // A pointer to a name elsewhere ends this one.
+            len if len & 0xc0 == 0xc0 => return Ok(offset + 2),
+            len => offset += 1 + len as usize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A response to `encode_query(id, name, ..)` with a single answer using a compressed name.
+    fn response(id: u16, name: &str, qtype: u16, qclass: u16, rdata: &[u8]) -> Vec<u8> {
+        let mut message = encode_query(id, name, qtype, qclass);
+        message[2] = 0x81;
+        message[3] = 0x80;
+        message[7] = 1;
+        message.extend_from_slice(&[0xc0, 12]);
+        message.extend_from_slice(&qtype.to_be_bytes());
+        message.extend_from_slice(&qclass.to_be_bytes());
+        message.extend_from_slice(&[0, 0, 0, 0]);
+        message.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        message.extend_from_slice(rdata);
+        message
+    }
+
+    #[test]
+    fn asks_for_the_family() {
+        let source = DnsSource::new(Provider::OpenDns, Family::V6);
+        assert!(source.provider.resolver(source.family).is_ipv6());
+        assert_eq!(source.provider.question(source.family).1, TYPE_AAAA);
+
+        let source = DnsSource::new(Provider::Cloudflare, Family::V6);
+        assert!(source.provider.resolver(source.family).is_ipv6());
+        assert!(Provider::Cloudflare.resolver(Family::V4).is_ipv4());
+    }
+
+    #[test]
+    fn encodes_query() {
+        let query = encode_query(0x1234, "myip.opendns.com", TYPE_A, CLASS_IN);
+        assert_eq!(&query[..4], &[0x12, 0x34, 0x01, 0x00]);
+        assert_eq!(&query[12..17], b"\x04myip");
+        assert_eq!(&query[query.len() - 5..], &[0, 0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn parses_a_answer() {
+        let message = response(7, "myip.opendns.com", TYPE_A, CLASS_IN, &[203, 0, 113, 7]);
+        assert_eq!(
+            parse_response(&message, 7, TYPE_A).unwrap(),
+            IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7))
+        );
+    }
+
+    #[test]
+    fn parses_aaaa_answer() {
+        let ip = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        let message = response(7, "myip.opendns.com", TYPE_AAAA, CLASS_IN, &ip.octets());
+        assert_eq!(
+            parse_response(&message, 7, TYPE_AAAA).unwrap(),
+            IpAddr::V6(ip)
+        );
+    }
+
+    #[test]
+    fn parses_txt_answer() {
+        let message = response(
+            7,
+            "whoami.cloudflare",
+            TYPE_TXT,
+            CLASS_CH,
+            b"\x0b203.0.113.7",
+        );
+
+        assert_eq!(
+            parse_response(&message, 7, TYPE_TXT).unwrap(),
+            IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7))
+        );
+    }
+
+    #[test]
+    fn rejects_mismatched_id() {
+        let message = response(7, "myip.opendns.com", TYPE_A, CLASS_IN, &[203, 0, 113, 7]);
+        assert!(matches!(
+            parse_response(&message, 8, TYPE_A),
+            Err(DnsError::Malformed)
+        ));
+    }
+
+    #[test]
+    fn reports_response_code() {
+        let mut message = response(7, "myip.opendns.com", TYPE_A, CLASS_IN, &[203, 0, 113, 7]);
+        message[3] = 0x83;
+        assert!(matches!(
+            parse_response(&message, 7, TYPE_A),
+            Err(DnsError::ResponseCode(3))
+        ));
+    }
+}
@@ -0,0 +1,625 @@
+//! Diagnosing common problems with the setup, for the doctor command.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use cf_ddns_cloudflare::blocking::Cloudflare;
+use cf_ddns_cloudflare::Record;
+
+use crate::http::Settings;
+use crate::records::{self, Selector};
+use crate::source::{Family, IpSource};
+use crate::token::{TokenError, TokenSource, API_TOKENS_URL};
+use crate::worker::WorkerSource;
+
+/// The Cloudflare API, whose `Date` header the clock is checked against.
+pub const CLOUDFLARE_API_URL: &str = "https://api.cloudflare.com/client/v4/";
+
+/// How far the local clock may be from the server's before it's reported, since TLS certificates and token expiry
+/// depend on it.
+pub const MAX_CLOCK_SKEW: Duration = Duration::from_secs(60);
+
+/// The result of a diagnostic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// Nothing is wrong.
+    Pass,
+
+    /// Something may be wrong, but updates can still work.
+    Warn,
+
+    /// Updates won't work until this is fixed.
+    Fail,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Pass => write!(f, "PASS"),
+            Self::Warn => write!(f, "WARN"),
+            Self::Fail => write!(f, "FAIL"),
+        }
+    }
+}
+
+/// A diagnostic that was run, with what it found and how to fix it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Check {
+    /// What was checked, e.g. `worker over IPv4`.
+    pub name: String,
+
+    /// The result.
+    pub verdict: Verdict,
+
+    /// What was found.
+    pub detail: String,
+
+    /// How to fix the problem, unless the check passed.
+    pub hint: Option<String>,
+}
+
+impl Check {
+    /// A passing check.
+    pub fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            verdict: Verdict::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    /// A check that found a problem with `verdict`, fixed as described by `hint`.
+    pub fn problem(
+        name: impl Into<String>,
+        verdict: Verdict,
+        detail: impl Into<String>,
+        hint: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            verdict,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.verdict, self.name, self.detail)?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n       {hint}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Check the local clock against the `Date` header of a server's response, received at `now`.
+pub fn check_clock(date: &str, now: SystemTime) -> Check {
+    let name = "clock";
+    let Ok(server) = httpdate::parse_http_date(date) else {
+        return Check::problem(
+            name,
+            Verdict::Warn,
+            format!("the server's date {date:?} is invalid"),
+            "This is likely a problem with a proxy in between, the clock can't be checked.",
+        );
+    };
+
+    // The header only has a resolution of a second.
+    let (skew, direction) = match now.duration_since(server) {
+        Ok(ahead) => (ahead, "ahead of"),
+        Err(behind) => (behind.duration(), "behind"),
+    };
+
+    let skew = Duration::from_secs(skew.as_secs());
+    let detail = format!(
+        "{} {direction} Cloudflare",
+        humantime::format_duration(skew)
+    );
+    if skew <= MAX_CLOCK_SKEW {
+        Check::pass(name, detail)
+    } else {
+        Check::problem(
+            name,
+            Verdict::Fail,
+            detail,
+            "Synchronize the clock, e.g. with `timedatectl set-ntp true`.",
+        )
+    }
+}
+
+/// Check that a file can be created in `dir`, creating it if needed, by writing and removing a probe file.
+pub fn check_writable(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let probe = dir.join(".cf-ddns-doctor");
+    fs::write(&probe, b"")?;
+    fs::remove_file(&probe)
+}
+
+/// The records of a zone that should exist.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Target {
+    /// The name of the zone.
+    pub zone_name: String,
+
+    /// The ID of the zone, looked up by its name if not given.
+    pub zone_id: Option<String>,
+
+    /// A description of the records, e.g. `home` or `tag:ddns`.
+    pub description: String,
+
+    /// How the records are selected, or none to only check the zone.
+    pub selector: Option<Selector>,
+}
+
+/// How many problems the checks found.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Findings {
+    /// The number of checks that didn't pass.
+    pub problems: usize,
+
+    /// Whether a check [failed](Verdict::Fail), so updates won't work.
+    pub failed: bool,
+}
+
+impl Findings {
+    fn add(&mut self, check: &Check) {
+        self.failed |= check.verdict == Verdict::Fail;
+        self.problems += usize::from(check.verdict != Verdict::Pass);
+    }
+}
+
+impl fmt::Display for Findings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.problems {
+            0 => write!(f, "No problems found."),
+            1 => write!(f, "Found 1 problem."),
+            n => write!(f, "Found {n} problems."),
+        }
+    }
+}
+
+/// The setup to diagnose: how Cloudflare and the workers are reached, how the IP address is discovered, the token,
+/// the records, and the files written while updating.
+pub struct Doctor {
+    http: Settings,
+    source: Box<dyn IpSource>,
+    ips: Vec<IpAddr>,
+    workers: Vec<WorkerSource>,
+    worker_required: bool,
+    token: Option<TokenSource>,
+    targets: Vec<Target>,
+    files: Vec<(&'static str, PathBuf)>,
+}
+
+impl Doctor {
+    /// Diagnose the setup sending requests to Cloudflare with `http` and discovering the IP address with `source`.
+    pub fn new(http: Settings, source: Box<dyn IpSource>) -> Self {
+        Self {
+            http,
+            source,
+            ips: Vec::new(),
+            workers: Vec::new(),
+            worker_required: false,
+            token: None,
+            targets: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Take the IP addresses as given instead of discovering them with the source, unless there are none.
+    pub fn with_ips(mut self, ips: Vec<IpAddr>) -> Self {
+        self.ips = ips;
+        self
+    }
+
+    /// Check that each of `workers` answers over IPv4 and IPv6.
+    pub fn with_workers(mut self, workers: impl IntoIterator<Item = WorkerSource>) -> Self {
+        self.workers.extend(workers);
+        self
+    }
+
+    /// Fail, rather than warn, when a worker doesn't answer over IPv4, since the source asks it for the address.
+    pub fn with_worker_required(mut self, required: bool) -> Self {
+        self.worker_required = required;
+        self
+    }
+
+    /// Check that the token from `source` is active and can see zones, failing if there is none.
+    pub fn with_token(mut self, source: Option<TokenSource>) -> Self {
+        self.token = source;
+        self
+    }
+
+    /// Check that the zones and records of `targets` exist, using the token.
+    pub fn with_targets(mut self, targets: impl IntoIterator<Item = Target>) -> Self {
+        self.targets.extend(targets);
+        self
+    }
+
+    /// Check that the directory of the file described by `name` is writable, if there is a `path`.
+    pub fn with_file(mut self, name: &'static str, path: Option<PathBuf>) -> Self {
+        self.files.extend(path.map(|path| (name, path)));
+        self
+    }
+
+    /// Run the checks, passing each to `report` as it finishes.
+    pub fn run(&self, mut report: impl FnMut(&Check)) -> Findings {
+        let mut findings = Findings::default();
+        let mut report = |check: Check| {
+            findings.add(&check);
+            report(&check);
+        };
+
+        report(
+            match self
+                .http
+                .builder()
+                .build()
+                .and_then(|http| http.head(CLOUDFLARE_API_URL).send())
+            {
+                Ok(response) => match response
+                    .headers()
+                    .get(reqwest::header::DATE)
+                    .and_then(|date| date.to_str().ok())
+                {
+                    Some(date) => check_clock(date, SystemTime::now()),
+                    None => Check::problem(
+                        "clock",
+                        Verdict::Warn,
+                        "Cloudflare sent no date",
+                        "The clock can't be checked.",
+                    ),
+                },
+                Err(err) => Check::problem(
+                    "clock",
+                    Verdict::Fail,
+                    format!("could not reach Cloudflare: {err}"),
+                    "Check the network connection, DNS resolution, and any firewall or proxy.",
+                ),
+            },
+        );
+
+        for (worker, family) in self
+            .workers
+            .iter()
+            .flat_map(|worker| [(worker, Family::V4), (worker, Family::V6)])
+        {
+            let name = format!("worker over {family}");
+            let url = worker.url();
+            report(
+                match (worker.clone().with_family(Some(family)).get_ip(), family) {
+                    (Ok(ip), _) => Check::pass(name, format!("{url} answered {ip}")),
+                    (Err(err), Family::V4) => Check::problem(
+                        name,
+                        if self.worker_required {
+                            Verdict::Fail
+                        } else {
+                            Verdict::Warn
+                        },
+                        format!("{url}: {err}"),
+                        "Check the network connection and --url, or choose another --source.",
+                    ),
+                    (Err(err), Family::V6) => Check::problem(
+                        name,
+                        Verdict::Warn,
+                        format!("{url}: {err}"),
+                        "Without IPv6 connectivity, only A records can be updated.",
+                    ),
+                },
+            );
+        }
+
+        report(match self.ips.as_slice() {
+            [] => match self.source.get_ip() {
+                Ok(ip) => Check::pass("IP discovery", format!("discovered {ip}")),
+                Err(err) => Check::problem(
+                    "IP discovery",
+                    Verdict::Fail,
+                    err.to_string(),
+                    "Try another --source, or pass -v when updating for details.",
+                ),
+            },
+            ips => Check::pass(
+                "IP discovery",
+                format!(
+                    "given {}",
+                    ips.iter()
+                        .map(IpAddr::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            ),
+        });
+
+        let client = match check_token(self.token.as_ref(), &self.http) {
+            Ok(token) => match Cloudflare::try_with_builder(&token, self.http.async_builder()) {
+                Ok(client) => Some(client),
+                Err(err) => {
+                    report(Check::problem(
+                        "token",
+                        Verdict::Fail,
+                        err.to_string(),
+                        "Check that the token was copied whole, without other characters.",
+                    ));
+                    None
+                }
+            },
+            Err(check) => {
+                report(check);
+                None
+            }
+        };
+
+        if let Some(client) = &client {
+            let hint = format!(
+                "Create a token at {API_TOKENS_URL} with the Zone:Read and DNS:Edit permissions."
+            );
+            let token = client.verify_token();
+            let active = token.as_ref().is_ok_and(|status| status.is_active());
+            report(match token {
+                Ok(status) if status.is_active() => Check::pass(
+                    "token",
+                    match status.expires_on {
+                        Some(expires_on) => format!("active until {expires_on}"),
+                        None => "active".to_string(),
+                    },
+                ),
+                Ok(status) => Check::problem("token", Verdict::Fail, status.status, hint.clone()),
+                Err(err) => Check::problem("token", Verdict::Fail, err.to_string(), hint.clone()),
+            });
+
+            if active {
+                report(match client.list_zones() {
+                    Ok(zones) if zones.is_empty() => Check::problem(
+                        "zones",
+                        Verdict::Fail,
+                        "the token can't see any zone",
+                        "Grant the token Zone:Read for the zones to update, or all zones of the account.",
+                    ),
+                    Ok(zones) => {
+                        Check::pass("zones", format!("the token can see {} zones", zones.len()))
+                    }
+                    Err(err) => Check::problem("zones", Verdict::Fail, err.to_string(), hint),
+                });
+
+                for target in &self.targets {
+                    for check in check_records(target, client) {
+                        report(check);
+                    }
+                }
+            }
+        }
+
+        // The files often share a directory, which only needs checking once.
+        let mut dirs: Vec<(&Path, Vec<&str>)> = Vec::new();
+        for (name, path) in &self.files {
+            let Some(dir) = path.parent() else {
+                continue;
+            };
+
+            match dirs.iter_mut().find(|(seen, _)| *seen == dir) {
+                Some((_, names)) => names.push(name),
+                None => dirs.push((dir, vec![name])),
+            }
+        }
+
+        for (dir, names) in dirs {
+            let name = format!("{} directory", names.join(", "));
+            report(match check_writable(dir) {
+                Ok(()) => Check::pass(name, format!("{} is writable", dir.display())),
+                Err(err) => Check::problem(
+                    name,
+                    Verdict::Fail,
+                    format!("{} is not writable: {err}", dir.display()),
+                    "Fix the directory's permissions for the user running cf-ddns, or choose another file.",
+                ),
+            });
+        }
+
+        findings
+    }
+}
+
+/// Read the token from `source`, or a failed check explaining why there is none.
+fn check_token(source: Option<&TokenSource>, http: &Settings) -> Result<String, Check> {
+    let Some(source) = source else {
+        return Err(Check::problem(
+            "token",
+            Verdict::Fail,
+            "no token given",
+            "Pass --token, --token-file, or --token-command, set CF_DDNS_TOKEN_FILE, or set token-file in the configuration.",
+        ));
+    };
+
+    source.read(http).map_err(|err| {
+        let hint = match err {
+            TokenError::Read(..) => {
+                "Check the path, and that the user running cf-ddns can read the file."
+            }
+            TokenError::Spawn(..) | TokenError::Failed(..) => {
+                "Check that the command prints the token when run by the user running cf-ddns."
+            }
+            TokenError::Empty(_) => "Save the token where cf-ddns reads it from.",
+            #[cfg(feature = "vault")]
+            TokenError::Vault(_) => {
+                "Check VAULT_ADDR and the credentials, and that their policy can read the secret."
+            }
+            #[cfg(feature = "keyring")]
+            TokenError::Keyring(crate::keyring::KeyringError::NoToken) => {
+                "Save the token with `cf-ddns-client token set`, or pass --token-file."
+            }
+            #[cfg(feature = "keyring")]
+            TokenError::Keyring(_) => {
+                "Check that a keyring is unlocked for the user running cf-ddns, or pass --token-file."
+            }
+        };
+
+        Check::problem("token", Verdict::Fail, err.to_string(), hint)
+    })
+}
+
+/// Check that the zone and records of `target` exist.
+fn check_records(target: &Target, client: &Cloudflare) -> Vec<Check> {
+    let zone_name = &target.zone_name;
+    let zone = match &target.zone_id {
+        Some(zone_id) => client.get_zone_by_id(zone_id),
+        None => client.get_zone(zone_name),
+    };
+
+    let zone = match zone {
+        Ok(zone) => zone,
+        Err(err) => {
+            return vec![Check::problem(
+                format!("zone {zone_name}"),
+                Verdict::Fail,
+                err.to_string(),
+                "Check the zone's name or ID, and that the token includes the zone.",
+            )]
+        }
+    };
+
+    let mut checks = vec![match zone.status.as_str() {
+        "active" => Check::pass(
+            format!("zone {zone_name}"),
+            format!("active with ID {}", zone.id),
+        ),
+        status => Check::problem(
+            format!("zone {zone_name}"),
+            Verdict::Warn,
+            format!("{status} with ID {}", zone.id),
+            "Finish adding the zone to Cloudflare, records of an inactive zone aren't served.",
+        ),
+    }];
+
+    let name = format!("records {zone_name}/{}", target.description);
+    let is_address = |record: &Record| matches!(record.content.record_type(), "A" | "AAAA");
+    let found = match &target.selector {
+        Some(Selector::Id(record_id)) => client
+            .get_record(&zone.id, record_id)
+            .map(|record| vec![record]),
+        Some(Selector::Name(record_name)) => {
+            client.get_records(&zone.id, &records::fqdn(record_name, zone_name))
+        }
+        Some(Selector::Discover(discover)) => client.list_records(&zone.id, None).map(|found| {
+            found
+                .into_iter()
+                .filter(|record| discover.matches(record))
+                .collect()
+        }),
+        None => return checks,
+    };
+
+    checks.push(match found.map(|found| found.into_iter().filter(is_address).collect::<Vec<_>>()) {
+        Ok(found) if found.is_empty() => Check::problem(
+            name,
+            Verdict::Fail,
+            "no A or AAAA records",
+            "Create the record in the Cloudflare dashboard, or pass --reconcile to create it.",
+        ),
+        Ok(found) => Check::pass(
+            name,
+            found
+                .iter()
+                .map(|record| format!("{} {}", record.content.record_type(), record.content))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        Err(err) => Check::problem(
+            name,
+            Verdict::Fail,
+            err.to_string(),
+            "Check the record's name or ID, and that the token has DNS permissions for the zone.",
+        ),
+    });
+
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::paths::tests::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn checks_clock_skew() {
+        let server = httpdate::parse_http_date("Wed, 14 Oct 2026 11:00:00 GMT").unwrap();
+        let date = "Wed, 14 Oct 2026 11:00:00 GMT";
+
+        let check = check_clock(date, server + Duration::from_millis(1500));
+        assert_eq!(check.verdict, Verdict::Pass);
+        assert_eq!(check.detail, "1s ahead of Cloudflare");
+
+        let check = check_clock(date, server - Duration::from_secs(300));
+        assert_eq!(check.verdict, Verdict::Fail);
+        assert_eq!(check.detail, "5m behind Cloudflare");
+        assert!(check.hint.is_some());
+
+        assert_eq!(check_clock("yesterday", server).verdict, Verdict::Warn);
+    }
+
+    #[test]
+    fn checks_writable_directories() {
+        let dir = TempDir::new("doctor");
+        assert!(check_writable(&dir.join("state")).is_ok());
+        assert!(fs::read_dir(dir.join("state")).unwrap().next().is_none());
+
+        fs::write(dir.join("file"), b"").unwrap();
+        assert!(check_writable(&dir.join("file")).is_err());
+    }
+
+    #[test]
+    fn summarizes_findings() {
+        let mut findings = Findings::default();
+        assert_eq!(findings.to_string(), "No problems found.");
+
+        findings.add(&Check::pass("clock", "0s ahead of Cloudflare"));
+        findings.add(&Check::problem(
+            "worker over IPv6",
+            Verdict::Warn,
+            "timed out",
+            "Check IPv6.",
+        ));
+        assert_eq!(findings.to_string(), "Found 1 problem.");
+        assert!(!findings.failed);
+
+        findings.add(&Check::problem(
+            "token",
+            Verdict::Fail,
+            "expired",
+            "Create a new token.",
+        ));
+        assert_eq!(findings.to_string(), "Found 2 problems.");
+        assert!(findings.failed);
+    }
+
+    #[test]
+    fn fails_without_a_token() {
+        let check = check_token(None, &Settings::default()).unwrap_err();
+        assert_eq!(check.verdict, Verdict::Fail);
+        assert_eq!(check.detail, "no token given");
+
+        let token = TokenSource::Literal("secret".to_string());
+        assert_eq!(
+            check_token(Some(&token), &Settings::default()).unwrap(),
+            "secret"
+        );
+    }
+
+    #[test]
+    fn displays_hints() {
+        let check = Check::problem("token", Verdict::Fail, "expired", "Create a new token.");
+        assert_eq!(
+            check.to_string(),
+            "[FAIL] token: expired\n       Create a new token."
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Resolving records through public [DNS over HTTPS](https://datatracker.ietf.org/doc/html/rfc8484) resolvers.
+//!
+//! After an update, the API reports success as soon as Cloudflare has accepted the new content, but resolvers may keep
+//! answering with the old address until their cached answer expires. Asking public resolvers with their JSON APIs
+//! shows what the rest of the internet sees.
+
+use std::error;
+use std::fmt;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::http::Settings;
+
+/// How long to wait for a resolver to answer, unless configured otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A public resolver with a JSON DNS over HTTPS API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Resolver {
+    /// A name for the resolver, used in messages.
+    pub name: &'static str,
+
+    /// The URL of the resolver's JSON API.
+    pub url: &'static str,
+}
+
+/// Cloudflare's 1.1.1.1 resolver.
+pub const CLOUDFLARE: Resolver = Resolver {
+    name: "Cloudflare DNS",
+    url: "https://cloudflare-dns.com/dns-query",
+};
+
+/// Google Public DNS.
+pub const GOOGLE: Resolver = Resolver {
+    name: "Google Public DNS",
+    url: "https://dns.google/resolve",
+};
+
+/// The resolvers asked to verify updates.
+pub const RESOLVERS: [Resolver; 2] = [CLOUDFLARE, GOOGLE];
+
+/// Errors that can occur when resolving a record over DNS over HTTPS.
+#[derive(Debug)]
+pub enum DohError {
+    /// The request failed, or the resolver answered with an error status or an unexpected body.
+    RequestFailed(reqwest::Error),
+
+    /// The resolver answered with a DNS response code other than `NOERROR`. Contains the code.
+    ResponseCode(u8),
+}
+
+impl fmt::Display for DohError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::RequestFailed(e) => write!(f, "Failed to send request: {e}."),
+            Self::ResponseCode(3) => write!(f, "The name does not exist (NXDOMAIN)."),
+            Self::ResponseCode(code) => {
+                write!(f, "The resolver answered with response code {code}.")
+            }
+        }
+    }
+}
+
+impl error::Error for DohError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::RequestFailed(e) => Some(e),
+            Self::ResponseCode(_) => None,
+        }
+    }
+}
+
+/// The JSON response of a resolver.
+#[derive(Debug, Deserialize)]
+struct Response {
+    #[serde(rename = "Status")]
+    status: u8,
+
+    #[serde(rename = "Answer", default)]
+    answer: Vec<Answer>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Answer {
+    data: String,
+}
+
+/// Resolve the `record_type` records of `name` with `resolver`, returning the addresses in the answer.
+///
+/// The request is sent with the proxy, certificates, and user agent of `settings`, and fails if the resolver hasn't
+/// answered within `timeout`.
+pub async fn resolve(
+    resolver: Resolver,
+    name: &str,
+    record_type: &str,
+    settings: &Settings,
+    timeout: Duration,
+) -> Result<Vec<IpAddr>, DohError> {
+    let response: Response = async {
+        settings
+            .async_builder()
+            .timeout(timeout)
+            .build()?
+            .get(resolver.url)
+            .query(&[("name", name), ("type", record_type)])
+            .header("accept", "application/dns-json")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+    .await
+    .map_err(DohError::RequestFailed)?;
+
+    addresses(response)
+}
+
+/// The addresses in the answer of `response`, ignoring records such as `CNAME`s that aren't addresses.
+fn addresses(response: Response) -> Result<Vec<IpAddr>, DohError> {
+    if response.status != 0 {
+        return Err(DohError::ResponseCode(response.status));
+    }
+
+    Ok(response
+        .answer
+        .iter()
+        .filter_map(|answer| answer.data.parse().ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    fn parse(json: &str) -> Result<Vec<IpAddr>, DohError> {
+        addresses(serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn parses_answer() {
+        let json = r#"{"Status":0,"TC":false,"Answer":[
+            {"name":"home.example.com","type":5,"TTL":300,"data":"target.example.com."},
+            {"name":"target.example.com","type":1,"TTL":300,"data":"203.0.113.7"}
+        ]}"#;
+
+        assert_eq!(
+            parse(json).unwrap(),
+            [IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7))]
+        );
+    }
+
+    #[test]
+    fn empty_answer() {
+        assert!(parse(r#"{"Status":0}"#).unwrap().is_empty());
+        assert!(matches!(
+            parse(r#"{"Status":3}"#),
+            Err(DohError::ResponseCode(3))
+        ));
+    }
+}
@@ -0,0 +1,421 @@
+//! Asking public IP echo services, such as `https://icanhazip.com`, for the IP address.
+//!
+//! These services answer a `GET` request with the address of the client in the body. The [format](Format) of the
+//! body is detected automatically, so any of the common services can be used.
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use cf_ddns::Response;
+use reqwest::blocking::ClientBuilder;
+use reqwest::header::{HeaderMap, ETAG};
+use reqwest::{StatusCode, Url};
+use serde_json::Value;
+
+use crate::http::{Settings, Tls};
+use crate::resolve;
+use crate::retry::Retry;
+use crate::source::{Family, IpSource, SourceError};
+
+/// Errors that can occur when asking an echo service for the IP address.
+#[derive(Debug)]
+pub enum EchoError {
+    /// The request failed or the service answered with an error status.
+    RequestFailed(reqwest::Error),
+
+    /// The body of the response did not contain an IP address. Contains the start of the body.
+    InvalidAddress(String),
+
+    /// The body was a cf-ddns worker response reporting errors.
+    UnsuccessfulResponse(Response),
+
+    /// The host of the URL could not be resolved.
+    Resolve(io::Error),
+
+    /// The host of the URL has no address in the family the request was forced over. Contains the host.
+    NoAddress(String, Family),
+
+    /// The service answered `304 Not Modified` to a conditional request, so the address is that of the answer with
+    /// the `ETag` sent.
+    NotModified,
+}
+
+impl fmt::Display for EchoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::RequestFailed(e) => write!(f, "Failed to send request: {e}."),
+            Self::InvalidAddress(body) => {
+                write!(f, "The response did not contain an IP address: {body:?}")
+            }
+            Self::UnsuccessfulResponse(response) => {
+                let errors = &response.errors;
+                write!(f, "Response was not successful: errors: {errors:?}")
+            }
+            Self::Resolve(e) => write!(f, "Failed to resolve the host: {e}."),
+            Self::NoAddress(host, family) => write!(f, "The host {host} has no {family} address."),
+            Self::NotModified => write!(f, "The address is unchanged since the last answer."),
+        }
+    }
+}
+
+impl EchoError {
+    /// Whether the error may go away when retrying: network errors, timeouts, server errors, and failures to resolve
+    /// the host. Client errors such as `404 Not Found` and unexpected responses aren't.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::RequestFailed(e) => e.status().map_or(true, |status| status.is_server_error()),
+            Self::Resolve(_) => true,
+            Self::InvalidAddress(_)
+            | Self::UnsuccessfulResponse(_)
+            | Self::NoAddress(..)
+            | Self::NotModified => false,
+        }
+    }
+}
+
+impl error::Error for EchoError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::RequestFailed(e) => Some(e),
+            Self::Resolve(e) => Some(e),
+            Self::InvalidAddress(_)
+            | Self::UnsuccessfulResponse(_)
+            | Self::NoAddress(..)
+            | Self::NotModified => None,
+        }
+    }
+}
+
+/// The formats of echo service responses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// The JSON [`Response`] of the cf-ddns worker.
+    CfDdns,
+
+    /// A JSON object with an `ip` field, as returned by [ipify](https://www.ipify.org/) and others.
+    Ipify,
+
+    /// `key=value` lines including an `ip` key, as returned by Cloudflare's `/cdn-cgi/trace`.
+    Trace,
+
+    /// A bare IP address.
+    Text,
+}
+
+impl Format {
+    /// Detect the format of `body`.
+    pub fn detect(body: &str) -> Self {
+        let body = body.trim();
+        if body.starts_with('{') {
+            if serde_json::from_str::<Value>(body).is_ok_and(|json| json.get("success").is_some()) {
+                Self::CfDdns
+            } else {
+                Self::Ipify
+            }
+        } else if body.lines().any(|line| line.trim().starts_with("ip=")) {
+            Self::Trace
+        } else {
+            Self::Text
+        }
+    }
+
+    /// Parse the IP address from `body`, which is in this format.
+    pub fn parse(self, body: &str) -> Result<IpAddr, EchoError> {
+        let body = body.trim();
+        let invalid = || EchoError::InvalidAddress(body.chars().take(64).collect());
+        match self {
+            Self::CfDdns => {
+                let response: Response = serde_json::from_str(body).map_err(|_| invalid())?;
+                match response.result {
+                    Some(ip) => Ok(ip),
+                    None => Err(EchoError::UnsuccessfulResponse(response)),
+                }
+            }
+            Self::Ipify => serde_json::from_str::<Value>(body)
+                .ok()
+                .as_ref()
+                .and_then(|json| json.get("ip"))
+                .and_then(Value::as_str)
+                .and_then(|ip| ip.trim().parse().ok())
+                .ok_or_else(invalid),
+            Self::Trace => body
+                .lines()
+                .find_map(|line| line.trim().strip_prefix("ip="))
+                .and_then(|ip| ip.trim().parse().ok())
+                .ok_or_else(invalid),
+            Self::Text => body.parse().map_err(|_| invalid()),
+        }
+    }
+}
+
+/// Get the IP address from the echo service at `url`, detecting the format of its response.
+///
+/// Fails if the service hasn't answered within the timeouts of `http`. The request carries `headers` besides those of
+/// every request. If `family` is given, the request is only sent over that family, so the service sees, and answers
+/// with, an address of that family.
+///
+/// A request that fails over [HTTP/3](Settings::http3) without an answer is sent again over HTTP/2 or HTTP/1.1.
+pub fn fetch(
+    url: &str,
+    http: &Settings,
+    headers: &HeaderMap,
+    family: Option<Family>,
+) -> Result<IpAddr, EchoError> {
+    fetch_tagged(url, http, headers, family).map(|(ip, _)| ip)
+}
+
+/// Get the IP address from the echo service at `url` like [`fetch`], along with the `ETag` of the answer, if any.
+///
+/// With an `If-None-Match` header among `headers`, fails with [`EchoError::NotModified`] if the service answers that
+/// the address is unchanged.
+pub fn fetch_tagged(
+    url: &str,
+    http: &Settings,
+    headers: &HeaderMap,
+    family: Option<Family>,
+) -> Result<(IpAddr, Option<String>), EchoError> {
+    #[cfg(feature = "http3")]
+    if http.http3 {
+        match send(url, http, headers, family) {
+            Err(EchoError::RequestFailed(e)) if !e.is_status() => {
+                tracing::debug!(%e, "HTTP/3 request failed, retrying over TCP");
+            }
+            result => return result,
+        }
+
+        let http = Settings {
+            http3: false,
+            ..http.clone()
+        };
+        return send(url, &http, headers, family);
+    }
+
+    send(url, http, headers, family)
+}
+
+fn send(
+    url: &str,
+    http: &Settings,
+    headers: &HeaderMap,
+    family: Option<Family>,
+) -> Result<(IpAddr, Option<String>), EchoError> {
+    let mut builder = http.builder();
+    if let Some(family) = family {
+        builder = force_family(builder, url, family, http.timeout)?;
+    }
+
+    let response = builder
+        .build()
+        .and_then(|client| client.get(url).headers(headers.clone()).send())
+        .and_then(|response| response.error_for_status())
+        .map_err(EchoError::RequestFailed)?;
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Err(EchoError::NotModified);
+    }
+
+    let etag = response
+        .headers()
+        .get(ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .map(str::to_string);
+
+    let body = response.text().map_err(EchoError::RequestFailed)?;
+    let ip = Format::detect(&body).parse(&body)?;
+    Ok((ip, etag))
+}
+
+/// Make requests to the host of `url` only connect to its addresses in `family`, resolving them within `timeout`,
+/// the time the whole request may take, and leaving the request what remains of it.
+///
+/// A URL that can't be parsed is left for the request to reject.
+fn force_family(
+    builder: ClientBuilder,
+    url: &str,
+    family: Family,
+    timeout: Duration,
+) -> Result<ClientBuilder, EchoError> {
+    let Ok(url) = Url::parse(url) else {
+        return Ok(builder);
+    };
+
+    let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+        return Ok(builder);
+    };
+
+    let literal = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = literal.parse::<IpAddr>() {
+        return if family.contains(ip) {
+            Ok(builder)
+        } else {
+            Err(EchoError::NoAddress(host.to_string(), family))
+        };
+    }
+
+    let started = Instant::now();
+    let addrs: Vec<SocketAddr> = resolve::lookup_timeout(host, port, timeout)
+        .map_err(EchoError::Resolve)?
+        .into_iter()
+        .filter(|addr| family.contains(addr.ip()))
+        .collect();
+
+    if addrs.is_empty() {
+        return Err(EchoError::NoAddress(host.to_string(), family));
+    }
+
+    let remaining = timeout.saturating_sub(started.elapsed());
+    Ok(builder.resolve_to_addrs(host, &addrs).timeout(remaining))
+}
+
+/// An [`IpSource`] that asks an echo service at a URL, detecting the format of its response.
+pub struct EchoSource {
+    name: String,
+    url: String,
+    http: Settings,
+    family: Option<Family>,
+    retry: Retry,
+}
+
+impl EchoSource {
+    /// Create a source using the echo service at `url`.
+    pub fn new(url: impl Into<String>) -> Self {
+        let url = url.into();
+        Self {
+            name: format!("url:{url}"),
+            url,
+            http: Settings::default(),
+            family: None,
+            retry: Retry::default(),
+        }
+    }
+
+    /// Give up on the service if it hasn't answered within `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.http.timeout = timeout;
+        self
+    }
+
+    /// Give up on connecting to the service if it hasn't connected within `timeout`, or only limit connecting by
+    /// [`Self::with_timeout`] if `None`.
+    pub fn with_connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.http.connect = timeout;
+        self
+    }
+
+    /// Identify the client to the service with `user_agent`, instead of the [default](crate::http::DEFAULT_USER_AGENT).
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.http.user_agent = user_agent.into();
+        self
+    }
+
+    /// Send the requests to the service through the proxy at `proxy`, checked with [`parse_proxy`](crate::http::parse_proxy),
+    /// or through those of the environment if `None`.
+    pub fn with_proxy(mut self, proxy: Option<String>) -> Self {
+        self.http.proxy = proxy;
+        self
+    }
+
+    /// Verify the certificate of the service as `tls` describes, instead of only against the system's CAs.
+    pub fn with_tls(mut self, tls: Tls) -> Self {
+        self.http.tls = tls;
+        self
+    }
+
+    /// Only contact the service over `family`, or over either family if `None`.
+    pub fn with_family(mut self, family: Option<Family>) -> Self {
+        self.family = family;
+        self
+    }
+
+    /// Retry [transient](EchoError::is_transient) failures according to `retry`.
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+impl IpSource for EchoSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_ip(&self) -> Result<IpAddr, SourceError> {
+        let fetch = || fetch(&self.url, &self.http, &HeaderMap::new(), self.family);
+        Ok(self.retry.run(fetch, EchoError::is_transient)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use reqwest::blocking::Client;
+
+    use super::*;
+    use crate::http::DEFAULT_TIMEOUT;
+
+    fn parse(body: &str) -> Result<IpAddr, EchoError> {
+        Format::detect(body).parse(body)
+    }
+
+    const IP: IpAddr = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+
+    #[test]
+    fn parses_trimmed_text() {
+        assert_eq!(Format::detect("203.0.113.7\n"), Format::Text);
+        assert_eq!(parse("203.0.113.7\n").unwrap(), IP);
+        assert!(parse(" 2001:db8::1 ").unwrap().is_ipv6());
+    }
+
+    #[test]
+    fn rejects_other_text() {
+        assert!(matches!(
+            parse("<html>blocked</html>"),
+            Err(EchoError::InvalidAddress(_))
+        ));
+    }
+
+    #[test]
+    fn parses_cf_ddns_response() {
+        let body = serde_json::to_string(&Response::from(IP)).unwrap();
+        assert_eq!(Format::detect(&body), Format::CfDdns);
+        assert_eq!(parse(&body).unwrap(), IP);
+
+        let body = serde_json::to_string(&Response::from(cf_ddns::Error::HeaderNotFound)).unwrap();
+        assert!(matches!(
+            parse(&body),
+            Err(EchoError::UnsuccessfulResponse(_))
+        ));
+    }
+
+    #[test]
+    fn parses_ipify_response() {
+        let body = r#"{"ip":"203.0.113.7"}"#;
+        assert_eq!(Format::detect(body), Format::Ipify);
+        assert_eq!(parse(body).unwrap(), IP);
+    }
+
+    #[test]
+    fn parses_trace_response() {
+        let body = "fl=123\nh=example.com\nip=203.0.113.7\nts=1700000000.000\nvisit_scheme=https\n";
+        assert_eq!(Format::detect(body), Format::Trace);
+        assert_eq!(parse(body).unwrap(), IP);
+    }
+
+    #[test]
+    fn forced_family_rejects_literal_of_other_family() {
+        let builder = Client::builder();
+        assert!(force_family(builder, "http://192.0.2.1/", Family::V4, DEFAULT_TIMEOUT).is_ok());
+        assert!(matches!(
+            force_family(Client::builder(), "http://192.0.2.1/", Family::V6, DEFAULT_TIMEOUT),
+            Err(EchoError::NoAddress(host, Family::V6)) if host == "192.0.2.1"
+        ));
+        assert!(matches!(
+            force_family(Client::builder(), "http://[2001:db8::1]/", Family::V4, DEFAULT_TIMEOUT),
+            Err(EchoError::NoAddress(host, Family::V4)) if host == "[2001:db8::1]"
+        ));
+    }
+}
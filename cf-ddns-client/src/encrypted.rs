@@ -0,0 +1,615 @@
+//! Decrypting configuration files encrypted with [age](https://age-encryption.org) or
+//! [sops](https://getsops.io) using age keys, so that a configuration including its tokens can be kept in git.
+//!
+//! A file encrypted with `age`, armored or not, is decrypted whole. A file encrypted with `sops` has its values
+//! decrypted with the data key, which is decrypted with one of the `age` recipients of its `sops` metadata. A TOML
+//! file, which sops encrypts as a binary file, is decrypted to its text. Like `sops -d`, the values are
+//! authenticated one by one and the whole file by its MAC, and a value that sops would have encrypted, given the
+//! suffixes and regular expressions of the metadata, is refused if it isn't.
+//!
+//! The identities are read from `--identity-file`, else from `SOPS_AGE_KEY` or `SOPS_AGE_KEY_FILE` like sops does,
+//! else from `sops/age/keys.txt` in the configuration directory.
+
+use std::env;
+use std::error;
+use std::fmt::{self, Write};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use aes_gcm::aead::consts::U32;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::aes::Aes256;
+use aes_gcm::AesGcm;
+use age::armor::ArmoredReader;
+use age::{Decryptor, Identity, IdentityFile};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use regex_lite::Regex;
+use serde_yaml::{Mapping, Value};
+use sha2::{Digest, Sha512};
+
+static IDENTITY_FILE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// The header of a binary age file.
+const AGE_HEADER: &[u8] = b"age-encryption.org/";
+
+/// The header of an armored age file.
+const ARMORED_AGE_HEADER: &[u8] = b"-----BEGIN AGE ENCRYPTED FILE-----";
+
+/// The suffix of the keys whose values sops leaves unencrypted, unless its metadata says otherwise.
+const UNENCRYPTED_SUFFIX: &str = "_unencrypted";
+
+/// The cipher sops encrypts values with, which takes 32 byte nonces.
+type SopsCipher = AesGcm<Aes256, U32>;
+
+/// Errors that can occur when decrypting a configuration.
+#[derive(Debug)]
+pub enum DecryptError {
+    /// The identities could not be read. Contains where they were read from.
+    Identities(String, io::Error),
+
+    /// None of the identities can decrypt the file, or it isn't a valid age file.
+    Age(age::DecryptError),
+
+    /// The sops file has no age recipient, since it was encrypted with other keys only.
+    NoAgeRecipient,
+
+    /// A value of the sops file could not be decrypted. Contains the path of its key.
+    Value(String),
+
+    /// A value of the sops file that should be encrypted isn't. Contains the path of its key.
+    Unencrypted(String),
+
+    /// The MAC of the sops file doesn't match its values.
+    Mac,
+
+    /// The `sops` metadata is invalid or unsupported. Contains the reason.
+    Metadata(String),
+}
+
+impl fmt::Display for DecryptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Identities(source, e) => {
+                write!(f, "Could not read the age identities from {source}: {e}")
+            }
+            Self::Age(e) => write!(f, "Could not decrypt the configuration: {e}"),
+            Self::NoAgeRecipient => {
+                write!(f, "The sops file isn't encrypted to any age recipient.")
+            }
+            Self::Value(path) => {
+                write!(f, "Could not decrypt the value of {path} in the sops file.")
+            }
+            Self::Unencrypted(path) => {
+                write!(f, "The value of {path} in the sops file isn't encrypted.")
+            }
+            Self::Mac => write!(
+                f,
+                "The MAC of the sops file doesn't match its values, which may have been tampered with."
+            ),
+            Self::Metadata(reason) => write!(f, "The sops metadata is invalid: {reason}."),
+        }
+    }
+}
+
+impl error::Error for DecryptError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Identities(_, e) => Some(e),
+            Self::Age(e) => Some(e),
+            Self::NoAgeRecipient
+            | Self::Value(_)
+            | Self::Unencrypted(_)
+            | Self::Mac
+            | Self::Metadata(_) => None,
+        }
+    }
+}
+
+impl From<age::DecryptError> for DecryptError {
+    fn from(e: age::DecryptError) -> Self {
+        Self::Age(e)
+    }
+}
+
+/// Read the identities from `path` instead of the sops locations, for `--identity-file`.
+pub fn set_identity_file(path: PathBuf) {
+    *IDENTITY_FILE.lock().unwrap_or_else(|e| e.into_inner()) = Some(path);
+}
+
+/// Whether `contents` is an age file, or a sops file with age recipients.
+pub fn is_encrypted(contents: &[u8]) -> bool {
+    contents.starts_with(AGE_HEADER)
+        || contents.trim_ascii_start().starts_with(ARMORED_AGE_HEADER)
+        || sops_metadata(contents).is_some()
+}
+
+/// A decrypted configuration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Decrypted {
+    /// The text of the configuration.
+    pub text: String,
+
+    /// Whether the text is YAML whatever the extension of the file, as it is for the YAML and JSON files that sops
+    /// encrypts value by value.
+    pub yaml: bool,
+}
+
+/// Decrypt `contents`, an [encrypted](is_encrypted) configuration.
+pub fn decrypt(contents: &[u8]) -> Result<Decrypted, DecryptError> {
+    let identities = identities()?;
+    match sops_metadata(contents) {
+        Some(file) => decrypt_sops(file, &identities),
+        None => {
+            let text = decrypt_age(contents, &identities)?;
+            Ok(Decrypted {
+                text: String::from_utf8_lossy(&text).into_owned(),
+                yaml: false,
+            })
+        }
+    }
+}
+
+fn identities() -> Result<Vec<Box<dyn Identity>>, DecryptError> {
+    let given = IDENTITY_FILE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    let (source, file) = match (given, env::var("SOPS_AGE_KEY")) {
+        (Some(path), _) => (path.display().to_string(), read_identity_file(&path)),
+        (None, Ok(keys)) => (
+            "SOPS_AGE_KEY".to_string(),
+            IdentityFile::from_buffer(keys.as_bytes()),
+        ),
+        (None, Err(_)) => {
+            let path = env::var_os("SOPS_AGE_KEY_FILE")
+                .map(PathBuf::from)
+                .or_else(|| dirs::config_dir().map(|dir| dir.join("sops/age/keys.txt")))
+                .unwrap_or_default();
+            (path.display().to_string(), read_identity_file(&path))
+        }
+    };
+
+    file.map_err(|e| DecryptError::Identities(source, e))?
+        .into_identities()
+        .map_err(DecryptError::Age)
+}
+
+fn read_identity_file(path: &Path) -> io::Result<IdentityFile<age::NoCallbacks>> {
+    IdentityFile::from_file(path.to_string_lossy().into_owned())
+}
+
+fn decrypt_age(contents: &[u8], identities: &[Box<dyn Identity>]) -> Result<Vec<u8>, DecryptError> {
+    let decryptor = Decryptor::new(ArmoredReader::new(contents))?;
+    let mut reader = decryptor.decrypt(identities.iter().map(|identity| identity.as_ref()))?;
+    let mut text = Vec::new();
+    reader
+        .read_to_end(&mut text)
+        .map_err(|e| DecryptError::Age(e.into()))?;
+    Ok(text)
+}
+
+/// The file parsed as YAML, which includes JSON, if it has the `sops` metadata.
+fn sops_metadata(contents: &[u8]) -> Option<Mapping> {
+    let text = std::str::from_utf8(contents).ok()?;
+    match serde_yaml::from_str(text).ok()? {
+        Value::Mapping(file) if file.get("sops").is_some_and(Value::is_mapping) => Some(file),
+        _ => None,
+    }
+}
+
+fn decrypt_sops(
+    mut file: Mapping,
+    identities: &[Box<dyn Identity>],
+) -> Result<Decrypted, DecryptError> {
+    let metadata = file.remove("sops").unwrap_or_default();
+    let recipients = metadata["age"]
+        .as_sequence()
+        .ok_or(DecryptError::NoAgeRecipient)?;
+
+    let mut key = Err(DecryptError::NoAgeRecipient);
+    for recipient in recipients {
+        if let Some(enc) = recipient["enc"].as_str() {
+            key = decrypt_age(enc.as_bytes(), identities);
+            if key.is_ok() {
+                break;
+            }
+        }
+    }
+
+    let key = key?;
+    let cipher = SopsCipher::new_from_slice(&key).map_err(|_| DecryptError::NoAgeRecipient)?;
+    let rules = Rules::new(&metadata)?;
+    let mut tree = Value::Mapping(file);
+    let mut hash = Sha512::new();
+    decrypt_values(&mut tree, &mut Vec::new(), &cipher, &rules, &mut hash)?;
+    check_mac(&metadata, &cipher, hash)?;
+
+    // A binary file, such as TOML, is kept whole in `data`.
+    Ok(match tree {
+        Value::Mapping(file)
+            if file.len() == 1 && file.get("data").is_some_and(Value::is_string) =>
+        {
+            Decrypted {
+                text: file["data"].as_str().unwrap_or_default().to_string(),
+                yaml: false,
+            }
+        }
+        tree => Decrypted {
+            text: serde_yaml::to_string(&tree).unwrap_or_default(),
+            yaml: true,
+        },
+    })
+}
+
+/// Which values of a sops file are encrypted, from its metadata.
+#[derive(Debug, Default)]
+struct Rules {
+    unencrypted_suffix: Option<String>,
+    encrypted_suffix: Option<String>,
+    unencrypted_regex: Option<Regex>,
+    encrypted_regex: Option<Regex>,
+
+    /// Whether the MAC covers only the encrypted values, rather than every value.
+    mac_only_encrypted: bool,
+}
+
+impl Rules {
+    fn new(metadata: &Value) -> Result<Self, DecryptError> {
+        for comments in ["unencrypted_comment_regex", "encrypted_comment_regex"] {
+            if metadata.get(comments).is_some() {
+                return Err(DecryptError::Metadata(format!(
+                    "{comments} isn't supported"
+                )));
+            }
+        }
+
+        let string = |name: &str| metadata[name].as_str().map(str::to_string);
+        let regex = |name: &str| {
+            string(name)
+                .map(|regex| Regex::new(&regex))
+                .transpose()
+                .map_err(|e| DecryptError::Metadata(format!("{name} isn't valid: {e}")))
+        };
+
+        let mut rules = Self {
+            unencrypted_suffix: string("unencrypted_suffix"),
+            encrypted_suffix: string("encrypted_suffix"),
+            unencrypted_regex: regex("unencrypted_regex")?,
+            encrypted_regex: regex("encrypted_regex")?,
+            mac_only_encrypted: metadata["mac_only_encrypted"].as_bool().unwrap_or_default(),
+        };
+
+        if rules.encrypted_suffix.is_none()
+            && rules.unencrypted_regex.is_none()
+            && rules.encrypted_regex.is_none()
+        {
+            rules
+                .unencrypted_suffix
+                .get_or_insert(UNENCRYPTED_SUFFIX.to_string());
+        }
+
+        Ok(rules)
+    }
+
+    /// Whether sops encrypts the values whose keys are at `path`: the first key matching a suffix or a regular
+    /// expression decides, else they are unless only encrypted ones are given.
+    fn encrypts(&self, path: &[String]) -> bool {
+        for key in path {
+            if self
+                .unencrypted_suffix
+                .as_ref()
+                .is_some_and(|suffix| key.ends_with(suffix.as_str()))
+            {
+                return false;
+            }
+            if self
+                .encrypted_suffix
+                .as_ref()
+                .is_some_and(|suffix| key.ends_with(suffix.as_str()))
+            {
+                return true;
+            }
+            if self
+                .unencrypted_regex
+                .as_ref()
+                .is_some_and(|regex| regex.is_match(key))
+            {
+                return false;
+            }
+            if self
+                .encrypted_regex
+                .as_ref()
+                .is_some_and(|regex| regex.is_match(key))
+            {
+                return true;
+            }
+        }
+
+        self.encrypted_suffix.is_none() && self.encrypted_regex.is_none()
+    }
+}
+
+/// Decrypt the encrypted values in `value`, whose keys are at `path`, in place, adding the values the MAC covers to
+/// `hash`.
+fn decrypt_values(
+    value: &mut Value,
+    path: &mut Vec<String>,
+    cipher: &SopsCipher,
+    rules: &Rules,
+    hash: &mut Sha512,
+) -> Result<(), DecryptError> {
+    match value {
+        Value::Mapping(mapping) => {
+            for (key, value) in mapping.iter_mut() {
+                path.push(key.as_str().unwrap_or_default().to_string());
+                decrypt_values(value, path, cipher, rules, hash)?;
+                path.pop();
+            }
+        }
+        // The items of a list share the path of the list.
+        Value::Sequence(items) => {
+            for item in items {
+                decrypt_values(item, path, cipher, rules, hash)?;
+            }
+        }
+        Value::Tagged(tagged) => decrypt_values(&mut tagged.value, path, cipher, rules, hash)?,
+        leaf => {
+            // Like sops, empty values are left unencrypted, as there's nothing to hide.
+            let empty = leaf.is_null() || leaf.as_str() == Some("");
+            let encrypted = rules.encrypts(path);
+            if encrypted && !empty {
+                let text = leaf
+                    .as_str()
+                    .filter(|text| text.starts_with("ENC["))
+                    .ok_or_else(|| DecryptError::Unencrypted(path.join(".")))?;
+                let aad = format!("{}:", path.join(":"));
+                *leaf = decrypt_value(text, &aad, cipher)
+                    .ok_or_else(|| DecryptError::Value(path.join(".")))?;
+            }
+
+            if encrypted || !rules.mac_only_encrypted {
+                hash.update(mac_bytes(leaf));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The bytes of a value the MAC covers, formatted as sops formats them.
+fn mac_bytes(value: &Value) -> Vec<u8> {
+    match value {
+        Value::String(text) => text.as_bytes().to_vec(),
+        Value::Bool(true) => b"True".to_vec(),
+        Value::Bool(false) => b"False".to_vec(),
+        Value::Number(number) => match number.as_f64().filter(|_| number.is_f64()) {
+            // Go formats floats without a trailing `.0`, as Rust does.
+            Some(float) => float.to_string().into_bytes(),
+            None => number.to_string().into_bytes(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+/// Check that the `mac` of the sops `metadata`, encrypted with the time the file was last modified, is that of the
+/// values added to `hash`.
+fn check_mac(metadata: &Value, cipher: &SopsCipher, hash: Sha512) -> Result<(), DecryptError> {
+    let (Some(mac), Some(modified)) = (metadata["mac"].as_str(), metadata["lastmodified"].as_str())
+    else {
+        return Err(DecryptError::Metadata(
+            "it has no mac or lastmodified".to_string(),
+        ));
+    };
+
+    let expected = decrypt_value(mac, modified, cipher).ok_or(DecryptError::Mac)?;
+    match expected.as_str() == Some(hex(&hash.finalize()).as_str()) {
+        true => Ok(()),
+        false => Err(DecryptError::Mac),
+    }
+}
+
+/// `bytes` in uppercase hexadecimal, as sops writes its MACs.
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(2 * bytes.len()), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02X}");
+            hex
+        })
+}
+
+/// Decrypt a value like `ENC[AES256_GCM,data:...,iv:...,tag:...,type:str]`, authenticated with `aad`.
+fn decrypt_value(text: &str, aad: &str, cipher: &SopsCipher) -> Option<Value> {
+    let fields = text.strip_prefix("ENC[AES256_GCM,")?.strip_suffix(']')?;
+    let field = |name: &str| {
+        fields
+            .split(',')
+            .find_map(|field| field.strip_prefix(name)?.strip_prefix(':'))
+    };
+    let decode = |name| BASE64_STANDARD.decode(field(name)?).ok();
+
+    let iv = decode("iv")?;
+    if iv.len() != 32 {
+        return None;
+    }
+
+    let mut message = decode("data")?;
+    message.extend(decode("tag")?);
+    let plaintext = cipher
+        .decrypt(
+            iv.as_slice().into(),
+            Payload {
+                msg: &message,
+                aad: aad.as_bytes(),
+            },
+        )
+        .ok()?;
+    let plaintext = String::from_utf8(plaintext).ok()?;
+
+    match field("type")? {
+        "int" => plaintext.parse::<i64>().ok().map(Value::from),
+        "float" => plaintext.parse::<f64>().ok().map(Value::from),
+        "bool" => plaintext
+            .to_lowercase()
+            .parse::<bool>()
+            .ok()
+            .map(Value::from),
+        _ => Some(Value::String(plaintext)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn encrypt(cipher: &SopsCipher, plaintext: &str, aad: &str, kind: &str) -> String {
+        let iv = [7; 32];
+        let mut data = cipher
+            .encrypt(
+                (&iv).into(),
+                Payload {
+                    msg: plaintext.as_bytes(),
+                    aad: aad.as_bytes(),
+                },
+            )
+            .unwrap();
+        let tag = data.split_off(data.len() - 16);
+        format!(
+            "ENC[AES256_GCM,data:{},iv:{},tag:{},type:{kind}]",
+            BASE64_STANDARD.encode(data),
+            BASE64_STANDARD.encode(iv),
+            BASE64_STANDARD.encode(tag)
+        )
+    }
+
+    #[test]
+    fn decrypts_age_files() {
+        let identity = age::x25519::Identity::generate();
+        let recipient = identity.to_public();
+        let encryptor = age::Encryptor::with_recipients(std::iter::once(&recipient as _)).unwrap();
+        let mut contents = Vec::new();
+        let mut writer = encryptor.wrap_output(&mut contents).unwrap();
+        writer.write_all(b"token = \"secret\"\n").unwrap();
+        writer.finish().unwrap();
+
+        assert!(is_encrypted(&contents));
+        assert!(!is_encrypted(b"token = \"secret\"\n"));
+        let identities: Vec<Box<dyn Identity>> = vec![Box::new(identity)];
+        assert_eq!(
+            decrypt_age(&contents, &identities).unwrap(),
+            b"token = \"secret\"\n"
+        );
+    }
+
+    #[test]
+    fn decrypts_sops_values() {
+        let cipher = SopsCipher::new_from_slice(&[1; 32]).unwrap();
+        let mut tree: Value = serde_yaml::from_str(&format!(
+            "token: '{}'\nzones:\n  - name: '{}'\n    records:\n      - ttl: '{}'\n",
+            encrypt(&cipher, "secret", "token:", "str"),
+            encrypt(&cipher, "example.com", "zones:name:", "str"),
+            encrypt(&cipher, "300", "zones:records:ttl:", "int"),
+        ))
+        .unwrap();
+
+        let rules = Rules::new(&Value::Null).unwrap();
+        decrypt_values(
+            &mut tree,
+            &mut Vec::new(),
+            &cipher,
+            &rules,
+            &mut Sha512::new(),
+        )
+        .unwrap();
+        assert_eq!(tree["token"], "secret");
+        assert_eq!(tree["zones"][0]["name"], "example.com");
+        assert_eq!(tree["zones"][0]["records"][0]["ttl"], 300);
+
+        // A value moved to another key doesn't decrypt.
+        let moved = encrypt(&cipher, "secret", "token:", "str");
+        assert_eq!(decrypt_value(&moved, "other:", &cipher), None);
+    }
+
+    #[test]
+    fn checks_the_sops_mac() {
+        let identity = age::x25519::Identity::generate();
+        let recipient = identity.to_public();
+        let identities: Vec<Box<dyn Identity>> = vec![Box::new(identity)];
+        let key = [1; 32];
+        let cipher = SopsCipher::new_from_slice(&key).unwrap();
+
+        let encryptor = age::Encryptor::with_recipients(std::iter::once(&recipient as _)).unwrap();
+        let mut enc = Vec::new();
+        let armored =
+            age::armor::ArmoredWriter::wrap_output(&mut enc, age::armor::Format::AsciiArmor)
+                .unwrap();
+        let mut writer = encryptor.wrap_output(armored).unwrap();
+        writer.write_all(&key).unwrap();
+        writer.finish().unwrap().finish().unwrap();
+
+        let modified = "2024-05-01T12:00:00Z";
+        let mac = hex(&Sha512::digest(b"secret300home"));
+        let enc = String::from_utf8(enc).unwrap();
+        let mac = encrypt(&cipher, &mac, modified, "str");
+        let file = |values: &str| {
+            // The armored key is written as a double-quoted string, whose escapes are those of Rust's.
+            let metadata = format!(
+                "sops:\n  age: [{{recipient: {recipient}, enc: {enc:?}}}]\n  lastmodified: '{modified}'\n  \
+                 mac: '{mac}'\n  unencrypted_suffix: _unencrypted\n"
+            );
+            sops_metadata(format!("{values}\n{metadata}").as_bytes()).unwrap()
+        };
+
+        let token = encrypt(&cipher, "secret", "token:", "str");
+        let ttl = encrypt(&cipher, "300", "ttl:", "int");
+        let decrypted = decrypt_sops(
+            file(&format!(
+                "token: '{token}'\nttl: '{ttl}'\nname_unencrypted: home"
+            )),
+            &identities,
+        )
+        .unwrap();
+        assert!(decrypted.yaml);
+        assert_eq!(
+            decrypted.text,
+            "token: secret\nttl: 300\nname_unencrypted: home\n"
+        );
+
+        // A changed unencrypted value, or a value that should be encrypted but isn't, is refused.
+        let tampered = file(&format!(
+            "token: '{token}'\nttl: '{ttl}'\nname_unencrypted: nas"
+        ));
+        assert!(matches!(
+            decrypt_sops(tampered, &identities),
+            Err(DecryptError::Mac)
+        ));
+        let plaintext = file(&format!(
+            "token: secret\nttl: '{ttl}'\nname_unencrypted: home"
+        ));
+        assert!(matches!(
+            decrypt_sops(plaintext, &identities),
+            Err(DecryptError::Unencrypted(path)) if path == "token"
+        ));
+    }
+
+    #[test]
+    fn encrypts_like_sops() {
+        let path = |keys: &[&str]| keys.iter().map(|key| key.to_string()).collect::<Vec<_>>();
+
+        let rules = Rules::new(&Value::Null).unwrap();
+        assert!(rules.encrypts(&path(&["token"])));
+        assert!(!rules.encrypts(&path(&["zones_unencrypted", "name"])));
+
+        let metadata = serde_yaml::from_str("encrypted_regex: ^(token|password)$").unwrap();
+        let rules = Rules::new(&metadata).unwrap();
+        assert!(rules.encrypts(&path(&["token"])));
+        assert!(rules.encrypts(&path(&["mqtt", "password"])));
+        assert!(!rules.encrypts(&path(&["zones", "name"])));
+
+        let metadata = serde_yaml::from_str("encrypted_comment_regex: sops:enc").unwrap();
+        assert!(Rules::new(&metadata).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+//! Loading environment variables from a `.env` file, so that credentials can be kept out of unit and compose files.
+//!
+//! Each line is `KEY=VALUE`, optionally preceded by `export`. Values may be quoted: double-quoted values understand
+//! the escapes `\n`, `\"`, and `\\`, and single-quoted values are taken literally. Lines starting with `#` and text
+//! after ` #` in unquoted values are comments.
+
+use std::env;
+use std::error;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The path of the file loaded unless configured otherwise, relative to the working directory.
+pub const DEFAULT_PATH: &str = ".env";
+
+/// Which `.env` file to load.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Selection {
+    /// [`DEFAULT_PATH`], if it exists.
+    Default,
+
+    /// The given file, which must exist.
+    Path(PathBuf),
+
+    /// No file.
+    Disabled,
+}
+
+impl Selection {
+    /// Find the `--env-file <PATH>` and `--no-env-file` options in `args`, the command line without the program name.
+    ///
+    /// The command line parser can't do this itself, since it reads environment variables that the file may set.
+    pub fn from_args(args: impl IntoIterator<Item = OsString>) -> Self {
+        let mut selection = Self::Default;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                break;
+            } else if arg == "--no-env-file" {
+                return Self::Disabled;
+            } else if arg == "--env-file" {
+                if let Some(path) = args.next() {
+                    selection = Self::Path(path.into());
+                }
+            } else if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix("--env-file="))
+            {
+                selection = Self::Path(path.into());
+            }
+        }
+
+        selection
+    }
+}
+
+/// Errors that can occur when loading a `.env` file.
+#[derive(Debug)]
+pub enum EnvFileError {
+    /// The file could not be read.
+    Read(io::Error),
+
+    /// A line is not a `KEY=VALUE` assignment. Contains the line number, counting from one.
+    Syntax(usize),
+}
+
+impl fmt::Display for EnvFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Read(e) => write!(f, "Failed to read the file: {e}."),
+            Self::Syntax(line) => write!(f, "Line {line} is not a KEY=VALUE assignment."),
+        }
+    }
+}
+
+impl error::Error for EnvFileError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Read(e) => Some(e),
+            Self::Syntax(_) => None,
+        }
+    }
+}
+
+/// Parse the assignments of a `.env` file, in order.
+pub fn parse(text: &str) -> Result<Vec<(String, String)>, EnvFileError> {
+    let mut vars = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line.split_once('=').ok_or(EnvFileError::Syntax(i + 1))?;
+        let key = key.trim();
+        if !is_key(key) {
+            return Err(EnvFileError::Syntax(i + 1));
+        }
+
+        let value = parse_value(value.trim()).ok_or(EnvFileError::Syntax(i + 1))?;
+        vars.push((key.to_string(), value));
+    }
+
+    Ok(vars)
+}
+
+/// Set the variables assigned in the `.env` file at `path` that aren't already set, so that the real environment
+/// takes precedence. Returns how many were set.
+pub fn load(path: &Path) -> Result<usize, EnvFileError> {
+    let text = fs::read_to_string(path).map_err(EnvFileError::Read)?;
+    let mut set = 0;
+    for (key, value) in parse(&text)? {
+        if env::var_os(&key).is_none() {
+            env::set_var(key, value);
+            set += 1;
+        }
+    }
+
+    Ok(set)
+}
+
+fn is_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_value(value: &str) -> Option<String> {
+    if let Some(quoted) = value.strip_prefix('\'') {
+        return quoted.strip_suffix('\'').map(str::to_string);
+    }
+
+    let Some(quoted) = value.strip_prefix('"') else {
+        let value = value.split_once(" #").map_or(value, |(value, _)| value);
+        return Some(value.trim_end().to_string());
+    };
+
+    let quoted = quoted.strip_suffix('"')?;
+    let mut unescaped = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                'n' => unescaped.push('\n'),
+                c => unescaped.push(c),
+            },
+            c => unescaped.push(c),
+        }
+    }
+
+    Some(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_assignments() {
+        let text = r#"
+# Cloudflare
+CF_DDNS_ZONE_NAME=example.com
+export CF_DDNS_RECORD_NAME = home # the NAS
+CF_DDNS_TOKEN="se\"cr\\et"
+CF_DDNS_SOURCE='upnp,worker # not a comment'
+CF_DDNS_URL=
+"#;
+
+        assert_eq!(
+            parse(text).unwrap(),
+            vars(&[
+                ("CF_DDNS_ZONE_NAME", "example.com"),
+                ("CF_DDNS_RECORD_NAME", "home"),
+                ("CF_DDNS_TOKEN", r#"se"cr\et"#),
+                ("CF_DDNS_SOURCE", "upnp,worker # not a comment"),
+                ("CF_DDNS_URL", ""),
+            ])
+        );
+    }
+
+    #[test]
+    fn selects_from_args() {
+        let select = |args: &[&str]| Selection::from_args(args.iter().map(OsString::from));
+        assert_eq!(select(&["--zone-name", "example.com"]), Selection::Default);
+        assert_eq!(
+            select(&["--env-file", "/run/secrets/env", "--debug"]),
+            Selection::Path("/run/secrets/env".into())
+        );
+        assert_eq!(
+            select(&["--env-file=a.env", "--env-file=b.env"]),
+            Selection::Path("b.env".into())
+        );
+        assert_eq!(
+            select(&["--env-file=a.env", "--no-env-file"]),
+            Selection::Disabled
+        );
+        assert_eq!(select(&["--", "--no-env-file"]), Selection::Default);
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert!(matches!(
+            parse("A=1\nnot an assignment\n"),
+            Err(EnvFileError::Syntax(2))
+        ));
+        assert!(matches!(parse("1A=1"), Err(EnvFileError::Syntax(1))));
+        assert!(matches!(
+            parse("A=\"unterminated"),
+            Err(EnvFileError::Syntax(1))
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+//! Running a command that prints the IP address, for setups no other source supports.
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::net::IpAddr;
+use std::process::{Command, ExitStatus, Stdio};
+
+use crate::ip::{self, InvalidIpError};
+use crate::source::{IpSource, SourceError};
+
+/// Errors that can occur when running a command for the IP address.
+#[derive(Debug)]
+pub enum ExecError {
+    /// The command could not be started.
+    Spawn(io::Error),
+
+    /// The command exited unsuccessfully. Contains its exit status and standard error.
+    Failed(ExitStatus, String),
+
+    /// The command's standard output was not a valid IP address.
+    Invalid(InvalidIpError),
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Spawn(e) => write!(f, "Failed to run the command: {e}."),
+            Self::Failed(status, stderr) => {
+                write!(f, "The command exited with {status}")?;
+                match stderr.trim() {
+                    "" => write!(f, "."),
+                    stderr => write!(f, ": {stderr}"),
+                }
+            }
+            Self::Invalid(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl error::Error for ExecError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Spawn(e) => Some(e),
+            Self::Failed(_, _) => None,
+            Self::Invalid(e) => Some(e),
+        }
+    }
+}
+
+/// An [`IpSource`] that runs a command with the system shell and parses its standard output as the IP address.
+pub struct ExecSource {
+    name: String,
+    command: String,
+}
+
+impl ExecSource {
+    /// Create a source running `command`.
+    pub fn new(command: impl Into<String>) -> Self {
+        let command = command.into();
+        Self {
+            name: format!("exec:{command}"),
+            command,
+        }
+    }
+
+    fn run(&self) -> Result<IpAddr, ExecError> {
+        let output = shell(&self.command)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(ExecError::Spawn)?;
+
+        if !output.status.success() {
+            return Err(ExecError::Failed(
+                output.status,
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            ));
+        }
+
+        ip::parse(&String::from_utf8_lossy(&output.stdout)).map_err(ExecError::Invalid)
+    }
+}
+
+impl IpSource for ExecSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_ip(&self) -> Result<IpAddr, SourceError> {
+        Ok(self.run()?)
+    }
+}
+
+/// A command running `command` with the system shell.
+pub(crate) fn shell(command: &str) -> Command {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+
+    shell.arg(command);
+    shell
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn parses_stdout() {
+        assert_eq!(
+            ExecSource::new("echo 203.0.113.7").run().unwrap(),
+            IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7))
+        );
+    }
+
+    #[test]
+    fn reports_failure() {
+        match ExecSource::new("echo oops >&2; exit 3").run() {
+            Err(ExecError::Failed(status, stderr)) => {
+                assert_eq!(status.code(), Some(3));
+                assert_eq!(stderr.trim(), "oops");
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+}
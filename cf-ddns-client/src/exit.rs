@@ -0,0 +1,128 @@
+//! The exit codes of the client, so that scripts and monitoring can tell classes of failures apart.
+
+use std::process::ExitCode;
+
+use cf_ddns_cloudflare::{ClientError, CloudflareError};
+
+/// The outcome of running the client, as reported by its exit code.
+///
+/// Code 2 is left for invalid command lines, which are reported by the argument parser.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exit {
+    /// Everything succeeded.
+    Success = 0,
+
+    /// A failure outside the other classes, e.g. an unreadable file or a failed health check.
+    Failure = 1,
+
+    /// The records were updated, but resolvers didn't answer with the new IP address in time.
+    NotPropagated = 3,
+
+    /// Another run holds the lock.
+    Locked = 4,
+
+    /// The IP address could not be discovered, or the discovered address can't be published.
+    IpDiscovery = 5,
+
+    /// The API token could not be read or sent, Cloudflare rejected it, or it lacks the needed permissions.
+    Auth = 6,
+
+    /// The zone or the records could not be found.
+    NotFound = 7,
+
+    /// Any other error from the Cloudflare API, including rate limiting and conflicting changes.
+    Api = 8,
+
+    /// Nothing needed changing. Only reported when asked for, since it isn't a failure.
+    NoChange = 9,
+}
+
+impl Exit {
+    /// The exit code of the class of `err`.
+    pub fn from_cloudflare<T>(err: &CloudflareError<T>) -> Self {
+        if err.is_auth_error() {
+            Self::Auth
+        } else if err.is_record_not_found() || matches!(err, CloudflareError::EmptyResult) {
+            Self::NotFound
+        } else {
+            Self::Api
+        }
+    }
+
+    /// The exit code of the class of `err`, a failure to create a Cloudflare client.
+    pub fn from_client(err: &ClientError) -> Self {
+        match err {
+            ClientError::InvalidToken(_) => Self::Auth,
+            ClientError::Build(_) | ClientError::Runtime(_) => Self::Failure,
+        }
+    }
+
+    /// The name of the class, e.g. `ip-discovery` for [`Self::IpDiscovery`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Failure => "failure",
+            Self::NotPropagated => "not-propagated",
+            Self::Locked => "locked",
+            Self::IpDiscovery => "ip-discovery",
+            Self::Auth => "auth",
+            Self::NotFound => "not-found",
+            Self::Api => "api",
+            Self::NoChange => "no-change",
+        }
+    }
+
+    /// Whether a later run may succeed without changing anything: when the lock is released, the network or the
+    /// address sources recover, or resolvers catch up. Other classes need the setup to be fixed.
+    pub fn is_retryable(self) -> bool {
+        matches!(self, Self::NotPropagated | Self::Locked | Self::IpDiscovery)
+    }
+}
+
+impl From<Exit> for ExitCode {
+    fn from(exit: Exit) -> Self {
+        ExitCode::from(exit as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cf_ddns_cloudflare::{CfResponseInfo, NoApiSpecific};
+
+    use super::*;
+
+    fn error(code: i32) -> CloudflareError<NoApiSpecific> {
+        CloudflareError::Error(vec![CfResponseInfo {
+            code,
+            message: String::new(),
+        }])
+    }
+
+    #[test]
+    fn classifies_cloudflare_errors() {
+        assert_eq!(Exit::from_cloudflare(&error(10000)), Exit::Auth);
+        assert_eq!(Exit::from_cloudflare(&error(81044)), Exit::NotFound);
+        assert_eq!(
+            Exit::from_cloudflare(&CloudflareError::<NoApiSpecific>::EmptyResult),
+            Exit::NotFound
+        );
+        assert_eq!(Exit::from_cloudflare(&error(1004)), Exit::Api);
+    }
+
+    #[test]
+    fn classifies_client_errors() {
+        let err = cf_ddns_cloudflare::Cloudflare::try_new("bad\ntoken")
+            .err()
+            .unwrap();
+        assert_eq!(Exit::from_client(&err), Exit::Auth);
+        let err = ClientError::Runtime(std::io::ErrorKind::Other.into());
+        assert_eq!(Exit::from_client(&err), Exit::Failure);
+    }
+
+    #[test]
+    fn names_classes() {
+        assert_eq!(Exit::IpDiscovery.name(), "ip-discovery");
+        assert!(Exit::Locked.is_retryable());
+        assert!(!Exit::Auth.is_retryable());
+    }
+}
@@ -0,0 +1,116 @@
+//! Reading the IP address from a file or standard input, as written by other tools such as pppd hooks.
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+use crate::ip::{self, InvalidIpError};
+use crate::source::{IpSource, SourceError};
+
+/// Errors that can occur when reading the IP address from a file.
+#[derive(Debug)]
+pub enum FileError {
+    /// The file could not be read.
+    Read(io::Error),
+
+    /// The file did not contain a valid IP address.
+    Invalid(InvalidIpError),
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Read(e) => write!(f, "Failed to read the address: {e}."),
+            Self::Invalid(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl error::Error for FileError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Read(e) => Some(e),
+            Self::Invalid(e) => Some(e),
+        }
+    }
+}
+
+/// An [`IpSource`] that reads the IP address from a file, or from standard input.
+pub struct FileSource {
+    name: String,
+    path: Option<PathBuf>,
+}
+
+impl FileSource {
+    /// Create a source reading the file at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        Self {
+            name: format!("file:{}", path.display()),
+            path: Some(path),
+        }
+    }
+
+    /// Create a source reading standard input.
+    pub fn stdin() -> Self {
+        Self {
+            name: "stdin".to_string(),
+            path: None,
+        }
+    }
+
+    fn read(&self) -> Result<IpAddr, FileError> {
+        let text = match &self.path {
+            Some(path) => fs::read_to_string(path).map_err(FileError::Read)?,
+            None => {
+                let mut text = String::new();
+                io::stdin()
+                    .read_to_string(&mut text)
+                    .map_err(FileError::Read)?;
+
+                text
+            }
+        };
+
+        ip::parse(&text).map_err(FileError::Invalid)
+    }
+}
+
+impl IpSource for FileSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_ip(&self) -> Result<IpAddr, SourceError> {
+        Ok(self.read()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use crate::paths::tests::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn reads_file() {
+        let dir = TempDir::new("file");
+        let path = dir.join("wan_ip");
+        fs::write(&path, "203.0.113.7\n").unwrap();
+        let ip = FileSource::new(&path).read();
+        assert_eq!(ip.unwrap(), IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)));
+    }
+
+    #[test]
+    fn missing_file_fails() {
+        assert!(matches!(
+            FileSource::new("/nonexistent/wan_ip").read(),
+            Err(FileError::Read(_))
+        ));
+    }
+}
@@ -0,0 +1,153 @@
+//! Asking an [AVM Fritz!Box](https://avm.de/) for its WAN address over its TR-064 / IGD interface.
+//!
+//! Fritz!Boxes answer the UPnP `GetExternalIPAddress` action, and AVM's `X_AVM_DE_GetExternalIPv6Address` for IPv6,
+//! at a fixed control URL on port 49000, so unlike the generic [UPnP source](crate::upnp) no discovery is needed.
+
+use std::error;
+use std::fmt;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use reqwest::Url;
+
+use crate::http;
+use crate::source::{Family, IpSource, SourceError};
+use crate::upnp::{element, soap};
+
+/// The host name Fritz!Boxes answer to on the LAN.
+pub const DEFAULT_HOST: &str = "fritz.box";
+
+/// The service providing the WAN address.
+const SERVICE: &str = "urn:schemas-upnp-org:service:WANIPConnection:1";
+
+/// The path of the service's control URL.
+const CONTROL_PATH: &str = "/igdupnp/control/WANIPConn1";
+
+/// How long to wait for the Fritz!Box to answer.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Errors that can occur when asking a Fritz!Box for its WAN address.
+#[derive(Debug)]
+pub enum FritzBoxError {
+    /// The host name does not form a valid URL. Contains the host.
+    InvalidHost(String),
+
+    /// The request failed or the Fritz!Box answered with an error.
+    RequestFailed(reqwest::Error),
+
+    /// The Fritz!Box's answer did not contain a valid IP address, e.g. because it isn't connected.
+    NoAddress,
+}
+
+impl fmt::Display for FritzBoxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidHost(host) => write!(f, "{host:?} is not a valid Fritz!Box host."),
+            Self::RequestFailed(e) => write!(f, "Failed to query the Fritz!Box: {e}."),
+            Self::NoAddress => write!(f, "The Fritz!Box did not return a WAN address."),
+        }
+    }
+}
+
+impl error::Error for FritzBoxError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::RequestFailed(e) => Some(e),
+            Self::InvalidHost(_) | Self::NoAddress => None,
+        }
+    }
+}
+
+/// An [`IpSource`] that asks a Fritz!Box for its WAN address.
+pub struct FritzBoxSource {
+    host: String,
+    family: Family,
+}
+
+impl FritzBoxSource {
+    /// Create a source asking the Fritz!Box at `host`, e.g. [`DEFAULT_HOST`], for its address of `family`.
+    pub fn new(host: impl Into<String>, family: Family) -> Self {
+        Self {
+            host: host.into(),
+            family,
+        }
+    }
+
+    fn query(&self) -> Result<IpAddr, FritzBoxError> {
+        let control = Url::parse(&format!("http://{}:49000{CONTROL_PATH}", self.host))
+            .map_err(|_| FritzBoxError::InvalidHost(self.host.clone()))?;
+
+        let client = http::client_builder()
+            .timeout(TIMEOUT)
+            .build()
+            .map_err(FritzBoxError::RequestFailed)?;
+
+        let (action, field) = action(self.family);
+        let response =
+            soap(&client, control, SERVICE, action).map_err(FritzBoxError::RequestFailed)?;
+
+        parse_address(&response, field)
+    }
+}
+
+impl IpSource for FritzBoxSource {
+    fn name(&self) -> &str {
+        "fritzbox"
+    }
+
+    fn get_ip(&self) -> Result<IpAddr, SourceError> {
+        Ok(self.query()?)
+    }
+}
+
+/// The SOAP action asking for the address of `family`, and the element of the response containing it.
+fn action(family: Family) -> (&'static str, &'static str) {
+    match family {
+        Family::V4 => ("GetExternalIPAddress", "NewExternalIPAddress"),
+        Family::V6 => ("X_AVM_DE_GetExternalIPv6Address", "NewExternalIPv6Address"),
+    }
+}
+
+/// Parse the address in the `field` element of a SOAP response. A disconnected Fritz!Box reports an empty or
+/// unspecified address.
+fn parse_address(response: &str, field: &str) -> Result<IpAddr, FritzBoxError> {
+    element(response, field)
+        .and_then(|ip| ip.trim().parse::<IpAddr>().ok())
+        .filter(|ip| !ip.is_unspecified())
+        .ok_or(FritzBoxError::NoAddress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ipv6_response() {
+        let response = r#"<s:Envelope><s:Body><u:X_AVM_DE_GetExternalIPv6AddressResponse xmlns:u="urn:schemas-upnp-org:service:WANIPConnection:1"><NewExternalIPv6Address>2001:db8::1</NewExternalIPv6Address><NewPrefixLength>64</NewPrefixLength></u:X_AVM_DE_GetExternalIPv6AddressResponse></s:Body></s:Envelope>"#;
+        assert_eq!(
+            parse_address(response, "NewExternalIPv6Address").unwrap(),
+            "2001:db8::1".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn asks_for_the_address_of_the_family() {
+        assert_eq!(
+            action(Family::V4),
+            ("GetExternalIPAddress", "NewExternalIPAddress")
+        );
+        assert_eq!(
+            action(Family::V6),
+            ("X_AVM_DE_GetExternalIPv6Address", "NewExternalIPv6Address")
+        );
+    }
+
+    #[test]
+    fn disconnected_has_no_address() {
+        let response = "<NewExternalIPAddress></NewExternalIPAddress>";
+        assert!(matches!(
+            parse_address(response, "NewExternalIPAddress"),
+            Err(FritzBoxError::NoAddress)
+        ));
+    }
+}
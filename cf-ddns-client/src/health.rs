@@ -0,0 +1,164 @@
+//! Checking that the service exposed by the records is up before pointing the records at it.
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::net::TcpStream;
+use std::str::FromStr;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+
+use crate::http::Settings;
+use crate::resolve;
+
+/// How long to wait for a health check, unless configured otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A check of a service's health.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HealthCheck {
+    /// Connect to `host:port` over TCP, written `tcp:<HOST>:<PORT>`.
+    Tcp(String),
+
+    /// Send a `GET` request to an `http://` or `https://` URL and expect a successful status.
+    Http(String),
+}
+
+impl HealthCheck {
+    /// Run the check, failing if it hasn't passed within `timeout`.
+    ///
+    /// HTTP checks are sent with the proxy, certificates, and user agent of `settings`.
+    pub fn check(&self, settings: &Settings, timeout: Duration) -> Result<(), HealthError> {
+        match self {
+            Self::Tcp(address) => {
+                let addrs = resolve::lookup_address(address).map_err(HealthError::Connect)?;
+                let mut last =
+                    io::Error::new(io::ErrorKind::NotFound, "no addresses to connect to");
+                for addr in addrs {
+                    match TcpStream::connect_timeout(&addr, timeout) {
+                        Ok(_) => return Ok(()),
+                        Err(err) => last = err,
+                    }
+                }
+
+                Err(HealthError::Connect(last))
+            }
+            Self::Http(url) => {
+                let response = settings
+                    .builder()
+                    .timeout(timeout)
+                    .build()
+                    .and_then(|client| client.get(url).send())
+                    .map_err(HealthError::RequestFailed)?;
+
+                match response.status() {
+                    status if status.is_success() => Ok(()),
+                    status => Err(HealthError::Status(status)),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for HealthCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Tcp(address) => write!(f, "tcp:{address}"),
+            Self::Http(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+impl FromStr for HealthCheck {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(address) = s.strip_prefix("tcp:") {
+            match address.rsplit_once(':') {
+                Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+                    Ok(Self::Tcp(address.to_string()))
+                }
+                _ => Err(format!("expected tcp:<HOST>:<PORT>, got {s}")),
+            }
+        } else if s.starts_with("http://") || s.starts_with("https://") {
+            Ok(Self::Http(s.to_string()))
+        } else {
+            Err(format!(
+                "expected tcp:<HOST>:<PORT> or an http:// or https:// URL, got {s}"
+            ))
+        }
+    }
+}
+
+/// Errors that can occur when checking a service's health.
+#[derive(Debug)]
+pub enum HealthError {
+    /// The TCP connection could not be established.
+    Connect(io::Error),
+
+    /// The HTTP request failed.
+    RequestFailed(reqwest::Error),
+
+    /// The HTTP request was answered with an unsuccessful status.
+    Status(StatusCode),
+}
+
+impl fmt::Display for HealthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Connect(e) => write!(f, "Failed to connect: {e}."),
+            Self::RequestFailed(e) => write!(f, "Failed to send request: {e}."),
+            Self::Status(status) => write!(f, "The service answered with {status}."),
+        }
+    }
+}
+
+impl error::Error for HealthError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Connect(e) => Some(e),
+            Self::RequestFailed(e) => Some(e),
+            Self::Status(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn parses_checks() {
+        assert_eq!(
+            "tcp:nas.lan:443".parse(),
+            Ok(HealthCheck::Tcp("nas.lan:443".to_string()))
+        );
+        assert_eq!(
+            "tcp:[::1]:22".parse(),
+            Ok(HealthCheck::Tcp("[::1]:22".to_string()))
+        );
+        assert_eq!(
+            "http://nas.lan/health".parse(),
+            Ok(HealthCheck::Http("http://nas.lan/health".to_string()))
+        );
+        assert!("tcp:nas.lan".parse::<HealthCheck>().is_err());
+        assert!("nas.lan:443".parse::<HealthCheck>().is_err());
+    }
+
+    #[test]
+    fn tcp_check() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let check = HealthCheck::Tcp(address);
+        assert!(check.check(&Settings::default(), DEFAULT_TIMEOUT).is_ok());
+
+        drop(listener);
+        assert!(matches!(
+            check.check(&Settings::default(), DEFAULT_TIMEOUT),
+            Err(HealthError::Connect(_))
+        ));
+    }
+}
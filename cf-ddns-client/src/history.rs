@@ -0,0 +1,134 @@
+//! An append-only log of the IP addresses observed and the updates made, one JSON object per line.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::state::Outcome;
+
+/// The file name of the history within the [state directory](crate::paths::state_dir).
+pub const FILE_NAME: &str = "history.jsonl";
+
+/// A run that observed an IP address and updated, or tried to update, a set of records.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Event {
+    /// When the run finished, in RFC 3339 format.
+    pub time: String,
+
+    /// A description of the records, as in the [state](crate::state::State).
+    pub records: String,
+
+    /// The IP address observed by the previous run, if known.
+    pub old_ip: Option<IpAddr>,
+
+    /// The IP address observed by this run.
+    pub new_ip: IpAddr,
+
+    /// The outcome of the run.
+    pub result: Outcome,
+}
+
+impl Event {
+    /// Create an event of a run that just finished.
+    pub fn new(
+        records: impl Into<String>,
+        old_ip: Option<IpAddr>,
+        new_ip: IpAddr,
+        result: Outcome,
+    ) -> Self {
+        Self {
+            time: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            records: records.into(),
+            old_ip,
+            new_ip,
+            result,
+        }
+    }
+
+    /// When the run finished, if [`Self::time`] is valid.
+    pub fn time(&self) -> Option<SystemTime> {
+        humantime::parse_rfc3339_weak(&self.time).ok()
+    }
+
+    /// Whether the run observed a different IP address than the previous one.
+    pub fn is_change(&self) -> bool {
+        self.old_ip.is_some_and(|old_ip| old_ip != self.new_ip)
+    }
+}
+
+/// Append `event` to the history at `path`, creating it and its parent directories if needed.
+pub fn append(path: &Path, event: &Event) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut line = serde_json::to_string(event)?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(line.as_bytes())
+}
+
+/// Read the events in the history at `path`, oldest first. A missing history is empty, and malformed lines are
+/// skipped.
+pub fn read(path: &Path) -> io::Result<Vec<Event>> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(parse(&text)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
+}
+
+/// The default path of the history, if a state directory can be determined.
+pub fn default_path() -> Option<PathBuf> {
+    crate::paths::state_dir().map(|dir| dir.join(FILE_NAME))
+}
+
+fn parse(text: &str) -> Vec<Event> {
+    text.lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use crate::paths::tests::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn skips_malformed_lines() {
+        let text = r#"{"time":"2024-01-01T00:00:00Z","records":"example.com/home/A","old_ip":null,"new_ip":"203.0.113.7","result":"updated"}
+not json
+{"time":"2024-01-02T00:00:00Z","records":"example.com/home/A","old_ip":"203.0.113.7","new_ip":"203.0.113.8","result":"failed"}
+"#;
+
+        let events = parse(text);
+        assert_eq!(events.len(), 2);
+        assert!(!events[0].is_change());
+        assert!(events[1].is_change());
+        assert_eq!(events[1].result, Outcome::Failed);
+        assert_eq!(events[1].new_ip, IpAddr::V4(Ipv4Addr::new(203, 0, 113, 8)));
+        assert!(events[1].time().is_some());
+    }
+
+    #[test]
+    fn appends_events() {
+        let dir = TempDir::new("history");
+        let path = dir.join("history.jsonl");
+
+        let ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+        let event = Event::new("example.com/home/A", None, ip, Outcome::Updated);
+        append(&path, &event).unwrap();
+        append(&path, &event).unwrap();
+        assert_eq!(read(&path).unwrap(), [event.clone(), event]);
+    }
+}
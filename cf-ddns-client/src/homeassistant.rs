@@ -0,0 +1,143 @@
+//! Telling Home Assistant the address, for `--home-assistant-url`.
+//!
+//! With a long-lived access token, the state of a sensor is set through the
+//! [REST API](https://developers.home-assistant.io/docs/api/rest/) to the address, with the time it last changed and
+//! the record as attributes. Without one, the [default JSON object](crate::notify::to_json) of the run is posted to a
+//! webhook trigger instead, for an automation to handle.
+
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use serde_json::{json, Value};
+
+use crate::history::Event;
+use crate::http::Settings;
+use crate::notify::{self, Notifier, NotifyError};
+use crate::state::Outcome;
+
+/// The sensor whose state is set unless configured otherwise.
+pub const DEFAULT_ENTITY: &str = "sensor.public_ip";
+
+/// How the address is sent to Home Assistant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// Set the state of the sensor `entity` with a long-lived access `token`.
+    Entity {
+        /// The ID of the sensor, e.g. [`DEFAULT_ENTITY`].
+        entity: String,
+
+        /// The long-lived access token.
+        token: String,
+    },
+
+    /// Post to the webhook trigger with this ID.
+    Webhook(String),
+}
+
+/// A Home Assistant instance.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HomeAssistant {
+    url: String,
+    target: Target,
+}
+
+impl HomeAssistant {
+    /// The instance at `url`, like `http://homeassistant.local:8123`, told the address through `target`.
+    pub fn new(url: &str, target: Target) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            target,
+        }
+    }
+
+    /// The new state of the sensor for `event`, given its current `state`. The time the address last changed is
+    /// kept while it stays the same.
+    fn state(event: &Event, state: &Value) -> Value {
+        let new_ip = event.new_ip.to_string();
+        let last_changed = match state["attributes"]["last_changed"].as_str() {
+            Some(last_changed) if state["state"] == new_ip.as_str() => last_changed.to_string(),
+            _ => event.time.clone(),
+        };
+
+        json!({
+            "state": new_ip,
+            "attributes": {
+                "friendly_name": "Public IP",
+                "icon": "mdi:ip-network",
+                "last_changed": last_changed,
+                "record": event.records,
+            },
+        })
+    }
+}
+
+impl Notifier for HomeAssistant {
+    fn name(&self) -> &str {
+        "Home Assistant"
+    }
+
+    fn wants(&self, outcome: Outcome) -> bool {
+        match self.target {
+            Target::Entity { .. } => outcome != Outcome::Failed,
+            Target::Webhook(_) => true,
+        }
+    }
+
+    fn send(&self, event: &Event, settings: &Settings) -> Result<(), NotifyError> {
+        let client = settings.builder().build()?;
+        let response = match &self.target {
+            Target::Entity { entity, token } => {
+                let url = format!("{}/api/states/{entity}", self.url);
+                let bearer = format!("Bearer {token}");
+
+                // A sensor that doesn't exist yet is created by setting its state.
+                let current = client
+                    .get(&url)
+                    .header(AUTHORIZATION, &bearer)
+                    .send()?
+                    .json()
+                    .unwrap_or_default();
+
+                client
+                    .post(&url)
+                    .header(AUTHORIZATION, &bearer)
+                    .json(&Self::state(event, &current))
+                    .send()?
+            }
+            Target::Webhook(id) => client
+                .post(format!("{}/api/webhook/{id}", self.url))
+                .header(CONTENT_TYPE, "application/json")
+                .body(notify::to_json(event))
+                .send()?,
+        };
+
+        notify::check(response, "message")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::*;
+
+    #[test]
+    fn keeps_the_time_of_the_last_change() {
+        let ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+        let mut event = Event::new("example.com/home/A", None, ip, Outcome::Unchanged);
+        event.time = "2024-01-02T00:00:00Z".to_string();
+
+        let current = json!({
+            "state": "203.0.113.7",
+            "attributes": { "last_changed": "2024-01-01T00:00:00Z" },
+        });
+        let state = HomeAssistant::state(&event, &current);
+        assert_eq!(state["state"], "203.0.113.7");
+        assert_eq!(state["attributes"]["last_changed"], "2024-01-01T00:00:00Z");
+
+        let changed = json!({ "state": "203.0.113.6", "attributes": current["attributes"] });
+        let state = HomeAssistant::state(&event, &changed);
+        assert_eq!(state["attributes"]["last_changed"], "2024-01-02T00:00:00Z");
+
+        let state = HomeAssistant::state(&event, &Value::Null);
+        assert_eq!(state["attributes"]["last_changed"], "2024-01-02T00:00:00Z");
+    }
+}
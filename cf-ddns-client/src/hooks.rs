@@ -0,0 +1,169 @@
+//! Running commands around an update, for `--pre-update`, `--on-change`, and `--on-failure`, e.g. to restart
+//! WireGuard or update firewall rules when the address changes.
+//!
+//! The commands are run with the system shell, with these environment variables describing the update:
+//!
+//! - `CF_DDNS_RECORD`: the records, as in the [history](crate::history), e.g. `example.com/home/A`.
+//! - `CF_DDNS_OLD_IP`: the address observed by the previous run, or empty if unknown.
+//! - `CF_DDNS_NEW_IP`: the address being published.
+//! - `CF_DDNS_OUTCOME`: `updated` or `failed`, except for `--pre-update`.
+//! - `CF_DDNS_TIMESTAMP`: when the update finished, in RFC 3339 format, except for `--pre-update`.
+//!
+//! Their standard output is sent to standard error, so that it doesn't mix with `--output json`.
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::net::IpAddr;
+use std::process::{ExitStatus, Stdio};
+
+use crate::exec;
+use crate::history::Event;
+use crate::http::Settings;
+use crate::notify::{Notifier, NotifyError};
+use crate::state::Outcome;
+
+/// Errors that can occur when running a hook.
+#[derive(Debug)]
+pub enum HookError {
+    /// The command could not be started.
+    Spawn(io::Error),
+
+    /// The command exited unsuccessfully. Contains its exit status.
+    Failed(ExitStatus),
+}
+
+impl fmt::Display for HookError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Spawn(e) => write!(f, "Failed to run the command: {e}."),
+            Self::Failed(status) => write!(f, "The command exited with {status}."),
+        }
+    }
+}
+
+impl error::Error for HookError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Spawn(e) => Some(e),
+            Self::Failed(_) => None,
+        }
+    }
+}
+
+/// A command run after each update with a given outcome, as a [`Notifier`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hook {
+    name: String,
+    command: String,
+    outcome: Outcome,
+}
+
+impl Hook {
+    /// Run `command` when the records are updated.
+    pub fn on_change(command: impl Into<String>) -> Self {
+        Self {
+            name: "the on-change hook".to_string(),
+            command: command.into(),
+            outcome: Outcome::Updated,
+        }
+    }
+
+    /// Run `command` when updating the records fails.
+    pub fn on_failure(command: impl Into<String>) -> Self {
+        Self {
+            name: "the on-failure hook".to_string(),
+            command: command.into(),
+            outcome: Outcome::Failed,
+        }
+    }
+}
+
+impl Notifier for Hook {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn wants(&self, outcome: Outcome) -> bool {
+        outcome == self.outcome
+    }
+
+    fn send(&self, event: &Event, _settings: &Settings) -> Result<(), NotifyError> {
+        let env = [
+            ("CF_DDNS_OUTCOME", event.result.to_string()),
+            ("CF_DDNS_TIMESTAMP", event.time.clone()),
+        ];
+
+        run(
+            &self.command,
+            &event.records,
+            event.old_ip,
+            event.new_ip,
+            &env,
+        )
+        .map_err(NotifyError::Hook)
+    }
+}
+
+/// Run the `--pre-update` `command` before updating `records` from `old_ip`, if known, to `new_ip`.
+pub fn pre_update(
+    command: &str,
+    records: &str,
+    old_ip: Option<IpAddr>,
+    new_ip: IpAddr,
+) -> Result<(), HookError> {
+    run(command, records, old_ip, new_ip, &[])
+}
+
+fn run(
+    command: &str,
+    records: &str,
+    old_ip: Option<IpAddr>,
+    new_ip: IpAddr,
+    env: &[(&str, String)],
+) -> Result<(), HookError> {
+    let status = exec::shell(command)
+        .env("CF_DDNS_RECORD", records)
+        .env(
+            "CF_DDNS_OLD_IP",
+            old_ip.map(|ip| ip.to_string()).unwrap_or_default(),
+        )
+        .env("CF_DDNS_NEW_IP", new_ip.to_string())
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdin(Stdio::null())
+        .stdout(io::stderr())
+        .status()
+        .map_err(HookError::Spawn)?;
+
+    match status.success() {
+        true => Ok(()),
+        false => Err(HookError::Failed(status)),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn passes_the_update_in_the_environment() {
+        let old_ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 6));
+        let new_ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+        let check = r#"[ "$CF_DDNS_RECORD $CF_DDNS_OLD_IP $CF_DDNS_NEW_IP" = "example.com/home/A 203.0.113.6 203.0.113.7" ]"#;
+        pre_update(check, "example.com/home/A", Some(old_ip), new_ip).unwrap();
+
+        let hook =
+            Hook::on_change(r#"[ "$CF_DDNS_OUTCOME" = updated ] && [ -z "$CF_DDNS_OLD_IP" ]"#);
+        let event = Event::new("example.com/home/A", None, new_ip, Outcome::Updated);
+        assert!(hook.wants(Outcome::Updated));
+        assert!(!hook.wants(Outcome::Failed));
+        hook.send(&event, &Settings::default()).unwrap();
+
+        match pre_update("exit 3", "example.com/home/A", None, new_ip) {
+            Err(HookError::Failed(status)) => assert_eq!(status.code(), Some(3)),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Internationalized domain names, such as `bücher.example`, which Cloudflare stores in their ASCII form,
+//! `xn--bcher-kva.example`.
+//!
+//! Each label with characters outside ASCII is lowercased and encoded with Punycode (RFC 3492) behind the `xn--`
+//! prefix. Names are expected to be in Unicode normalization form C, as typed on most keyboards.
+
+const BASE: u32 = 36;
+const T_MIN: u32 = 1;
+const T_MAX: u32 = 26;
+const SKEW: u32 = 38;
+const DAMP: u32 = 700;
+const INITIAL_BIAS: u32 = 72;
+const INITIAL_N: u32 = 0x80;
+
+/// The prefix of the labels encoded with Punycode.
+const ACE_PREFIX: &str = "xn--";
+
+/// The ASCII form of `name`, in which labels with characters outside ASCII are lowercased and encoded. ASCII labels
+/// are returned unchanged.
+pub fn to_ascii(name: &str) -> String {
+    map_labels(name, |label| {
+        if label.is_ascii() {
+            return label.to_string();
+        }
+
+        let lower: Vec<char> = label.chars().flat_map(char::to_lowercase).collect();
+        format!("{ACE_PREFIX}{}", encode(&lower))
+    })
+}
+
+/// The Unicode form of `name`, for display, in which labels with the `xn--` prefix are decoded. Labels that can't
+/// be decoded are returned unchanged.
+pub fn to_unicode(name: &str) -> String {
+    map_labels(name, |label| {
+        label
+            .get(..ACE_PREFIX.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(ACE_PREFIX))
+            .and_then(|_| decode(&label[ACE_PREFIX.len()..]))
+            .unwrap_or_else(|| label.to_string())
+    })
+}
+
+fn map_labels(name: &str, map: impl Fn(&str) -> String) -> String {
+    name.split('.').map(map).collect::<Vec<_>>().join(".")
+}
+
+/// The threshold of the digit in position `k`, with the current `bias`.
+fn threshold(k: u32, bias: u32) -> u32 {
+    if k <= bias {
+        T_MIN
+    } else if k >= bias + T_MAX {
+        T_MAX
+    } else {
+        k - bias
+    }
+}
+
+/// Adapt the bias after encoding or decoding a code point.
+fn adapt(delta: u32, points: u32, first: bool) -> u32 {
+    let mut delta = if first { delta / DAMP } else { delta / 2 };
+    delta += delta / points;
+
+    let mut k = 0;
+    while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+        delta /= BASE - T_MIN;
+        k += BASE;
+    }
+
+    k + (BASE - T_MIN + 1) * delta / (delta + SKEW)
+}
+
+fn encode_digit(digit: u32) -> char {
+    match digit {
+        0..=25 => (b'a' + digit as u8) as char,
+        _ => (b'0' + (digit - 26) as u8) as char,
+    }
+}
+
+fn decode_digit(c: char) -> Option<u32> {
+    match c {
+        'a'..='z' => Some(c as u32 - 'a' as u32),
+        'A'..='Z' => Some(c as u32 - 'A' as u32),
+        '0'..='9' => Some(c as u32 - '0' as u32 + 26),
+        _ => None,
+    }
+}
+
+/// Encode a label with Punycode, without the `xn--` prefix.
+fn encode(input: &[char]) -> String {
+    let mut output: String = input.iter().filter(|c| c.is_ascii()).collect();
+    let basic = output.len() as u32;
+    if basic > 0 {
+        output.push('-');
+    }
+
+    let mut n = INITIAL_N;
+    let mut delta: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    let mut handled = basic;
+    while (handled as usize) < input.len() {
+        let m = input
+            .iter()
+            .map(|&c| c as u32)
+            .filter(|&c| c >= n)
+            .min()
+            .expect("an unhandled code point remains");
+        delta += (m - n) * (handled + 1);
+        n = m;
+
+        for &c in input {
+            let c = c as u32;
+            if c < n {
+                delta += 1;
+            }
+
+            if c != n {
+                continue;
+            }
+
+            let mut q = delta;
+            let mut k = BASE;
+            loop {
+                let t = threshold(k, bias);
+                if q < t {
+                    break;
+                }
+
+                output.push(encode_digit(t + (q - t) % (BASE - t)));
+                q = (q - t) / (BASE - t);
+                k += BASE;
+            }
+
+            output.push(encode_digit(q));
+            bias = adapt(delta, handled + 1, handled == basic);
+            delta = 0;
+            handled += 1;
+        }
+
+        delta += 1;
+        n += 1;
+    }
+
+    output
+}
+
+/// Decode a label encoded with Punycode, without the `xn--` prefix, or `None` if it isn't valid.
+fn decode(input: &str) -> Option<String> {
+    let (basic, encoded) = match input.rfind('-') {
+        Some(end) => (&input[..end], &input[end + 1..]),
+        None => ("", input),
+    };
+
+    if !basic.is_ascii() {
+        return None;
+    }
+
+    let mut output: Vec<char> = basic.chars().collect();
+    let mut n = INITIAL_N;
+    let mut i: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    let mut digits = encoded.chars();
+    while digits.as_str() != "" {
+        let old_i = i;
+        let mut w: u32 = 1;
+        let mut k = BASE;
+        loop {
+            let digit = decode_digit(digits.next()?)?;
+            i = i.checked_add(digit.checked_mul(w)?)?;
+            let t = threshold(k, bias);
+            if digit < t {
+                break;
+            }
+
+            w = w.checked_mul(BASE - t)?;
+            k += BASE;
+        }
+
+        let points = output.len() as u32 + 1;
+        bias = adapt(i - old_i, points, old_i == 0);
+        n = n.checked_add(i / points)?;
+        i %= points;
+        output.insert(i as usize, char::from_u32(n)?);
+        i += 1;
+    }
+
+    Some(output.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_names() {
+        assert_eq!(to_ascii("bücher.example"), "xn--bcher-kva.example");
+        assert_eq!(to_ascii("Bücher.Example"), "xn--bcher-kva.Example");
+        assert_eq!(to_ascii("例え.テスト"), "xn--r8jz45g.xn--zckzah");
+        assert_eq!(to_ascii("home.example.com"), "home.example.com");
+
+        assert_eq!(to_unicode("xn--bcher-kva.example"), "bücher.example");
+        assert_eq!(to_unicode("xn--r8jz45g.xn--zckzah"), "例え.テスト");
+        assert_eq!(to_unicode("home.example.com"), "home.example.com");
+        assert_eq!(to_unicode("xn--!.example"), "xn--!.example");
+    }
+}
@@ -0,0 +1,700 @@
+//! Converting the configuration of another dynamic DNS client into a cf-ddns [configuration](crate::config), for
+//! `cf-ddns-client import`.
+//!
+//! Only the hosts updated through Cloudflare are converted. Whatever has no equivalent is left out, with a note
+//! explaining what to do instead.
+
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::vec;
+
+use crate::records;
+use crate::source::{Family, SourceKind};
+
+/// A configuration converted from another client.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Imported {
+    /// The API token.
+    pub token: Option<String>,
+
+    /// The zones to update.
+    pub zones: Vec<ImportedZone>,
+
+    /// What couldn't be converted, and what to do instead.
+    pub notes: Vec<String>,
+}
+
+/// A zone of an [`Imported`] configuration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedZone {
+    /// The name of the zone.
+    pub name: String,
+
+    /// The records to update in it.
+    pub records: Vec<ImportedRecord>,
+}
+
+/// A record of an [`ImportedZone`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedRecord {
+    /// The name of the record, relative to the zone.
+    pub name: String,
+
+    /// Where to get the IP address from.
+    pub source: Vec<SourceKind>,
+
+    /// The family to contact worker and `url:` sources over, if any.
+    pub resolve_family: Option<Family>,
+}
+
+impl Imported {
+    /// Add a record to the zone named `zone`, adding the zone if needed.
+    fn add(&mut self, zone: &str, record: ImportedRecord) {
+        let index = match self.zones.iter().position(|existing| existing.name == zone) {
+            Some(index) => index,
+            None => {
+                self.zones.push(ImportedZone {
+                    name: zone.to_string(),
+                    records: Vec::new(),
+                });
+                self.zones.len() - 1
+            }
+        };
+
+        let records = &mut self.zones[index].records;
+        if !records.contains(&record) {
+            records.push(record);
+        }
+    }
+
+    /// Whether `host` is outside `zone`, noting that it's left out if so.
+    fn leaves_out(&mut self, host: &str, zone: &str) -> bool {
+        let outside = host != zone && !host.ends_with(&format!(".{zone}"));
+        if outside {
+            self.notes.push(format!(
+                "{host} is outside its zone {zone} and is left out."
+            ));
+        }
+
+        outside
+    }
+
+    /// Keep `token` unless another one was already found, noting that only one token can be imported.
+    fn set_token(&mut self, token: String) {
+        match &self.token {
+            None => self.token = Some(token),
+            Some(existing) if *existing == token => {}
+            Some(_) => {
+                let note = "The hosts use several tokens, of which only the first is imported. Put the zones of the \
+                            others in profiles with their own token.";
+                if !self.notes.iter().any(|existing| existing == note) {
+                    self.notes.push(note.to_string());
+                }
+            }
+        }
+    }
+
+    /// The configuration as TOML, starting with a comment naming `origin` and the notes.
+    pub fn render(&self, origin: &str) -> String {
+        let quote = |value: &str| toml::Value::String(value.to_string()).to_string();
+        let mut text = format!("# Imported from {origin}.\n");
+        for note in &self.notes {
+            text += &format!("#\n# {note}\n");
+        }
+
+        text += "\n";
+        match &self.token {
+            Some(token) => text += &format!("token = {}\n", quote(token)),
+            None => text += "# token-file = \"/etc/cf-ddns/token\"\n",
+        }
+
+        for zone in &self.zones {
+            text += &format!("\n[[zones]]\nname = {}\n", quote(&zone.name));
+            for record in &zone.records {
+                text += &format!("\n[[zones.records]]\nname = {}\n", quote(&record.name));
+                if record.source != [SourceKind::Worker] {
+                    let source: Vec<String> = record
+                        .source
+                        .iter()
+                        .map(|kind| quote(&kind.to_string()))
+                        .collect();
+                    text += &format!("source = [{}]\n", source.join(", "));
+                }
+
+                match record.resolve_family {
+                    Some(Family::V4) => text += "resolve-family = 4\n",
+                    Some(Family::V6) => text += "resolve-family = 6\n",
+                    None => {}
+                }
+            }
+        }
+
+        text
+    }
+}
+
+/// Convert the Cloudflare hosts of a ddclient configuration, e.g. `/etc/ddclient.conf`.
+///
+/// Settings on a line of their own apply to the hosts after them, and settings on a line with hosts to those hosts
+/// only. Hosts are converted if their `protocol` is `cloudflare`, and their `use`, `usev4`, or `usev6` become the
+/// sources of their records.
+pub fn ddclient(text: &str) -> Imported {
+    let mut imported = Imported::default();
+    let mut globals = BTreeMap::new();
+    let mut hosts = Vec::new();
+    for line in ddclient_lines(text) {
+        let mut settings = BTreeMap::new();
+        let mut names = Vec::new();
+        for token in ddclient_tokens(&line) {
+            match token.split_once('=') {
+                Some((key, value)) => {
+                    settings.insert(key.trim().to_lowercase(), unquote(value.trim()));
+                }
+                None => names.push(unquote(&token)),
+            }
+        }
+
+        if names.is_empty() {
+            globals.extend(settings);
+        } else {
+            let mut local = globals.clone();
+            local.extend(settings);
+            hosts.extend(names.into_iter().map(|name| (name, local.clone())));
+        }
+    }
+
+    if let Some(interval) = globals.get("daemon") {
+        imported.notes.push(format!(
+            "ddclient ran as a daemon every {interval} seconds. Run cf-ddns-client from a timer or cron instead, \
+             e.g. with the cf-ddns.timer unit."
+        ));
+    }
+
+    for (host, settings) in hosts {
+        let protocol = settings.get("protocol").map(String::as_str);
+        if !protocol.is_some_and(|protocol| protocol.eq_ignore_ascii_case("cloudflare")) {
+            imported.notes.push(format!(
+                "{host} uses the protocol {}, not cloudflare, and is left out.",
+                protocol.unwrap_or("dyndns2")
+            ));
+            continue;
+        }
+
+        let Some(zone) = settings.get("zone") else {
+            imported
+                .notes
+                .push(format!("{host} has no zone and is left out."));
+            continue;
+        };
+
+        if imported.leaves_out(&host, zone) {
+            continue;
+        }
+
+        match settings.get("login").map(String::as_str) {
+            None | Some("token") => {
+                if let Some(password) = settings.get("password") {
+                    imported.set_token(password.clone());
+                }
+            }
+            Some(login) => imported.notes.push(format!(
+                "{host} authenticates as {login} with a global API key, which cf-ddns doesn't support. Create an API \
+                 token with the Zone / DNS / Edit permission and set token instead."
+            )),
+        }
+
+        let name = records::relative_name(&host, zone);
+        for (source, resolve_family) in ddclient_sources(&host, &settings, &mut imported.notes) {
+            let record = ImportedRecord {
+                name: name.clone(),
+                source,
+                resolve_family,
+            };
+
+            imported.add(zone, record);
+        }
+    }
+
+    imported
+}
+
+/// The sources of a ddclient host given by its `use`, `usev4`, and `usev6` settings, with the family each is for.
+fn ddclient_sources(
+    host: &str,
+    settings: &BTreeMap<String, String>,
+    notes: &mut Vec<String>,
+) -> Vec<(Vec<SourceKind>, Option<Family>)> {
+    let families = [
+        ("use", None),
+        ("usev4", Some(Family::V4)),
+        ("usev6", Some(Family::V6)),
+    ];
+
+    // The older `use` is ignored by ddclient once `usev4` or `usev6` is set.
+    let per_family = settings.contains_key("usev4") || settings.contains_key("usev6");
+    let mut sources = Vec::new();
+    for (key, family) in families {
+        let Some(method) = settings.get(key).filter(|_| key != "use" || !per_family) else {
+            continue;
+        };
+
+        // `usev4=webv4` reads `webv4`, and the older `usev4=web` reads `web`.
+        let value = settings
+            .get(method.as_str())
+            .or_else(|| settings.get(method.trim_end_matches("v4").trim_end_matches("v6")));
+
+        let source = match (method.trim_end_matches("v4").trim_end_matches("v6"), value) {
+            ("web", Some(web)) if web.starts_with("http://") || web.starts_with("https://") => {
+                SourceKind::Url(web.clone())
+            }
+            ("web", Some(web)) if web.contains('/') => SourceKind::Url(format!("http://{web}")),
+            ("web", _) => SourceKind::Worker,
+            ("if", Some(interface)) if family == Some(Family::V6) => {
+                SourceKind::Interface(interface.clone())
+            }
+            ("if", _) => {
+                notes.push(format!(
+                    "{host} read the IPv4 address of an interface, which cf-ddns can't, and asks the worker instead."
+                ));
+                SourceKind::Worker
+            }
+            ("cmd", Some(command)) => SourceKind::Exec(command.clone()),
+            ("disabled", _) => continue,
+            (method, _) => {
+                notes.push(format!(
+                    "{host} found its address with {key}={method}, which cf-ddns can't, and asks the worker instead."
+                ));
+                SourceKind::Worker
+            }
+        };
+
+        let resolve_family = match source {
+            SourceKind::Worker | SourceKind::Url(_) => family,
+            _ => None,
+        };
+
+        sources.push((vec![source], resolve_family));
+    }
+
+    if sources.is_empty() {
+        sources.push((vec![SourceKind::Worker], None));
+    }
+
+    sources
+}
+
+/// The lines of a ddclient configuration without comments, joining the lines continued with a backslash.
+fn ddclient_lines(text: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for line in text.lines() {
+        let line = strip_comment(line).trim_end();
+        match line.strip_suffix('\\') {
+            Some(start) => {
+                current += start;
+                current.push(' ');
+            }
+            None => {
+                current += line;
+                lines.push(std::mem::take(&mut current));
+            }
+        }
+    }
+
+    lines.push(current);
+    lines.retain(|line| !line.trim().is_empty());
+    lines
+}
+
+/// `line` up to a `#` outside of single quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '\'' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+
+    line
+}
+
+/// The settings and host names of a ddclient line, separated by commas or whitespace outside of single quotes.
+///
+/// Spaces around `=` are allowed, so a setting may span several tokens before they are joined.
+fn ddclient_tokens(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '\'' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            ',' | ' ' | '\t' if !quoted => tokens.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+
+    tokens.push(current);
+    let mut joined: Vec<String> = Vec::new();
+    for token in tokens.into_iter().filter(|token| !token.is_empty()) {
+        match joined.last_mut() {
+            Some(last) if last.ends_with('=') || token.starts_with('=') => *last += &token,
+            _ => joined.push(token),
+        }
+    }
+
+    joined
+}
+
+/// `value` without the single quotes around it, if any.
+fn unquote(value: &str) -> String {
+    value
+        .strip_prefix('\'')
+        .and_then(|value| value.strip_suffix('\''))
+        .unwrap_or(value)
+        .to_string()
+}
+
+/// Convert the providers of an inadyn configuration, e.g. `/etc/inadyn.conf`, that update Cloudflare.
+///
+/// Each `provider cloudflare.com` section names its zone in `username`, its token in `password`, and its records in
+/// `hostname`. `checkip-server` and `checkip-command` become the sources of the records.
+pub fn inadyn(text: &str) -> Imported {
+    let mut imported = Imported::default();
+    let config = InadynSection::parse(&mut inadyn_tokens(text).into_iter().peekable());
+    if let Some(period) = config.get("period") {
+        imported.notes.push(format!(
+            "inadyn checked the address every {period} seconds. Run cf-ddns-client from a timer or cron instead, \
+             e.g. with the cf-ddns.timer unit."
+        ));
+    }
+
+    for (kind, title, provider) in &config.sections {
+        let hosts = provider.list("hostname");
+        let name = title.trim_start_matches("default@");
+        let name = name.split_once(':').map_or(name, |(name, _)| name);
+        if kind != "provider" || !name.eq_ignore_ascii_case("cloudflare.com") {
+            imported.notes.push(format!(
+                "{} use the {kind} {title}, not cloudflare.com, and are left out.",
+                hosts.join(", ")
+            ));
+            continue;
+        }
+
+        let Some(zone) = provider.get("username") else {
+            imported.notes.push(format!(
+                "{} have no zone in username and are left out.",
+                hosts.join(", ")
+            ));
+            continue;
+        };
+
+        if let Some(password) = provider.get("password") {
+            imported.set_token(password.to_string());
+        }
+
+        let source = match (
+            provider.get("checkip-command"),
+            provider.get("checkip-server"),
+            provider.get("iface").or(config.get("iface")),
+        ) {
+            (Some(command), _, _) => SourceKind::Exec(command.to_string()),
+            (None, Some(server), _) => {
+                let scheme = match provider.get("checkip-ssl") {
+                    Some("false") => "http",
+                    _ => "https",
+                };
+
+                let path = provider.get("checkip-path").unwrap_or("/");
+                SourceKind::Url(format!("{scheme}://{server}{path}"))
+            }
+            (None, None, Some(_)) => {
+                imported.notes.push(format!(
+                    "{} read the address of an interface, which cf-ddns can only do for IPv6, and ask the worker \
+                     instead.",
+                    hosts.join(", ")
+                ));
+                SourceKind::Worker
+            }
+            (None, None, None) => SourceKind::Worker,
+        };
+
+        for host in hosts {
+            if imported.leaves_out(host, zone) {
+                continue;
+            }
+
+            let record = ImportedRecord {
+                name: records::relative_name(host, zone),
+                source: vec![source.clone()],
+                resolve_family: None,
+            };
+
+            imported.add(zone, record);
+        }
+    }
+
+    imported
+}
+
+/// A token of an inadyn configuration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum InadynToken {
+    /// A word or a quoted string.
+    Word(String),
+
+    /// One of `=`, `{`, `}`, or `,`.
+    Punct(char),
+}
+
+/// The tokens of an inadyn configuration, without its `#`, `//`, and `/* */` comments.
+fn inadyn_tokens(text: &str) -> Vec<InadynToken> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '#' => while chars.next_if(|&c| c != '\n').is_some() {},
+            '/' if chars.next_if_eq(&'/').is_some() => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            '/' if chars.next_if_eq(&'*').is_some() => {
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+
+                    last = c;
+                }
+            }
+            '=' | '{' | '}' | ',' => tokens.push(InadynToken::Punct(c)),
+            '"' | '\'' => {
+                let word = chars.by_ref().take_while(|&next| next != c).collect();
+                tokens.push(InadynToken::Word(word));
+            }
+            _ if c.is_whitespace() => {}
+            _ => {
+                let mut word = c.to_string();
+                while let Some(c) = chars
+                    .next_if(|&c| !c.is_whitespace() && !matches!(c, '=' | '{' | '}' | ',' | '#'))
+                {
+                    word.push(c);
+                }
+
+                tokens.push(InadynToken::Word(word));
+            }
+        }
+    }
+
+    tokens
+}
+
+/// The settings and sections of an inadyn configuration, or of one of its sections.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct InadynSection {
+    /// The values of each setting, of which lists such as `hostname = { "a", "b" }` have several.
+    settings: BTreeMap<String, Vec<String>>,
+
+    /// The sections, such as `provider cloudflare.com { ... }`, with their kind and title.
+    sections: Vec<(String, String, InadynSection)>,
+}
+
+impl InadynSection {
+    /// Parse `tokens` up to the `}` closing the section, or to the end. Anything malformed is skipped.
+    fn parse(tokens: &mut Peekable<vec::IntoIter<InadynToken>>) -> Self {
+        let mut section = Self::default();
+        while let Some(token) = tokens.next() {
+            let key = match token {
+                InadynToken::Word(key) => key.to_lowercase(),
+                InadynToken::Punct('}') => break,
+                InadynToken::Punct(_) => continue,
+            };
+
+            match tokens.next() {
+                Some(InadynToken::Punct('=')) => {
+                    let values = match tokens.next() {
+                        Some(InadynToken::Word(value)) => vec![value],
+                        Some(InadynToken::Punct('{')) => {
+                            let mut values = Vec::new();
+                            for token in tokens.by_ref() {
+                                match token {
+                                    InadynToken::Word(value) => values.push(value),
+                                    InadynToken::Punct('}') => break,
+                                    InadynToken::Punct(_) => {}
+                                }
+                            }
+
+                            values
+                        }
+                        _ => continue,
+                    };
+
+                    section.settings.insert(key, values);
+                }
+                Some(InadynToken::Word(title))
+                    if tokens.next_if_eq(&InadynToken::Punct('{')).is_some() =>
+                {
+                    section.sections.push((key, title, Self::parse(tokens)));
+                }
+                Some(InadynToken::Punct('{')) => {
+                    section
+                        .sections
+                        .push((key, String::new(), Self::parse(tokens)));
+                }
+                _ => {}
+            }
+        }
+
+        section
+    }
+
+    /// The value of a setting, or its first value if it's a list.
+    fn get(&self, key: &str) -> Option<&str> {
+        self.settings.get(key)?.first().map(String::as_str)
+    }
+
+    /// The values of a setting, which may be given as a single value or a list.
+    fn list(&self, key: &str) -> Vec<&str> {
+        self.settings.get(key).map_or(Vec::new(), |values| {
+            values.iter().map(String::as_str).collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn imports_ddclient() {
+        let imported = ddclient(
+            r#"
+            # Updated every five minutes.
+            daemon=300
+            ssl=yes
+            use=web, web='https://ipv4.icanhazip.com'
+
+            protocol=cloudflare, \
+            zone=example.com, \
+            ttl=1, \
+            login=token, \
+            password='secret#token' \
+            home.example.com,example.com
+
+            protocol = cloudflare
+            zone=example.org
+            usev4=webv4, webv4=dyndns
+            usev6=ifv6, ifv6=eth0
+            password=other # The token of the other account.
+            vpn.example.org
+
+            protocol=dyndns2, server=members.dyndns.org, login=me, password=other other.dyndns.org
+            "#,
+        );
+
+        assert_eq!(imported.token.as_deref(), Some("secret#token"));
+        assert_eq!(imported.zones.len(), 2);
+        let names: Vec<_> = imported.zones[0]
+            .records
+            .iter()
+            .map(|record| record.name.as_str())
+            .collect();
+        assert_eq!(names, ["home", "@"]);
+        assert_eq!(
+            imported.zones[0].records[0].source,
+            [SourceKind::Url("https://ipv4.icanhazip.com".to_string())]
+        );
+
+        let vpn = &imported.zones[1].records;
+        assert_eq!(vpn.len(), 2);
+        assert_eq!(vpn[0].source, [SourceKind::Worker]);
+        assert_eq!(vpn[0].resolve_family, Some(Family::V4));
+        assert_eq!(vpn[1].source, [SourceKind::Interface("eth0".to_string())]);
+        assert_eq!(vpn[1].resolve_family, None);
+
+        // The daemon interval, the other token, and the dyndns2 host.
+        assert_eq!(imported.notes.len(), 3, "{:?}", imported.notes);
+
+        let config = Config::from_toml(&imported.render("/etc/ddclient.conf")).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.zones[1].records[0].resolve_family, Some(Family::V4));
+    }
+
+    #[test]
+    fn imports_inadyn() {
+        let imported = inadyn(
+            r#"
+            # Checked every five minutes.
+            period = 300
+            allow-ipv6 = true
+
+            provider cloudflare.com {
+                username = example.com
+                password = "secret"
+                hostname = { "home.example.com", "example.com" } // Both records.
+                ttl = 1
+                proxied = false
+            }
+
+            /* The records of the second zone. */
+            provider default@cloudflare.com:2 {
+                username = example.org
+                password = secret
+                hostname = vpn.example.org
+                checkip-server = api.ipify.org
+                checkip-path = "/?format=text"
+            }
+
+            provider default@dyndns.org {
+                username = me
+                password = other
+                hostname = other.dyndns.org
+            }
+            "#,
+        );
+
+        assert_eq!(imported.token.as_deref(), Some("secret"));
+        assert_eq!(imported.zones.len(), 2);
+        let names: Vec<_> = imported.zones[0]
+            .records
+            .iter()
+            .map(|record| record.name.as_str())
+            .collect();
+        assert_eq!(names, ["home", "@"]);
+        assert_eq!(imported.zones[0].records[0].source, [SourceKind::Worker]);
+        assert_eq!(
+            imported.zones[1].records[0].source,
+            [SourceKind::Url(
+                "https://api.ipify.org/?format=text".to_string()
+            )]
+        );
+
+        // The period and the dyndns.org provider.
+        assert_eq!(imported.notes.len(), 2, "{:?}", imported.notes);
+
+        let config = Config::from_toml(&imported.render("/etc/inadyn.conf")).unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn tokenizes_ddclient_lines() {
+        assert_eq!(
+            ddclient_tokens("protocol = cloudflare, password='a b,c' home.example.com"),
+            [
+                "protocol=cloudflare",
+                "password='a b,c'",
+                "home.example.com"
+            ]
+        );
+        assert_eq!(strip_comment("password='a#b' # comment"), "password='a#b' ");
+        assert_eq!(
+            ddclient_lines("zone=example.com, \\\n  home.example.com\n\n# done\n"),
+            ["zone=example.com,    home.example.com"]
+        );
+    }
+}
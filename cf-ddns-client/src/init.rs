@@ -0,0 +1,255 @@
+//! Setting up a first configuration by asking questions on the terminal, for the init command.
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use cf_ddns_cloudflare::blocking::Cloudflare;
+use cf_ddns_cloudflare::{ClientError, CloudflareError, NewRecord, NoApiSpecific};
+
+use crate::config::{self, Starter};
+use crate::http::Settings;
+use crate::idn;
+use crate::managed;
+use crate::prompt;
+use crate::records;
+use crate::source::{IpSource, SourceError};
+use crate::token::API_TOKENS_URL;
+use crate::worker::WorkerSource;
+
+/// Errors that can occur when setting up the configuration.
+#[derive(Debug)]
+pub enum InitError {
+    /// The configuration already exists, and replacing it wasn't allowed. Contains its path.
+    Exists(PathBuf),
+
+    /// An answer could not be read from the terminal.
+    Prompt(io::Error),
+
+    /// No answer was given to a question that needs one.
+    Aborted,
+
+    /// The Cloudflare client could not be created with the token.
+    Client(ClientError),
+
+    /// The token isn't active. Contains its status, e.g. `expired`.
+    Inactive(String),
+
+    /// Cloudflare could not be asked whether the token is active.
+    Verify(CloudflareError<NoApiSpecific>),
+
+    /// The token can't see any zone.
+    NoZones,
+
+    /// The zones could not be listed.
+    ListZones(CloudflareError<NoApiSpecific>),
+
+    /// The records of the zone could not be listed.
+    ListRecords(CloudflareError<NoApiSpecific>),
+
+    /// The IP address of a new record could not be discovered.
+    Discover(SourceError),
+
+    /// The new record could not be created. Contains its name.
+    Create(String, CloudflareError<NoApiSpecific>),
+
+    /// The configuration or the token could not be written. Contains the path of the file.
+    Write(PathBuf, io::Error),
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Exists(path) => write!(
+                f,
+                "{} already exists, pass --force to replace it.",
+                path.display()
+            ),
+            Self::Prompt(e) => write!(f, "Could not read the answer: {e}"),
+            Self::Aborted => write!(f, "Aborted."),
+            Self::Client(e) => write!(f, "{e}"),
+            Self::Inactive(status) => write!(f, "The token is {status}, create a new one."),
+            Self::Verify(e) => write!(f, "Could not verify the token: {e}"),
+            Self::NoZones => write!(
+                f,
+                "The token can't see any zone, grant it Zone:Read for the zone to update."
+            ),
+            Self::ListZones(e) => write!(f, "Could not list zones: {e}"),
+            Self::ListRecords(e) => write!(f, "Could not list records: {e}"),
+            Self::Discover(e) => write!(
+                f,
+                "Could not discover the IP address for the new record: {e}"
+            ),
+            Self::Create(name, e) => write!(f, "Could not create {name}: {e}"),
+            Self::Write(path, e) => write!(f, "Could not write {}: {e}", path.display()),
+        }
+    }
+}
+
+impl error::Error for InitError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Prompt(e) | Self::Write(_, e) => Some(e),
+            Self::Client(e) => Some(e),
+            Self::Verify(e) | Self::ListZones(e) | Self::ListRecords(e) | Self::Create(_, e) => {
+                Some(e)
+            }
+            Self::Discover(e) => Some(e),
+            Self::Exists(_) | Self::Aborted | Self::Inactive(_) | Self::NoZones => None,
+        }
+    }
+}
+
+impl From<io::Error> for InitError {
+    fn from(e: io::Error) -> Self {
+        Self::Prompt(e)
+    }
+}
+
+/// Ask for a token, the zone, and the records to update, and write a configuration updating them to `path`, replacing
+/// it only if `force` is set.
+///
+/// Requests to Cloudflare are sent with `http`. If no existing record is chosen, one is created with the address
+/// discovered by `worker`.
+pub fn run(
+    path: &Path,
+    force: bool,
+    http: &Settings,
+    worker: &WorkerSource,
+) -> Result<(), InitError> {
+    if path.exists() && !force {
+        return Err(InitError::Exists(path.to_path_buf()));
+    }
+
+    eprintln!(
+        "Create an API token at {API_TOKENS_URL} from the \"Edit zone DNS\" template, with the Zone:Read and DNS:Edit \
+         permissions."
+    );
+
+    let token = prompt::secret("API token:")?;
+    if token.is_empty() {
+        return Err(InitError::Aborted);
+    }
+
+    let client =
+        Cloudflare::try_with_builder(&token, http.async_builder()).map_err(InitError::Client)?;
+    match client.verify_token() {
+        Ok(status) if status.is_active() => {}
+        Ok(status) => return Err(InitError::Inactive(status.status)),
+        Err(err) => return Err(InitError::Verify(err)),
+    }
+
+    let zones = match client.list_zones() {
+        Ok(zones) if zones.is_empty() => return Err(InitError::NoZones),
+        Ok(zones) => zones,
+        Err(err) => return Err(InitError::ListZones(err)),
+    };
+
+    let zone = match zones.as_slice() {
+        [zone] => {
+            eprintln!(
+                "Using the only zone the token can see, {}.",
+                idn::to_unicode(&zone.name)
+            );
+            zone
+        }
+        zones => {
+            for (i, zone) in zones.iter().enumerate() {
+                eprintln!("{:>3}. {}", i + 1, idn::to_unicode(&zone.name));
+            }
+
+            loop {
+                match prompt::choose("Zone to update:", zones.len())?.as_slice() {
+                    [index] => break &zones[*index],
+                    _ => eprintln!("Choose one zone."),
+                }
+            }
+        }
+    };
+
+    let mut names: Vec<String> = client
+        .list_records(&zone.id, None)
+        .map_err(InitError::ListRecords)?
+        .iter()
+        .filter(|record| matches!(record.content.record_type(), "A" | "AAAA"))
+        .map(|record| records::relative_name(&record.name, &zone.name))
+        .collect();
+
+    names.sort();
+    names.dedup();
+    let mut record_names = Vec::new();
+    if !names.is_empty() {
+        for (i, name) in names.iter().enumerate() {
+            eprintln!("{:>3}. {name}", i + 1);
+        }
+
+        let chosen = prompt::choose(
+            "Records to update, separated by commas, or none to create one:",
+            names.len(),
+        )?;
+        record_names.extend(chosen.into_iter().map(|index| names[index].clone()));
+    }
+
+    if record_names.is_empty() {
+        let name = prompt::ask("Name of the record to create, e.g. home:")?;
+        if name.is_empty() {
+            return Err(InitError::Aborted);
+        }
+
+        let ip = worker.get_ip().map_err(InitError::Discover)?;
+        let fqdn = records::fqdn(&name, &zone.name);
+        let new = NewRecord {
+            comment: Some(managed::MANAGED_COMMENT.to_string()),
+            ..NewRecord::new(&fqdn, ip)
+        };
+
+        match client.create_record(&zone.id, &new) {
+            Ok(_) => eprintln!("Created {fqdn} {} {ip}.", records::record_type(ip)),
+            Err(err) => return Err(InitError::Create(fqdn, err)),
+        }
+
+        record_names.push(name);
+    }
+
+    let token_file = path.with_file_name("token");
+    let question = format!(
+        "Store the token in {}, readable only by you, instead of in the configuration?",
+        token_file.display()
+    );
+
+    let (token, token_file) = if prompt::confirm(&question)? {
+        config::write_private(&token_file, &format!("{token}\n"), force)
+            .map_err(|err| InitError::Write(token_file.clone(), err))?;
+        (None, Some(token_file))
+    } else {
+        (Some(token), None)
+    };
+
+    let starter = Starter {
+        zone_name: Some(zone.name.clone()),
+        record_names,
+        token,
+        token_file,
+        source: Vec::new(),
+    };
+
+    config::write_private(path, &starter.render(), force)
+        .map_err(|err| InitError::Write(path.to_path_buf(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_an_existing_configuration() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        let worker = WorkerSource::new("http://127.0.0.1:1");
+        let err = run(&path, false, &Settings::default(), &worker).unwrap_err();
+        assert!(matches!(&err, InitError::Exists(existing) if *existing == path));
+        assert!(err
+            .to_string()
+            .ends_with("already exists, pass --force to replace it."));
+    }
+}
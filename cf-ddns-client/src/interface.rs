@@ -0,0 +1,241 @@
+//! Reading the IPv6 address of a local network interface, on Linux.
+//!
+//! Hosts with IPv6 usually have a global address of their own, and its prefix is the one the router delegates to the
+//! network, so this is also the usual starting point for [composing](crate::ipv6) the addresses of other hosts.
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::{IpAddr, Ipv6Addr};
+
+use crate::ip;
+use crate::source::{IpSource, SourceError};
+
+/// The kernel's list of IPv6 interface addresses.
+const IF_INET6: &str = "/proc/net/if_inet6";
+
+/// The scope of global addresses in [`IF_INET6`].
+const SCOPE_GLOBAL: u8 = 0;
+
+/// The flag in [`IF_INET6`] of temporary privacy addresses, which rotate.
+const FLAG_TEMPORARY: u8 = 0x01;
+
+/// The flag in [`IF_INET6`] of addresses whose duplicate address detection failed.
+const FLAG_DAD_FAILED: u8 = 0x08;
+
+/// The flag in [`IF_INET6`] of deprecated addresses, whose preferred lifetime has ended.
+const FLAG_DEPRECATED: u8 = 0x20;
+
+/// The flag in [`IF_INET6`] of tentative addresses, whose duplicate address detection hasn't finished.
+const FLAG_TENTATIVE: u8 = 0x40;
+
+/// Which of an interface's addresses may be selected.
+///
+/// Stable global addresses are always preferred. The others are only selected when allowed and no better address
+/// exists.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Policy {
+    /// Allow temporary privacy addresses, which rotate as often as hourly.
+    pub allow_temporary: bool,
+
+    /// Allow deprecated addresses, e.g. of a prefix that is being replaced.
+    pub allow_deprecated: bool,
+
+    /// Allow unique local addresses, `fc00::/7`, which aren't reachable from the internet.
+    pub allow_ula: bool,
+}
+
+impl Policy {
+    /// The rank of an address with `flags`, lower being better, or `None` if it may not be selected.
+    fn rank(&self, ip: Ipv6Addr, flags: u8) -> Option<(bool, bool, bool)> {
+        if flags & (FLAG_TENTATIVE | FLAG_DAD_FAILED) != 0 {
+            return None;
+        }
+
+        let ula = (ip.segments()[0] & 0xfe00) == 0xfc00;
+        let deprecated = flags & FLAG_DEPRECATED != 0;
+        let temporary = flags & FLAG_TEMPORARY != 0;
+        let allowed = (!ula || self.allow_ula)
+            && (!deprecated || self.allow_deprecated)
+            && (!temporary || self.allow_temporary)
+            && (ula || ip::non_global_range(IpAddr::V6(ip)).is_none());
+
+        allowed.then_some((ula, deprecated, temporary))
+    }
+}
+
+/// Errors that can occur when reading the address of an interface.
+#[derive(Debug)]
+pub enum InterfaceError {
+    /// The interface addresses could not be read, e.g. because this isn't Linux.
+    Read(io::Error),
+
+    /// The interface has no IPv6 address allowed by the [`Policy`]. Contains the interface.
+    NoAddress(String),
+}
+
+impl fmt::Display for InterfaceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Read(e) => write!(f, "Failed to read {IF_INET6}: {e}."),
+            Self::NoAddress(interface) => {
+                write!(
+                    f,
+                    "The interface {interface} has no usable global IPv6 address."
+                )
+            }
+        }
+    }
+}
+
+impl error::Error for InterfaceError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Read(e) => Some(e),
+            Self::NoAddress(_) => None,
+        }
+    }
+}
+
+/// An [`IpSource`] that reads the IPv6 address of a local interface, selected by a [`Policy`].
+pub struct InterfaceSource {
+    name: String,
+    interface: String,
+    policy: Policy,
+}
+
+impl InterfaceSource {
+    /// Create a source reading the address of `interface`, e.g. `eth0`.
+    pub fn new(interface: impl Into<String>) -> Self {
+        let interface = interface.into();
+        Self {
+            name: format!("interface:{interface}"),
+            interface,
+            policy: Policy::default(),
+        }
+    }
+
+    /// Select the address with `policy` instead of the default, which only allows stable addresses.
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    fn read(&self) -> Result<IpAddr, InterfaceError> {
+        let table = fs::read_to_string(IF_INET6).map_err(InterfaceError::Read)?;
+        select(&table, &self.interface, self.policy)
+            .map(IpAddr::V6)
+            .ok_or_else(|| InterfaceError::NoAddress(self.interface.clone()))
+    }
+}
+
+impl IpSource for InterfaceSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_ip(&self) -> Result<IpAddr, SourceError> {
+        Ok(self.read()?)
+    }
+}
+
+/// Select the best address of `interface` allowed by `policy` from the contents of [`IF_INET6`].
+///
+/// Each line holds the address in hex, the interface index, the prefix length, the scope, the flags, and the name.
+fn select(table: &str, interface: &str, policy: Policy) -> Option<Ipv6Addr> {
+    table
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [address, _, _, scope, flags, name] = fields[..] else {
+                return None;
+            };
+
+            let scope = u8::from_str_radix(scope, 16).ok()?;
+            let flags = u8::from_str_radix(flags, 16).ok()?;
+            if name != interface || scope != SCOPE_GLOBAL {
+                return None;
+            }
+
+            let ip = Ipv6Addr::from(u128::from_str_radix(address, 16).ok()?);
+            policy.rank(ip, flags).map(|rank| (rank, ip))
+        })
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, ip)| ip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: &str = "\
+        fe800000000000000000000000000001 02 40 20 80 eth0\n\
+        fd000000000000000000000000000001 02 40 00 80 eth0\n\
+        2a01000000000000000000000000abcd 02 40 00 01 eth0\n\
+        2a010000000000000000000000000002 02 40 00 20 eth0\n\
+        2a010000000000000000000000000003 02 40 00 40 eth0\n\
+        2a010000000000000000000000000001 02 40 00 80 eth0\n\
+        2a020000000000000000000000000001 03 40 00 80 wlan0\n";
+
+    #[test]
+    fn prefers_stable_global_address() {
+        assert_eq!(
+            select(TABLE, "eth0", Policy::default()),
+            Some("2a01::1".parse().unwrap())
+        );
+
+        let policy = Policy {
+            allow_temporary: true,
+            allow_deprecated: true,
+            allow_ula: true,
+        };
+
+        assert_eq!(
+            select(TABLE, "eth0", policy),
+            Some("2a01::1".parse().unwrap())
+        );
+        assert_eq!(select(TABLE, "eth1", policy), None);
+    }
+
+    #[test]
+    fn falls_back_only_when_allowed() {
+        let table = TABLE
+            .lines()
+            .filter(|line| !line.starts_with("2a010000000000000000000000000001"))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        assert_eq!(select(&table, "eth0", Policy::default()), None);
+
+        let temporary = Policy {
+            allow_temporary: true,
+            ..Policy::default()
+        };
+
+        assert_eq!(
+            select(&table, "eth0", temporary),
+            Some("2a01::abcd".parse().unwrap())
+        );
+
+        let deprecated = Policy {
+            allow_deprecated: true,
+            ..Policy::default()
+        };
+
+        assert_eq!(
+            select(&table, "eth0", deprecated),
+            Some("2a01::2".parse().unwrap())
+        );
+
+        let ula = Policy {
+            allow_ula: true,
+            ..Policy::default()
+        };
+
+        assert_eq!(
+            select(&table, "eth0", ula),
+            Some("fd00::1".parse().unwrap())
+        );
+    }
+}
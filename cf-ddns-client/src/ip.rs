@@ -0,0 +1,242 @@
+//! Parsing and validating IP addresses given by the user or by other tools.
+
+use std::error;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Errors that can occur when parsing an IP address to publish.
+#[derive(Debug, PartialEq)]
+pub enum InvalidIpError {
+    /// The text is not an IP address. Contains the text.
+    NotAnAddress(String),
+
+    /// The address can't be the address of this host as seen by others, such as a loopback or multicast address.
+    /// Contains the address.
+    Unusable(IpAddr),
+}
+
+impl fmt::Display for InvalidIpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotAnAddress(text) => write!(f, "{text:?} is not an IP address."),
+            Self::Unusable(ip) => write!(
+                f,
+                "{ip} is an unspecified, loopback, or multicast address and can't be published."
+            ),
+        }
+    }
+}
+
+impl error::Error for InvalidIpError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// The error returned when the discovered address is in the shared address space used by carrier-grade NAT.
+#[derive(Debug, PartialEq)]
+pub struct CgnatError(pub IpAddr);
+
+impl fmt::Display for CgnatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} is in 100.64.0.0/10, the shared address space used by carrier-grade NAT (CGNAT). \
+             Your ISP shares one public address between many customers, so this address can't be reached from the \
+             internet and publishing it would be wrong. Ask your ISP for a public IPv4 address, or use IPv6 or a \
+             tunnel instead.",
+            self.0
+        )
+    }
+}
+
+impl error::Error for CgnatError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// Whether `ip` is in `100.64.0.0/10`, the [shared address space](https://datatracker.ietf.org/doc/html/rfc6598) used
+/// by carrier-grade NAT.
+pub fn is_cgnat(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    a == 100 && (b & 0xc0) == 64
+}
+
+/// Check that `ip` isn't a carrier-grade NAT address.
+pub fn check_cgnat(ip: IpAddr) -> Result<IpAddr, CgnatError> {
+    match ip {
+        IpAddr::V4(v4) if is_cgnat(v4) => Err(CgnatError(ip)),
+        _ => Ok(ip),
+    }
+}
+
+/// The error returned when an address isn't globally reachable, such as a private or documentation address.
+#[derive(Debug, PartialEq)]
+pub struct NonGlobalError {
+    /// The address.
+    pub ip: IpAddr,
+
+    /// A description of the range the address is in, e.g. `private`.
+    pub range: &'static str,
+}
+
+impl fmt::Display for NonGlobalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} is a {} address, which isn't reachable from the internet. Pass --allow-non-global to publish it anyway, \
+             e.g. for split-horizon DNS.",
+            self.ip, self.range
+        )
+    }
+}
+
+impl error::Error for NonGlobalError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// The special-purpose range `ip` is in, if it isn't a globally reachable unicast address.
+pub fn non_global_range(ip: IpAddr) -> Option<&'static str> {
+    match ip {
+        IpAddr::V4(ip) => non_global_range_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => non_global_range_v4(v4),
+            None => non_global_range_v6(ip),
+        },
+    }
+}
+
+fn non_global_range_v4(ip: Ipv4Addr) -> Option<&'static str> {
+    let [a, b, c, _] = ip.octets();
+    if ip.is_unspecified() || a == 0 {
+        Some("\"this network\"")
+    } else if ip.is_loopback() {
+        Some("loopback")
+    } else if ip.is_private() {
+        Some("private")
+    } else if is_cgnat(ip) {
+        Some("carrier-grade NAT")
+    } else if ip.is_link_local() {
+        Some("link-local")
+    } else if ip.is_documentation() {
+        Some("documentation")
+    } else if a == 198 && (b & 0xfe) == 18 {
+        Some("benchmarking")
+    } else if a == 192 && b == 0 && c == 0 {
+        Some("IETF protocol assignment")
+    } else if ip.is_multicast() {
+        Some("multicast")
+    } else if ip.is_broadcast() || a >= 240 {
+        Some("reserved")
+    } else {
+        None
+    }
+}
+
+fn non_global_range_v6(ip: Ipv6Addr) -> Option<&'static str> {
+    let [first, second, ..] = ip.segments();
+    if ip.is_unspecified() {
+        Some("unspecified")
+    } else if ip.is_loopback() {
+        Some("loopback")
+    } else if (first & 0xfe00) == 0xfc00 {
+        Some("unique local")
+    } else if (first & 0xffc0) == 0xfe80 {
+        Some("link-local")
+    } else if first == 0x2001 && second == 0x0db8 {
+        Some("documentation")
+    } else if ip.is_multicast() {
+        Some("multicast")
+    } else {
+        None
+    }
+}
+
+/// Check that `ip` is a globally reachable unicast address.
+pub fn check_global(ip: IpAddr) -> Result<IpAddr, NonGlobalError> {
+    match non_global_range(ip) {
+        Some(range) => Err(NonGlobalError { ip, range }),
+        None => Ok(ip),
+    }
+}
+
+/// Parse `text`, ignoring surrounding whitespace, as an IP address that can be published.
+pub fn parse(text: &str) -> Result<IpAddr, InvalidIpError> {
+    let text = text.trim();
+    let ip: IpAddr = text
+        .parse()
+        .map_err(|_| InvalidIpError::NotAnAddress(text.chars().take(64).collect()))?;
+
+    validate(ip)
+}
+
+/// Check that `ip` can be published, i.e. that it isn't unspecified, loopback, or multicast.
+pub fn validate(ip: IpAddr) -> Result<IpAddr, InvalidIpError> {
+    if ip.is_unspecified() || ip.is_loopback() || ip.is_multicast() {
+        Err(InvalidIpError::Unusable(ip))
+    } else {
+        Ok(ip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn parses_trimmed_addresses() {
+        assert_eq!(
+            parse(" 203.0.113.7\n"),
+            Ok(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)))
+        );
+
+        assert!(parse("2001:db8::1").unwrap().is_ipv6());
+    }
+
+    #[test]
+    fn detects_cgnat() {
+        assert!(is_cgnat(Ipv4Addr::new(100, 64, 0, 1)));
+        assert!(is_cgnat(Ipv4Addr::new(100, 127, 255, 255)));
+        assert!(!is_cgnat(Ipv4Addr::new(100, 128, 0, 1)));
+        assert!(!is_cgnat(Ipv4Addr::new(100, 63, 255, 255)));
+        assert!(check_cgnat("2001:db8::1".parse().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn classifies_non_global_addresses() {
+        let range = |ip: &str| non_global_range(ip.parse().unwrap());
+        assert_eq!(range("192.168.1.2"), Some("private"));
+        assert_eq!(range("169.254.0.1"), Some("link-local"));
+        assert_eq!(range("203.0.113.7"), Some("documentation"));
+        assert_eq!(range("224.0.0.1"), Some("multicast"));
+        assert_eq!(range("255.255.255.255"), Some("reserved"));
+        assert_eq!(range("fd00::1"), Some("unique local"));
+        assert_eq!(range("fe80::1"), Some("link-local"));
+        assert_eq!(range("2001:db8::1"), Some("documentation"));
+        assert_eq!(range("::ffff:10.0.0.1"), Some("private"));
+        assert_eq!(range("1.1.1.1"), None);
+        assert_eq!(range("2606:4700:4700::1111"), None);
+    }
+
+    #[test]
+    fn rejects_unusable_addresses() {
+        assert!(matches!(
+            parse("localhost"),
+            Err(InvalidIpError::NotAnAddress(_))
+        ));
+        assert!(matches!(
+            parse("127.0.0.1"),
+            Err(InvalidIpError::Unusable(_))
+        ));
+        assert!(matches!(parse("::"), Err(InvalidIpError::Unusable(_))));
+        assert!(matches!(
+            parse("224.0.0.1"),
+            Err(InvalidIpError::Unusable(_))
+        ));
+    }
+}
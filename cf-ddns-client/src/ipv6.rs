@@ -0,0 +1,68 @@
+//! Composing IPv6 addresses from the current prefix and a fixed host suffix.
+//!
+//! ISPs that change the prefix delegated to a network change the address of every host in it. The host part of each
+//! address stays the same, so the address of any host can be derived from the current prefix, as discovered on the
+//! host running the client, and the host's fixed suffix, e.g. `::1:2:3:4`.
+
+use std::net::Ipv6Addr;
+
+/// The host part of an IPv6 address, combined with the prefix of another address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Suffix {
+    suffix: Ipv6Addr,
+    prefix_len: u8,
+}
+
+impl Suffix {
+    /// Create a suffix that replaces all but the first `prefix_len` bits of an address with those of `suffix`.
+    ///
+    /// `prefix_len` is clamped to 128.
+    pub fn new(suffix: Ipv6Addr, prefix_len: u8) -> Self {
+        Self {
+            suffix,
+            prefix_len: prefix_len.min(128),
+        }
+    }
+
+    /// Combine the prefix of `ip` with the suffix.
+    pub fn apply(&self, ip: Ipv6Addr) -> Ipv6Addr {
+        let mask = u128::MAX
+            .checked_shl(128 - u32::from(self.prefix_len))
+            .unwrap_or(0);
+        Ipv6Addr::from((u128::from(ip) & mask) | (u128::from(self.suffix) & !mask))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(text: &str) -> Ipv6Addr {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn combines_64_bit_prefix() {
+        let suffix = Suffix::new(ip("::1:2:3:4"), 64);
+        assert_eq!(
+            suffix.apply(ip("2001:db8:aa:bb:dead:beef:0:1")),
+            ip("2001:db8:aa:bb:1:2:3:4")
+        );
+    }
+
+    #[test]
+    fn combines_56_bit_prefix() {
+        let suffix = Suffix::new(ip("::12:1:2:3:4"), 56);
+        assert_eq!(
+            suffix.apply(ip("2001:db8:aa:bbcc::1")),
+            ip("2001:db8:aa:bb12:1:2:3:4")
+        );
+    }
+
+    #[test]
+    fn extreme_prefix_lengths() {
+        let address = ip("2001:db8::1");
+        assert_eq!(Suffix::new(ip("::5"), 0).apply(address), ip("::5"));
+        assert_eq!(Suffix::new(ip("::5"), 128).apply(address), address);
+    }
+}
@@ -0,0 +1,75 @@
+//! Keeping the API token in the platform keyring: the Secret Service on Linux, the Keychain on macOS, and the
+//! Credential Manager on Windows, so that it isn't left in a plaintext file.
+//!
+//! The token is stored under the service `cf-ddns`, and is read when no other token is given.
+
+use std::error;
+use std::fmt;
+
+use ::keyring::Entry;
+
+/// The service the token is stored under.
+pub const SERVICE: &str = "cf-ddns";
+
+/// The user the token is stored under.
+pub const USER: &str = "api-token";
+
+/// Errors that can occur when using the keyring.
+#[derive(Debug)]
+pub enum KeyringError {
+    /// The keyring holds no token.
+    NoToken,
+
+    /// The keyring could not be reached or refused the operation.
+    Keyring(::keyring::Error),
+}
+
+impl fmt::Display for KeyringError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoToken => write!(
+                f,
+                "No token given, and the keyring holds none. Pass --token-file, or save the token with \
+                 `cf-ddns-client token set`."
+            ),
+            Self::Keyring(e) => write!(f, "Could not use the keyring: {e}"),
+        }
+    }
+}
+
+impl error::Error for KeyringError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::NoToken => None,
+            Self::Keyring(e) => Some(e),
+        }
+    }
+}
+
+impl From<::keyring::Error> for KeyringError {
+    fn from(e: ::keyring::Error) -> Self {
+        match e {
+            ::keyring::Error::NoEntry => Self::NoToken,
+            e => Self::Keyring(e),
+        }
+    }
+}
+
+fn entry() -> Result<Entry, KeyringError> {
+    Ok(Entry::new(SERVICE, USER)?)
+}
+
+/// Read the token from the keyring.
+pub fn get() -> Result<String, KeyringError> {
+    Ok(entry()?.get_password()?)
+}
+
+/// Save `token` in the keyring, replacing any token saved before.
+pub fn set(token: &str) -> Result<(), KeyringError> {
+    Ok(entry()?.set_password(token)?)
+}
+
+/// Delete the token from the keyring.
+pub fn delete() -> Result<(), KeyringError> {
+    Ok(entry()?.delete_credential()?)
+}
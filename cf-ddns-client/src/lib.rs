@@ -1,5 +1,74 @@
-//! The cf-ddns library contains code for interacting with the Cloudflare API and the Cloudflare DDNS worker.
+//! The cf-ddns library contains code for interacting with the Cloudflare DDNS worker.
+//!
+//! The Cloudflare API client lives in the [`cf_ddns_cloudflare`] crate.
 #![deny(missing_docs)]
 
-pub mod cloudflare;
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+compile_error!(
+    "Enable the native-tls or rustls feature, since Cloudflare is only reachable over HTTPS."
+);
+
+pub mod backup;
+pub mod build_info;
+pub mod cache;
+pub mod color;
+pub mod compat;
+pub mod config;
+pub mod cron;
+pub mod daemon;
+pub mod discord;
+pub mod dns;
+pub mod doctor;
+pub mod doh;
+pub mod echo;
+#[cfg(feature = "encrypted-config")]
+pub mod encrypted;
+pub mod env_file;
+pub mod exec;
+pub mod exit;
+pub mod file;
+pub mod fritzbox;
+pub mod health;
+pub mod history;
+pub mod homeassistant;
+pub mod hooks;
+pub mod http;
+pub mod idn;
+pub mod import;
+pub mod init;
+pub mod interface;
+pub mod ip;
+pub mod ipv6;
+#[cfg(feature = "keyring")]
+pub mod keyring;
+pub mod lock;
+pub mod log_file;
+pub mod logging;
+pub mod managed;
+pub mod mikrotik;
+pub mod mqtt;
+pub mod natpmp;
+pub mod notify;
+pub mod output;
+pub mod paths;
+pub mod prompt;
+pub mod ratelimit;
+pub mod records;
+pub mod report;
+pub mod resolve;
+pub mod retry;
+pub mod self_update;
+pub mod source;
+pub mod state;
+pub mod status;
+#[cfg(unix)]
+pub mod syslog;
+pub mod telegram;
+pub mod token;
+pub mod unifi;
+pub mod upnp;
+#[cfg(feature = "vault")]
+pub mod vault;
+pub mod verbosity;
 pub mod worker;
+pub mod zonefile;
@@ -0,0 +1,90 @@
+//! Preventing concurrent runs with an advisory lock on a file.
+//!
+//! When a timer starts the client while a previous run is still waiting on a slow API call, both runs would read and
+//! patch the same records. Holding a [`Lock`] for the duration of a run makes the second one exit instead.
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The file name of the lock within the [runtime directory](crate::paths::runtime_dir).
+pub const FILE_NAME: &str = "update.lock";
+
+/// An exclusive lock on a file, released when dropped or when the process exits.
+#[derive(Debug)]
+pub struct Lock {
+    _file: File,
+}
+
+impl Lock {
+    /// Try to take the lock on the file at `path`, creating it and its parent directories if needed.
+    ///
+    /// Returns `None` if another process, or another [`Lock`] in this one, already holds it.
+    pub fn try_acquire(path: &Path) -> io::Result<Option<Self>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut options = OpenOptions::new();
+        options.create(true).truncate(false).write(true);
+
+        #[cfg(windows)]
+        {
+            use std::os::windows::fs::OpenOptionsExt;
+
+            // Deny all sharing, so that opening the file again fails while it's open.
+            options.share_mode(0);
+        }
+
+        let file = match options.open(path) {
+            Ok(file) => file,
+            #[cfg(windows)]
+            Err(err) if err.raw_os_error() == Some(ERROR_SHARING_VIOLATION) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::AsRawFd;
+
+            // SAFETY: the file descriptor is valid for as long as `file` is open.
+            if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+                let err = io::Error::last_os_error();
+                return match err.raw_os_error() {
+                    Some(libc::EWOULDBLOCK) => Ok(None),
+                    _ => Err(err),
+                };
+            }
+        }
+
+        Ok(Some(Self { _file: file }))
+    }
+}
+
+/// The Windows error code for opening a file that another handle denies sharing.
+#[cfg(windows)]
+const ERROR_SHARING_VIOLATION: i32 = 32;
+
+/// The default path of the lock, if a runtime directory can be determined.
+pub fn default_path() -> Option<PathBuf> {
+    crate::paths::runtime_dir().map(|dir| dir.join(FILE_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::paths::tests::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn lock_is_exclusive() {
+        let dir = TempDir::new("lock");
+        let path = dir.join("cf-ddns.lock");
+        let lock = Lock::try_acquire(&path).unwrap();
+        assert!(lock.is_some());
+        assert!(Lock::try_acquire(&path).unwrap().is_none());
+
+        drop(lock);
+        assert!(Lock::try_acquire(&path).unwrap().is_some());
+    }
+}
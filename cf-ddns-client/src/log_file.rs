@@ -0,0 +1,208 @@
+//! Writing the log to a file that is rotated by size or by time, for platforms without journald such as routers,
+//! Windows, or containers whose stdout isn't collected.
+//!
+//! Rotating renames `cf-ddns.log` to `cf-ddns.log.1`, `cf-ddns.log.1` to `cf-ddns.log.2`, and so on, deleting the
+//! oldest file beyond the number kept.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// When to start a new log file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    /// Never, leaving the rotation to another tool such as logrotate.
+    Never,
+
+    /// At the start of every hour, in UTC.
+    Hourly,
+
+    /// At the start of every day, in UTC.
+    Daily,
+
+    /// When writing would grow the file beyond this many bytes.
+    Size(u64),
+}
+
+impl Rotation {
+    /// The number of the period that `time` falls in, for rotations by time.
+    fn period(self, time: SystemTime) -> Option<u64> {
+        let seconds = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        match self {
+            Self::Hourly => Some(seconds / 3600),
+            Self::Daily => Some(seconds / 86400),
+            Self::Never | Self::Size(_) => None,
+        }
+    }
+}
+
+impl FromStr for Rotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "never" => return Ok(Self::Never),
+            "hourly" => return Ok(Self::Hourly),
+            "daily" => return Ok(Self::Daily),
+            _ => {}
+        }
+
+        let expected = || format!("expected never, hourly, daily, or a size such as 10M, got {s}");
+        let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (number, unit) = s.split_at(digits);
+        let number: u64 = number.parse().map_err(|_| expected())?;
+        let multiplier: u64 = match unit.trim_end_matches("iB").trim_end_matches('B') {
+            "" => 1,
+            "K" | "k" => 1 << 10,
+            "M" => 1 << 20,
+            "G" => 1 << 30,
+            _ => return Err(expected()),
+        };
+
+        match number.checked_mul(multiplier) {
+            Some(0) | None => Err(expected()),
+            Some(size) => Ok(Self::Size(size)),
+        }
+    }
+}
+
+/// A log file, rotated according to a [`Rotation`] before writing to it.
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    rotation: Rotation,
+    keep: usize,
+    file: File,
+    len: u64,
+    period: Option<u64>,
+}
+
+impl RotatingFile {
+    /// Open the log file at `path` for appending, creating it and its parent directories if needed, and keep `keep`
+    /// rotated files besides it.
+    ///
+    /// A file left by an earlier run is rotated if it's from an earlier period.
+    pub fn open(path: &Path, rotation: Rotation, keep: usize) -> io::Result<Self> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let metadata = file.metadata()?;
+        let mut log = Self {
+            path: path.to_path_buf(),
+            rotation,
+            keep,
+            file,
+            len: metadata.len(),
+            period: rotation.period(metadata.modified()?),
+        };
+
+        if log.len > 0 && log.period != rotation.period(SystemTime::now()) {
+            log.rotate()?;
+        }
+
+        Ok(log)
+    }
+
+    /// The path of the `index`th rotated file, the most recent being 1.
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        path.into()
+    }
+
+    /// Shift the rotated files, move the current one to the first of them, and start a new one.
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            match fs::remove_file(self.rotated_path(self.keep)) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+
+            for index in (1..self.keep).rev() {
+                match fs::rename(self.rotated_path(index), self.rotated_path(index + 1)) {
+                    Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                    _ => {}
+                }
+            }
+
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.len = 0;
+        self.period = self.rotation.period(SystemTime::now());
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let due = match self.rotation {
+            Rotation::Never => false,
+            Rotation::Size(size) => self.len + buf.len() as u64 > size,
+            Rotation::Hourly | Rotation::Daily => {
+                self.period != self.rotation.period(SystemTime::now())
+            }
+        };
+
+        if due && self.len > 0 {
+            self.rotate()?;
+        }
+
+        let written = self.file.write(buf)?;
+        self.len += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::paths::tests::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn parses_rotation() {
+        assert_eq!("daily".parse(), Ok(Rotation::Daily));
+        assert_eq!("never".parse(), Ok(Rotation::Never));
+        assert_eq!("4096".parse(), Ok(Rotation::Size(4096)));
+        assert_eq!("512K".parse(), Ok(Rotation::Size(512 << 10)));
+        assert_eq!("10MiB".parse(), Ok(Rotation::Size(10 << 20)));
+        assert_eq!("1GB".parse(), Ok(Rotation::Size(1 << 30)));
+        assert!("0".parse::<Rotation>().is_err());
+        assert!("weekly".parse::<Rotation>().is_err());
+        assert!("10T".parse::<Rotation>().is_err());
+    }
+
+    #[test]
+    fn rotates_by_size() {
+        let dir = TempDir::new("log-file");
+        let path = dir.join("cf-ddns.log");
+        let mut log = RotatingFile::open(&path, Rotation::Size(10), 2).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            log.write_all(line.as_bytes()).unwrap();
+        }
+
+        let read = |path: PathBuf| fs::read_to_string(path).unwrap();
+        assert_eq!(read(path.clone()), "fourth\n");
+        assert_eq!(read(dir.join("cf-ddns.log.1")), "third\n");
+        assert_eq!(read(dir.join("cf-ddns.log.2")), "second\n");
+        assert!(!dir.join("cf-ddns.log.3").exists());
+    }
+}
@@ -0,0 +1,249 @@
+//! Logging the decisions of a run with [`tracing`], as text or JSON on stderr.
+//!
+//! Events are filtered by `RUST_LOG` if it's set, e.g. `RUST_LOG=cf_ddns_client=debug,reqwest=trace`, and otherwise
+//! according to the [`Verbosity`] selected with `-v`.
+//!
+//! Under systemd, they are sent to journald instead, with the fields of their spans such as `RECORD_NAME`, `OLD_IP`,
+//! and `NEW_IP`, so that `journalctl -t cf-ddns OLD_IP=203.0.113.7` finds the runs that changed that address. They can
+//! also be sent to syslog. With `--log-file`, they are also written to a [`RotatingFile`].
+
+use std::env;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer, Registry};
+
+use crate::color::Stream;
+use crate::log_file::RotatingFile;
+use crate::verbosity::Verbosity;
+
+static PRINTS_OUTCOME: AtomicBool = AtomicBool::new(true);
+
+/// The identifier of the log in journald, and the tag of its syslog messages.
+pub const IDENTIFIER: &str = "cf-ddns";
+
+/// The crates whose events are logged unless `RUST_LOG` says otherwise.
+const CRATES: [&str; 3] = ["cf_ddns", "cf_ddns_client", "cf_ddns_cloudflare"];
+
+/// The format of the log on stderr.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Text,
+
+    /// A JSON object per event, with the fields of the event and its spans.
+    Json,
+}
+
+/// Where the log goes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogTo {
+    /// To journald when run by systemd, otherwise to stderr.
+    #[default]
+    Auto,
+
+    /// To stderr.
+    Stderr,
+
+    /// To journald, with the outcome of the run instead of printing it.
+    Journald,
+
+    /// To the syslog daemon on `/dev/log`, with the outcome of the run instead of printing it.
+    Syslog,
+}
+
+impl LogTo {
+    /// The destination chosen by [`Self::Auto`], given whether systemd connected stderr to the journal.
+    pub fn resolve(self, journal_stream: bool) -> Self {
+        match (self, journal_stream) {
+            (Self::Auto, true) => Self::Journald,
+            (Self::Auto, false) => Self::Stderr,
+            (to, _) => to,
+        }
+    }
+}
+
+/// The target of the events reporting the outcome of a run, which are printed on stdout instead of being logged on
+/// stderr, but are written to the `--log-file`.
+pub const OUTCOME: &str = "outcome";
+
+/// Whether the outcome of the run is printed, rather than only logged to journald or syslog.
+pub fn prints_outcome() -> bool {
+    PRINTS_OUTCOME.load(Ordering::Relaxed)
+}
+
+/// The filter used unless `RUST_LOG` is set, which logs the events of this project at the level selected by
+/// `verbosity`, and only warnings and errors of other crates.
+pub fn default_filter(verbosity: Verbosity) -> String {
+    let level = match verbosity {
+        Verbosity::Quiet | Verbosity::Normal => "warn",
+        Verbosity::Verbose => "info",
+        Verbosity::Debug => "debug",
+        Verbosity::Trace => "trace",
+    };
+
+    let mut filter = String::from("warn");
+    for name in CRATES {
+        filter += &format!(",{name}={level}");
+    }
+
+    filter
+}
+
+/// The filter of the log on stderr or in a file: `RUST_LOG`, else the [`default_filter`] at `verbosity`, with the
+/// [`OUTCOME`] events shown or not.
+fn filter(verbosity: Verbosity, outcome: bool) -> EnvFilter {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(default_filter(verbosity)));
+
+    let level = match outcome {
+        true => "info",
+        false => "off",
+    };
+
+    filter.add_directive(
+        format!("{OUTCOME}={level}")
+            .parse()
+            .expect("The directive is valid."),
+    )
+}
+
+/// Log events in `format` on stderr, filtered by `RUST_LOG` or else by `verbosity`. Text is colored if stderr
+/// [is](Stream::is_colored).
+///
+/// Sent to journald or syslog according to `to`, events are logged at least from `-v` on, with the outcome of the run
+/// which is then no longer printed. With a `file`, events are also written to it the same way, with their time.
+pub fn init(
+    format: LogFormat,
+    verbosity: Verbosity,
+    to: LogTo,
+    file: Option<RotatingFile>,
+) -> io::Result<()> {
+    let at_least_verbose = verbosity.max(Verbosity::Verbose);
+    let (main, prints_outcome) = match to.resolve(env::var_os("JOURNAL_STREAM").is_some()) {
+        LogTo::Auto | LogTo::Stderr => (stderr(format, verbosity), true),
+        // Under systemd, stderr still reaches the journal if its socket can't be used.
+        LogTo::Journald => match journald() {
+            Ok(layer) => (
+                layer.with_filter(filter(at_least_verbose, true)).boxed(),
+                false,
+            ),
+            Err(_) if to == LogTo::Auto => (stderr(format, verbosity), true),
+            Err(err) => return Err(err),
+        },
+        LogTo::Syslog => (
+            syslog()?
+                .with_filter(filter(at_least_verbose, true))
+                .boxed(),
+            false,
+        ),
+    };
+
+    let file = file.map(|file| {
+        let layer = fmt::layer()
+            .with_writer(Mutex::new(file))
+            .with_ansi(false)
+            .with_target(false);
+
+        let layer = match format {
+            LogFormat::Text => layer.boxed(),
+            LogFormat::Json => layer.json().flatten_event(true).boxed(),
+        };
+
+        layer.with_filter(filter(at_least_verbose, true))
+    });
+
+    // Another subscriber may already be set, e.g. in tests, which is fine.
+    let _ = tracing_subscriber::registry()
+        .with(main)
+        .with(file)
+        .try_init();
+
+    PRINTS_OUTCOME.store(prints_outcome, Ordering::Relaxed);
+    Ok(())
+}
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+fn stderr(format: LogFormat, verbosity: Verbosity) -> BoxedLayer {
+    let layer = fmt::layer()
+        .with_writer(io::stderr)
+        .with_ansi(Stream::Stderr.is_colored())
+        .with_target(false);
+
+    let layer = match format {
+        LogFormat::Text => layer.without_time().boxed(),
+        LogFormat::Json => layer.json().flatten_event(true).boxed(),
+    };
+
+    layer.with_filter(filter(verbosity, false)).boxed()
+}
+
+#[cfg(unix)]
+fn journald() -> io::Result<BoxedLayer> {
+    let layer = tracing_journald::layer()?
+        .with_field_prefix(None)
+        .with_syslog_identifier(IDENTIFIER.to_string());
+
+    Ok(layer.boxed())
+}
+
+#[cfg(unix)]
+fn syslog() -> io::Result<BoxedLayer> {
+    use crate::syslog::{Syslog, SOCKET};
+
+    let layer = fmt::layer()
+        .with_writer(Syslog::connect(SOCKET.as_ref())?)
+        .with_ansi(false)
+        .without_time()
+        .with_level(false)
+        .with_target(false);
+
+    Ok(layer.boxed())
+}
+
+#[cfg(not(unix))]
+fn journald() -> io::Result<BoxedLayer> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "journald is only available on Linux",
+    ))
+}
+
+#[cfg(not(unix))]
+fn syslog() -> io::Result<BoxedLayer> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "syslog is only available on Unix",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_by_verbosity() {
+        assert_eq!(
+            default_filter(Verbosity::Normal),
+            "warn,cf_ddns=warn,cf_ddns_client=warn,cf_ddns_cloudflare=warn"
+        );
+        assert_eq!(
+            default_filter(Verbosity::Debug),
+            "warn,cf_ddns=debug,cf_ddns_client=debug,cf_ddns_cloudflare=debug"
+        );
+        assert!(EnvFilter::try_new(default_filter(Verbosity::Trace)).is_ok());
+    }
+
+    #[test]
+    fn logs_to_journald_under_systemd() {
+        assert_eq!(LogTo::Auto.resolve(true), LogTo::Journald);
+        assert_eq!(LogTo::Auto.resolve(false), LogTo::Stderr);
+        assert_eq!(LogTo::Syslog.resolve(true), LogTo::Syslog);
+        assert_eq!(LogTo::Stderr.resolve(true), LogTo::Stderr);
+    }
+}
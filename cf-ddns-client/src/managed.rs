@@ -0,0 +1,141 @@
+//! Markers identifying DNS records that are managed by cf-ddns.
+//!
+//! A record is managed when it carries the [`MANAGED_TAG`] tag or its comment contains [`MANAGED_COMMENT`]. Tags are
+//! only available on some Cloudflare plans, so either marker is accepted.
+
+use std::error;
+use std::fmt;
+
+use cf_ddns_cloudflare::Record;
+
+/// The tag marking a record as managed by cf-ddns.
+pub const MANAGED_TAG: &str = "managed-by:cf-ddns";
+
+/// The comment marking a record as managed by cf-ddns.
+pub const MANAGED_COMMENT: &str = "managed-by=cf-ddns";
+
+/// Whether `record` is marked as managed by cf-ddns.
+pub fn is_managed(record: &Record) -> bool {
+    record.tags.iter().any(|tag| tag == MANAGED_TAG)
+        || record
+            .comment
+            .as_deref()
+            .is_some_and(|comment| comment.contains(MANAGED_COMMENT))
+}
+
+/// The error returned when modifying a record that isn't managed by cf-ddns is refused. Contains the record name.
+#[derive(Debug, PartialEq)]
+pub struct NotManagedError(pub String);
+
+impl fmt::Display for NotManagedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Refusing to modify {}, which is not marked as managed by cf-ddns. Pass --adopt to mark it.",
+            self.0
+        )
+    }
+}
+
+impl error::Error for NotManagedError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// The comment of `record` with the [`MANAGED_COMMENT`] marker appended.
+pub fn adopted_comment(record: &Record) -> String {
+    match record.comment.as_deref().map(str::trim) {
+        Some(comment) if !comment.is_empty() => format!("{comment} {MANAGED_COMMENT}"),
+        _ => MANAGED_COMMENT.to_string(),
+    }
+}
+
+/// Check whether `record` may be modified.
+///
+/// Unmanaged records are refused when `require` is set, unless `adopt` is also set. When adopting an unmanaged
+/// record, the comment that marks it as managed is returned so that it can be written alongside the update.
+pub fn check(
+    record: &Record,
+    require: bool,
+    adopt: bool,
+) -> Result<Option<String>, NotManagedError> {
+    if is_managed(record) {
+        Ok(None)
+    } else if adopt {
+        Ok(Some(adopted_comment(record)))
+    } else if require {
+        Err(NotManagedError(record.name.clone()))
+    } else {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use cf_ddns_cloudflare::RecordContent;
+
+    use super::*;
+    use crate::records;
+
+    fn record(comment: Option<&str>, tags: &[&str]) -> Record {
+        let content = RecordContent::A {
+            content: Ipv4Addr::new(10, 0, 0, 1),
+        };
+
+        Record {
+            comment: comment.map(str::to_string),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..records::tests::record("1", "home.example.com", content)
+        }
+    }
+
+    #[test]
+    fn managed_by_tag() {
+        assert!(is_managed(&record(None, &["other:tag", MANAGED_TAG])));
+    }
+
+    #[test]
+    fn managed_by_comment() {
+        assert!(is_managed(&record(
+            Some("home router; managed-by=cf-ddns"),
+            &[]
+        )));
+    }
+
+    #[test]
+    fn unmanaged() {
+        assert!(!is_managed(&record(
+            Some("managed by hand"),
+            &["managed-by:someone"]
+        )));
+    }
+
+    #[test]
+    fn adopting_appends_marker() {
+        assert_eq!(adopted_comment(&record(None, &[])), MANAGED_COMMENT);
+        assert_eq!(
+            adopted_comment(&record(Some("router "), &[])),
+            "router managed-by=cf-ddns"
+        );
+    }
+
+    #[test]
+    fn check_requires_marker() {
+        let unmanaged = record(None, &[]);
+        assert_eq!(check(&unmanaged, false, false), Ok(None));
+        assert_eq!(
+            check(&unmanaged, true, false),
+            Err(NotManagedError("home.example.com".to_string()))
+        );
+
+        assert_eq!(
+            check(&unmanaged, true, true),
+            Ok(Some(MANAGED_COMMENT.to_string()))
+        );
+
+        assert_eq!(check(&record(None, &[MANAGED_TAG]), true, false), Ok(None));
+    }
+}
@@ -0,0 +1,228 @@
+//! Reading the WAN interface address from a [MikroTik RouterOS](https://help.mikrotik.com/docs/spaces/ROS/pages/47579162/REST+API)
+//! router with its REST API, available since RouterOS 7.1.
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::http;
+use crate::source::{Family, IpSource, SourceError};
+
+/// How long to wait for the router to answer.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Errors that can occur when reading the address from a MikroTik router.
+#[derive(Debug)]
+pub enum MikroTikError {
+    /// The router's URL or WAN interface wasn't configured. Contains the name of the missing option.
+    NotConfigured(&'static str),
+
+    /// The password file could not be read.
+    Password(io::Error),
+
+    /// The request failed or the router answered with an error, e.g. because the credentials are wrong.
+    RequestFailed(reqwest::Error),
+
+    /// The interface has no enabled, valid address of the requested family. Contains the interface.
+    NoAddress(String),
+}
+
+impl fmt::Display for MikroTikError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotConfigured(option) => {
+                write!(f, "The mikrotik source requires {option}.")
+            }
+            Self::Password(e) => write!(f, "Failed to read the MikroTik password file: {e}."),
+            Self::RequestFailed(e) => write!(f, "Failed to query the MikroTik router: {e}."),
+            Self::NoAddress(interface) => {
+                write!(
+                    f,
+                    "The MikroTik interface {interface} has no usable address."
+                )
+            }
+        }
+    }
+}
+
+impl error::Error for MikroTikError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Password(e) => Some(e),
+            Self::RequestFailed(e) => Some(e),
+            Self::NotConfigured(_) | Self::NoAddress(_) => None,
+        }
+    }
+}
+
+/// An address entry returned by `/rest/ip/address` or `/rest/ipv6/address`. RouterOS returns booleans as strings.
+#[derive(Debug, Deserialize)]
+struct Address {
+    address: String,
+    interface: String,
+    #[serde(default)]
+    disabled: String,
+    #[serde(default)]
+    invalid: String,
+}
+
+/// The connection details of a MikroTik router.
+#[derive(Clone, Debug, Default)]
+pub struct MikroTikConfig {
+    /// The base URL of the router, e.g. `https://192.168.88.1`.
+    pub url: Option<String>,
+
+    /// The name of the WAN interface, e.g. `ether1` or `pppoe-out1`.
+    pub interface: Option<String>,
+
+    /// The user to authenticate as.
+    pub user: String,
+
+    /// The password of the user.
+    pub password: Option<String>,
+
+    /// A file containing the password of the user.
+    pub password_file: Option<PathBuf>,
+
+    /// Accept invalid TLS certificates, such as the router's self-signed one.
+    pub insecure: bool,
+}
+
+/// An [`IpSource`] that reads the address of the WAN interface of a MikroTik router.
+pub struct MikroTikSource {
+    config: MikroTikConfig,
+    family: Family,
+}
+
+impl MikroTikSource {
+    /// Create a source reading the address of `family` from the router described by `config`.
+    pub fn new(config: MikroTikConfig, family: Family) -> Self {
+        Self { config, family }
+    }
+
+    fn query(&self) -> Result<IpAddr, MikroTikError> {
+        let config = &self.config;
+        let url = config
+            .url
+            .as_deref()
+            .ok_or(MikroTikError::NotConfigured("--mikrotik-url"))?;
+
+        let interface = config
+            .interface
+            .as_deref()
+            .ok_or(MikroTikError::NotConfigured("--mikrotik-interface"))?;
+
+        let password = match (&config.password, &config.password_file) {
+            (Some(password), _) => Some(password.clone()),
+            (None, Some(path)) => Some(
+                fs::read_to_string(path)
+                    .map_err(MikroTikError::Password)?
+                    .trim()
+                    .to_string(),
+            ),
+            (None, None) => None,
+        };
+
+        let path = match self.family {
+            Family::V4 => "ip/address",
+            Family::V6 => "ipv6/address",
+        };
+
+        let builder = http::client_builder().timeout(TIMEOUT);
+        #[cfg(any(feature = "native-tls", feature = "rustls"))]
+        let builder = builder.danger_accept_invalid_certs(config.insecure);
+        let addresses: Vec<Address> = builder
+            .build()
+            .and_then(|client| {
+                client
+                    .get(format!("{}/rest/{path}", url.trim_end_matches('/')))
+                    .query(&[("interface", interface)])
+                    .basic_auth(&config.user, password)
+                    .send()
+            })
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.json())
+            .map_err(MikroTikError::RequestFailed)?;
+
+        select(&addresses, interface, self.family)
+            .ok_or_else(|| MikroTikError::NoAddress(interface.to_string()))
+    }
+}
+
+impl IpSource for MikroTikSource {
+    fn name(&self) -> &str {
+        "mikrotik"
+    }
+
+    fn get_ip(&self) -> Result<IpAddr, SourceError> {
+        Ok(self.query()?)
+    }
+}
+
+/// Select the first enabled, valid address of `family` on `interface`, skipping IPv6 link-local addresses.
+fn select(addresses: &[Address], interface: &str, family: Family) -> Option<IpAddr> {
+    addresses
+        .iter()
+        .filter(|address| {
+            address.interface == interface
+                && address.disabled != "true"
+                && address.invalid != "true"
+        })
+        .filter_map(|address| {
+            let ip = address.address.split('/').next()?;
+            ip.parse::<IpAddr>().ok()
+        })
+        .find(|ip| match (ip, family) {
+            (IpAddr::V4(_), Family::V4) => true,
+            (IpAddr::V6(ip), Family::V6) => (ip.segments()[0] & 0xffc0) != 0xfe80,
+            _ => false,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addresses(json: &str) -> Vec<Address> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn selects_enabled_address_on_interface() {
+        let addresses = addresses(
+            r#"[
+                {".id":"*1","address":"192.168.88.1/24","interface":"bridge","disabled":"false","invalid":"false"},
+                {".id":"*2","address":"198.51.100.1/24","interface":"ether1","disabled":"true","invalid":"false"},
+                {".id":"*3","address":"203.0.113.7/24","interface":"ether1","disabled":"false","invalid":"false"}
+            ]"#,
+        );
+
+        assert_eq!(
+            select(&addresses, "ether1", Family::V4),
+            Some("203.0.113.7".parse().unwrap())
+        );
+
+        assert_eq!(select(&addresses, "ether2", Family::V4), None);
+    }
+
+    #[test]
+    fn skips_link_local_ipv6() {
+        let addresses = addresses(
+            r#"[
+                {"address":"fe80::1/64","interface":"ether1"},
+                {"address":"2001:db8::1/64","interface":"ether1"}
+            ]"#,
+        );
+
+        assert_eq!(
+            select(&addresses, "ether1", Family::V6),
+            Some("2001:db8::1".parse().unwrap())
+        );
+    }
+}
@@ -0,0 +1,309 @@
+//! Asking the default gateway for its external address with [NAT-PMP](https://datatracker.ietf.org/doc/html/rfc6886)
+//! or [PCP](https://datatracker.ietf.org/doc/html/rfc6887).
+//!
+//! NAT-PMP has a dedicated request for the external address. Gateways that only speak PCP answer it with an
+//! unsupported version, in which case a short-lived PCP mapping is requested to learn the external address and then
+//! deleted again.
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::source::{IpSource, SourceError};
+
+/// The port NAT-PMP and PCP servers listen on.
+const PORT: u16 = 5351;
+
+/// How long to wait for the first answer. Each retry waits twice as long.
+const INITIAL_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// How many times to send each request.
+const ATTEMPTS: u32 = 3;
+
+/// The NAT-PMP result code for an unsupported version, sent by PCP-only servers.
+const UNSUPPORTED_VERSION: u16 = 1;
+
+const PCP_VERSION: u8 = 2;
+const PCP_MAP: u8 = 1;
+const PCP_RESULT_SUCCESS: u8 = 0;
+
+/// The lifetime of the PCP mapping used to learn the external address. It is deleted straight away.
+const PCP_LIFETIME: u32 = 120;
+
+/// The internal port of the PCP mapping, the discard port, so nothing is exposed while it exists.
+const PCP_INTERNAL_PORT: u16 = 9;
+
+/// Errors that can occur when asking the gateway for its external address.
+#[derive(Debug)]
+pub enum NatPmpError {
+    /// The default gateway could not be determined. Pass it explicitly instead.
+    NoGateway,
+
+    /// The request could not be sent or no answer was received in time.
+    Io(io::Error),
+
+    /// The answer was not a valid response to the request.
+    Malformed,
+
+    /// The gateway answered with an error. Contains the result code.
+    ResultCode(u16),
+}
+
+impl fmt::Display for NatPmpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoGateway => write!(
+                f,
+                "Could not determine the default gateway; pass it with --gateway."
+            ),
+            Self::Io(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                write!(f, "The gateway did not answer NAT-PMP or PCP requests.")
+            }
+            Self::Io(e) => write!(f, "Failed to query the gateway: {e}."),
+            Self::Malformed => write!(f, "The gateway sent a malformed NAT-PMP or PCP response."),
+            Self::ResultCode(code) => write!(f, "The gateway answered with result code {code}."),
+        }
+    }
+}
+
+impl error::Error for NatPmpError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::NoGateway | Self::Malformed | Self::ResultCode(_) => None,
+        }
+    }
+}
+
+/// An [`IpSource`] that asks the default gateway for its external address with NAT-PMP or PCP.
+#[derive(Default)]
+pub struct NatPmpSource {
+    gateway: Option<Ipv4Addr>,
+}
+
+impl NatPmpSource {
+    /// Create a source asking `gateway`, or the default gateway if `None`.
+    pub fn new(gateway: Option<Ipv4Addr>) -> Self {
+        Self { gateway }
+    }
+
+    fn query(&self) -> Result<IpAddr, NatPmpError> {
+        let gateway = self
+            .gateway
+            .or_else(default_gateway)
+            .ok_or(NatPmpError::NoGateway)?;
+
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).map_err(NatPmpError::Io)?;
+        socket
+            .connect(SocketAddr::from((gateway, PORT)))
+            .map_err(NatPmpError::Io)?;
+
+        let response = request(&socket, &[0, 0])?;
+        match parse_natpmp(&response) {
+            Err(NatPmpError::ResultCode(UNSUPPORTED_VERSION)) => self.query_pcp(&socket),
+            result => result.map(IpAddr::V4),
+        }
+    }
+
+    fn query_pcp(&self, socket: &UdpSocket) -> Result<IpAddr, NatPmpError> {
+        let client = match socket.local_addr().map_err(NatPmpError::Io)?.ip() {
+            IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+            IpAddr::V6(ip) => ip,
+        };
+
+        let nonce = nonce();
+        let response = request(socket, &encode_pcp_map(client, nonce, PCP_LIFETIME))?;
+        let ip = parse_pcp_map(&response, nonce)?;
+
+        // Delete the mapping again. It expires on its own if this is lost.
+        let _ = socket.send(&encode_pcp_map(client, nonce, 0));
+        Ok(ip)
+    }
+}
+
+impl IpSource for NatPmpSource {
+    fn name(&self) -> &str {
+        "natpmp"
+    }
+
+    fn get_ip(&self) -> Result<IpAddr, SourceError> {
+        Ok(self.query()?)
+    }
+}
+
+/// Send `message` on the connected `socket` and wait for the answer, retrying with increasing timeouts.
+fn request(socket: &UdpSocket, message: &[u8]) -> Result<Vec<u8>, NatPmpError> {
+    let mut timeout = INITIAL_TIMEOUT;
+    let mut buf = [0; 1100];
+    for attempt in 1..=ATTEMPTS {
+        socket.send(message).map_err(NatPmpError::Io)?;
+        socket
+            .set_read_timeout(Some(timeout))
+            .map_err(NatPmpError::Io)?;
+
+        match socket.recv(&mut buf) {
+            Ok(len) => return Ok(buf[..len].to_vec()),
+            Err(e)
+                if attempt < ATTEMPTS
+                    && matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+            {
+                timeout *= 2;
+            }
+            Err(e) => return Err(NatPmpError::Io(e)),
+        }
+    }
+
+    unreachable!("The last attempt always returns.")
+}
+
+/// Parse the answer to a NAT-PMP external address request.
+fn parse_natpmp(response: &[u8]) -> Result<Ipv4Addr, NatPmpError> {
+    if response.len() < 4 || response[1] != 128 {
+        return Err(NatPmpError::Malformed);
+    }
+
+    let result = u16::from_be_bytes([response[2], response[3]]);
+    if result != 0 {
+        return Err(NatPmpError::ResultCode(result));
+    }
+
+    let octets: [u8; 4] = response
+        .get(8..12)
+        .and_then(|octets| octets.try_into().ok())
+        .ok_or(NatPmpError::Malformed)?;
+
+    Ok(Ipv4Addr::from(octets))
+}
+
+/// Encode a PCP `MAP` request for the discard port of `client` with `lifetime`, in seconds.
+fn encode_pcp_map(client: Ipv6Addr, nonce: [u8; 12], lifetime: u32) -> Vec<u8> {
+    let mut message = Vec::with_capacity(60);
+    message.extend_from_slice(&[PCP_VERSION, PCP_MAP, 0, 0]);
+    message.extend_from_slice(&lifetime.to_be_bytes());
+    message.extend_from_slice(&client.octets());
+    message.extend_from_slice(&nonce);
+    // UDP, then three reserved bytes.
+    message.extend_from_slice(&[17, 0, 0, 0]);
+    message.extend_from_slice(&PCP_INTERNAL_PORT.to_be_bytes());
+    // No suggested external port or address.
+    message.extend_from_slice(&[0, 0]);
+    message.extend_from_slice(&Ipv4Addr::UNSPECIFIED.to_ipv6_mapped().octets());
+    message
+}
+
+/// Parse the answer to a PCP `MAP` request with `nonce`, returning the assigned external address.
+fn parse_pcp_map(response: &[u8], nonce: [u8; 12]) -> Result<IpAddr, NatPmpError> {
+    if response.len() < 60 || response[0] != PCP_VERSION || response[1] != 0x80 | PCP_MAP {
+        return Err(NatPmpError::Malformed);
+    }
+
+    if response[3] != PCP_RESULT_SUCCESS {
+        return Err(NatPmpError::ResultCode(response[3].into()));
+    }
+
+    if response[24..36] != nonce {
+        return Err(NatPmpError::Malformed);
+    }
+
+    let octets: [u8; 16] = response[44..60].try_into().unwrap();
+    let ip = Ipv6Addr::from(octets);
+    Ok(ip.to_ipv4_mapped().map_or(IpAddr::V6(ip), IpAddr::V4))
+}
+
+/// A nonce that is unlikely to repeat between runs.
+fn nonce() -> [u8; 12] {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+
+    let mut nonce = [0; 12];
+    nonce.copy_from_slice(&nanos.to_le_bytes()[..12]);
+    nonce
+}
+
+/// The default IPv4 gateway from the kernel's routing table, on Linux.
+fn default_gateway() -> Option<Ipv4Addr> {
+    parse_route_table(&fs::read_to_string("/proc/net/route").ok()?)
+}
+
+/// Parse the gateway of the default route from the contents of `/proc/net/route`.
+fn parse_route_table(table: &str) -> Option<Ipv4Addr> {
+    table.lines().skip(1).find_map(|line| {
+        let mut fields = line.split_whitespace().skip(1);
+        let destination = fields.next()?;
+        let gateway = fields.next()?;
+        if destination != "00000000" {
+            return None;
+        }
+
+        // The kernel prints the address in host byte order.
+        let gateway = u32::from_str_radix(gateway, 16).ok()?;
+        Some(Ipv4Addr::from(gateway.to_ne_bytes()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_natpmp_response() {
+        let response = [0, 128, 0, 0, 0, 0, 1, 0, 203, 0, 113, 7];
+        assert_eq!(
+            parse_natpmp(&response).unwrap(),
+            Ipv4Addr::new(203, 0, 113, 7)
+        );
+    }
+
+    #[test]
+    fn reports_unsupported_version() {
+        let response = [0, 128, 0, 1, 0, 0, 0, 0];
+        assert!(matches!(
+            parse_natpmp(&response),
+            Err(NatPmpError::ResultCode(UNSUPPORTED_VERSION))
+        ));
+    }
+
+    #[test]
+    fn parses_pcp_map_response() {
+        let nonce = [7; 12];
+        let client = Ipv4Addr::new(192, 168, 1, 2).to_ipv6_mapped();
+        let mut response = encode_pcp_map(client, nonce, PCP_LIFETIME);
+        response[1] |= 0x80;
+        response[44..60].copy_from_slice(&Ipv4Addr::new(203, 0, 113, 7).to_ipv6_mapped().octets());
+        assert_eq!(
+            parse_pcp_map(&response, nonce).unwrap(),
+            IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7))
+        );
+
+        assert!(matches!(
+            parse_pcp_map(&response, [8; 12]),
+            Err(NatPmpError::Malformed)
+        ));
+    }
+
+    #[cfg(target_endian = "little")]
+    #[test]
+    fn parses_route_table() {
+        let table =
+            "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n\
+            eth0\t0001A8C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\t0\t0\t0\n\
+            eth0\t00000000\t0101A8C0\t0003\t0\t0\t0\t00000000\t0\t0\t0\n";
+
+        assert_eq!(
+            parse_route_table(table),
+            Some(Ipv4Addr::new(192, 168, 1, 1))
+        );
+    }
+}
@@ -0,0 +1,468 @@
+//! Notifying other systems when the records change, or fail to, through a [`Notifier`] such as a [`Webhook`].
+//!
+//! The body of a webhook's request is JSON, by default an object with the fields of the [`Event`] of the run. A
+//! template can replace it, in which `{old_ip}`, `{new_ip}`, `{record}`, `{timestamp}`, and `{outcome}` are replaced
+//! by those of the event, escaped to be placed within a JSON string:
+//!
+//! ```json
+//! {"text": "{record} is now {new_ip}, was {old_ip}"}
+//! ```
+//!
+//! Each notifier decides which runs it's told about, unless a [`Policy`] overrides it with [triggers](Trigger) and a
+//! minimum interval between notifications, so that a flapping address doesn't flood a chat.
+
+use std::error;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
+use std::str::FromStr;
+use std::time::Duration;
+
+use reqwest::blocking::Response;
+use reqwest::header::CONTENT_TYPE;
+use serde_json::{json, Value};
+
+use crate::history::Event;
+use crate::hooks::HookError;
+use crate::http::Settings;
+use crate::mqtt::MqttError;
+use crate::state::Outcome;
+
+/// A way of telling other systems about the outcome of a run.
+pub trait Notifier {
+    /// A short name for the notifier, used in messages, e.g. `the webhook`.
+    fn name(&self) -> &str;
+
+    /// Whether to notify of a run with `outcome`.
+    fn wants(&self, outcome: Outcome) -> bool;
+
+    /// Notify of `event`, with a client built from `settings`.
+    fn send(&self, event: &Event, settings: &Settings) -> Result<(), NotifyError>;
+}
+
+/// The notifiers a [`Policy`] can be given for, by the name of their option.
+pub const NOTIFIERS: [&str; 5] = ["webhook", "telegram", "discord", "mqtt", "home-assistant"];
+
+/// How often a day is, for [`Trigger::Daily`].
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A kind of run to notify of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Trigger {
+    /// The records were updated.
+    Change,
+
+    /// Updating the records failed.
+    Failure,
+
+    /// The records were updated or unchanged, after the previous run failed.
+    Recovery,
+
+    /// The first run of each day, whatever its outcome, as a summary of the current address.
+    Daily,
+}
+
+impl FromStr for Trigger {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "change" => Ok(Self::Change),
+            "failure" => Ok(Self::Failure),
+            "recovery" => Ok(Self::Recovery),
+            "daily" => Ok(Self::Daily),
+            _ => Err(format!(
+                "expected change, failure, recovery, or daily, not {s}"
+            )),
+        }
+    }
+}
+
+/// Which runs to notify a notifier of, overriding those it [wants](Notifier::wants).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Policy {
+    /// The runs to notify of, or `None` for those the notifier wants.
+    pub triggers: Option<Vec<Trigger>>,
+
+    /// The least time between two notifications, within which any other is dropped.
+    pub min_interval: Option<Duration>,
+}
+
+impl Policy {
+    /// Whether to tell `notifier` about a run with `outcome`, after a previous run with `previous`, if any, the last
+    /// notification `since_notified` ago, if any, and the last daily one `since_daily` ago, if any.
+    pub fn wants(
+        &self,
+        notifier: &dyn Notifier,
+        outcome: Outcome,
+        previous: Option<Outcome>,
+        since_notified: Option<Duration>,
+        since_daily: Option<Duration>,
+    ) -> bool {
+        if since_notified
+            .zip(self.min_interval)
+            .is_some_and(|(since, min_interval)| since < min_interval)
+        {
+            return false;
+        }
+
+        let Some(triggers) = &self.triggers else {
+            return notifier.wants(outcome);
+        };
+
+        triggers.iter().any(|trigger| match trigger {
+            Trigger::Change => outcome == Outcome::Updated,
+            Trigger::Failure => outcome == Outcome::Failed,
+            Trigger::Recovery => outcome != Outcome::Failed && previous == Some(Outcome::Failed),
+            Trigger::Daily => self.wants_daily(since_daily),
+        })
+    }
+
+    /// Whether a [`Trigger::Daily`] notification is due, the last daily one having been sent `since_daily` ago, if
+    /// ever. Notifications of other triggers don't count, so that frequent changes don't hold back the summary.
+    pub fn wants_daily(&self, since_daily: Option<Duration>) -> bool {
+        self.triggers
+            .as_ref()
+            .is_some_and(|triggers| triggers.contains(&Trigger::Daily))
+            && since_daily.map_or(true, |since| since >= DAY)
+    }
+}
+
+/// Parse the triggers of `--notify-on`, written like `change,recovery` for every notifier or `telegram=failure` for
+/// one of [`NOTIFIERS`].
+pub fn parse_triggers(value: &str) -> Result<(Option<String>, Vec<Trigger>), String> {
+    let (notifier, triggers) = split_notifier(value)?;
+    let triggers = triggers
+        .split(',')
+        .map(str::parse)
+        .collect::<Result<_, _>>()?;
+    Ok((notifier, triggers))
+}
+
+/// Parse the minimum interval of `--notify-interval`, written like `1h` for every notifier or `telegram=1h` for one
+/// of [`NOTIFIERS`].
+pub fn parse_interval(value: &str) -> Result<(Option<String>, Duration), String> {
+    let (notifier, interval) = split_notifier(value)?;
+    let interval = humantime::parse_duration(interval).map_err(|e| e.to_string())?;
+    Ok((notifier, interval))
+}
+
+fn split_notifier(value: &str) -> Result<(Option<String>, &str), String> {
+    match value.split_once('=') {
+        Some((notifier, _)) if !NOTIFIERS.contains(&notifier) => Err(format!(
+            "expected one of {} before the =, not {notifier}",
+            NOTIFIERS.join(", ")
+        )),
+        Some((notifier, value)) => Ok((Some(notifier.to_string()), value)),
+        None => Ok((None, value)),
+    }
+}
+
+/// Errors that can occur when notifying.
+#[derive(Debug)]
+pub enum NotifyError {
+    /// The request could not be sent, or was answered with an error status. The URL, which often contains a secret,
+    /// is left out of the error.
+    RequestFailed(reqwest::Error),
+
+    /// The service answered that it rejected the notification. Contains its explanation.
+    Rejected(String),
+
+    /// The command of a [hook](crate::hooks) failed.
+    Hook(HookError),
+
+    /// Publishing to an [MQTT](crate::mqtt) broker failed.
+    Mqtt(MqttError),
+}
+
+impl fmt::Display for NotifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::RequestFailed(e) => write!(f, "Failed to send the request: {e}."),
+            Self::Rejected(description) => {
+                write!(f, "The notification was rejected: {description}")
+            }
+            Self::Hook(e) => write!(f, "{e}"),
+            Self::Mqtt(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl error::Error for NotifyError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::RequestFailed(e) => Some(e),
+            Self::Rejected(_) => None,
+            Self::Hook(e) => Some(e),
+            Self::Mqtt(e) => Some(e),
+        }
+    }
+}
+
+impl From<reqwest::Error> for NotifyError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::RequestFailed(e.without_url())
+    }
+}
+
+/// A webhook posted to when the records are updated, and optionally when updating them fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Webhook {
+    url: String,
+    template: Option<String>,
+    on_failure: bool,
+}
+
+impl Webhook {
+    /// A webhook at `url`, posted the event as is, only when the records are updated.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            template: None,
+            on_failure: false,
+        }
+    }
+
+    /// Post `template`, checked with [`parse_template`], with the placeholders filled in instead of the event.
+    pub fn with_template(self, template: Option<String>) -> Self {
+        Self { template, ..self }
+    }
+
+    /// Whether to also post when updating the records fails.
+    pub fn with_on_failure(self, on_failure: bool) -> Self {
+        Self { on_failure, ..self }
+    }
+
+    /// The body posted for `event`.
+    pub fn body(&self, event: &Event) -> String {
+        match &self.template {
+            Some(template) => render(template, event),
+            None => to_json(event),
+        }
+    }
+}
+
+impl Notifier for Webhook {
+    fn name(&self) -> &str {
+        "the webhook"
+    }
+
+    fn wants(&self, outcome: Outcome) -> bool {
+        match outcome {
+            Outcome::Updated => true,
+            Outcome::Failed => self.on_failure,
+            Outcome::Unchanged => false,
+        }
+    }
+
+    fn send(&self, event: &Event, settings: &Settings) -> Result<(), NotifyError> {
+        settings
+            .builder()
+            .build()?
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(self.body(event))
+            .send()?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// `event` as a JSON object with its `old_ip`, `new_ip`, `record`, `timestamp`, and `outcome`.
+pub fn to_json(event: &Event) -> String {
+    json!({
+        "old_ip": event.old_ip,
+        "new_ip": event.new_ip,
+        "record": event.records,
+        "timestamp": event.time,
+        "outcome": event.result,
+    })
+    .to_string()
+}
+
+/// A line telling people about `event`, e.g. `example.com/home/A was updated to 203.0.113.7, from 203.0.113.6`.
+pub fn summary(event: &Event) -> String {
+    let records = &event.records;
+    let new_ip = event.new_ip;
+    match (event.result, event.old_ip) {
+        (Outcome::Updated, Some(old_ip)) if old_ip != new_ip => {
+            format!("{records} was updated to {new_ip}, from {old_ip}")
+        }
+        (Outcome::Updated, _) => format!("{records} was updated to {new_ip}"),
+        (Outcome::Unchanged, _) => format!("{records} is unchanged at {new_ip}"),
+        (Outcome::Failed, _) => format!("{records} could not be updated to {new_ip}"),
+    }
+}
+
+/// Check that a service accepted a notification with `response`, or return the explanation it gave in the `field` of
+/// its JSON body, e.g. that a chat wasn't found.
+pub fn check(response: Response, field: &str) -> Result<(), NotifyError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+
+    let answer: Value = response.json().unwrap_or_default();
+    Err(NotifyError::Rejected(
+        answer[field]
+            .as_str()
+            .map_or_else(|| status.to_string(), str::to_string),
+    ))
+}
+
+/// Fill in the placeholders of `template` with those of `event`.
+pub fn render(template: &str, event: &Event) -> String {
+    let values = [
+        (
+            "{old_ip}",
+            event.old_ip.map(|ip| ip.to_string()).unwrap_or_default(),
+        ),
+        ("{new_ip}", event.new_ip.to_string()),
+        ("{record}", event.records.clone()),
+        ("{timestamp}", event.time.clone()),
+        ("{outcome}", event.result.to_string()),
+    ];
+
+    values
+        .iter()
+        .fold(template.to_string(), |body, (placeholder, value)| {
+            body.replace(placeholder, &escape(value))
+        })
+}
+
+/// Parse a template of a webhook's body, checking that it's JSON once its placeholders are filled in.
+pub fn parse_template(template: &str) -> Result<String, String> {
+    let event = Event::new(
+        "example.com/home/A",
+        Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))),
+        IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)),
+        Outcome::Updated,
+    );
+
+    serde_json::from_str::<Value>(&render(template, &event))
+        .map(|_| template.to_string())
+        .map_err(|e| format!("the template isn't JSON once filled in: {e}"))
+}
+
+/// `value` escaped to be placed within a JSON string, without the quotes.
+fn escape(value: &str) -> String {
+    let quoted = Value::from(value).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_templates() {
+        let mut event = Event::new(
+            "example.com/home/A",
+            None,
+            IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)),
+            Outcome::Updated,
+        );
+        event.time = "2024-01-01T00:00:00Z".to_string();
+
+        assert_eq!(
+            render(
+                r#"{"text": "{record} is {new_ip}, was {old_ip}, {outcome} at {timestamp}"}"#,
+                &event
+            ),
+            r#"{"text": "example.com/home/A is 203.0.113.7, was , updated at 2024-01-01T00:00:00Z"}"#
+        );
+
+        event.records = r#"say "hi""#.to_string();
+        assert_eq!(render("{record}", &event), r#"say \"hi\""#);
+
+        let body: Value = serde_json::from_str(&Webhook::new("").body(&event)).unwrap();
+        assert_eq!(body["new_ip"], "203.0.113.7");
+        assert_eq!(body["old_ip"], Value::Null);
+
+        assert!(parse_template(r#"{"ip": "{new_ip}"}"#).is_ok());
+        assert!(parse_template(r#"{"ip": {new_ip}}"#).is_err());
+    }
+
+    #[test]
+    fn summarizes_events() {
+        let old_ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 6));
+        let new_ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+        let event = Event::new("example.com/home/A", Some(old_ip), new_ip, Outcome::Updated);
+        assert_eq!(
+            summary(&event),
+            "example.com/home/A was updated to 203.0.113.7, from 203.0.113.6"
+        );
+
+        let event = Event::new("example.com/home/A", None, new_ip, Outcome::Failed);
+        assert_eq!(
+            summary(&event),
+            "example.com/home/A could not be updated to 203.0.113.7"
+        );
+    }
+
+    #[test]
+    fn applies_policies() {
+        let webhook = Webhook::new("https://example.com/hook");
+        let hour = Duration::from_secs(60 * 60);
+
+        let default = Policy::default();
+        assert!(default.wants(&webhook, Outcome::Updated, None, Some(hour), None));
+        assert!(!default.wants(&webhook, Outcome::Failed, None, None, None));
+        assert!(!default.wants_daily(None));
+
+        let throttled = Policy {
+            min_interval: Some(2 * hour),
+            ..Policy::default()
+        };
+        assert!(!throttled.wants(&webhook, Outcome::Updated, None, Some(hour), None));
+        assert!(throttled.wants(&webhook, Outcome::Updated, None, Some(3 * hour), None));
+
+        let (notifier, triggers) = parse_triggers("telegram=recovery,daily").unwrap();
+        assert_eq!(notifier.as_deref(), Some("telegram"));
+        let policy = Policy {
+            triggers: Some(triggers),
+            min_interval: None,
+        };
+        let failed = Some(Outcome::Failed);
+        let (day, hours) = (Some(hour), Some(25 * hour));
+        assert!(policy.wants(&webhook, Outcome::Unchanged, failed, Some(hour), day));
+        assert!(!policy.wants(&webhook, Outcome::Failed, failed, Some(hour), day));
+        assert!(!policy.wants(&webhook, Outcome::Updated, None, Some(hour), day));
+        assert!(policy.wants(&webhook, Outcome::Unchanged, None, hours, hours));
+        assert!(policy.wants(&webhook, Outcome::Unchanged, None, None, None));
+
+        assert_eq!(parse_interval("1h"), Ok((None, hour)));
+        assert!(parse_triggers("change,sometimes").is_err());
+        assert!(parse_interval("pager=1h").is_err());
+    }
+
+    #[test]
+    fn sends_daily_between_changes() {
+        let webhook = Webhook::new("https://example.com/hook");
+        let hour = Duration::from_secs(60 * 60);
+        let policy = Policy {
+            triggers: Some(vec![Trigger::Change, Trigger::Daily]),
+            min_interval: None,
+        };
+
+        // A change was just notified, but the last summary was over a day ago.
+        let (since, daily) = (Some(hour), Some(25 * hour));
+        assert!(policy.wants_daily(daily));
+        assert!(policy.wants(&webhook, Outcome::Unchanged, None, since, daily));
+
+        // The summary was sent an hour ago: changes are still notified, unchanged runs aren't.
+        let daily = Some(hour);
+        assert!(!policy.wants_daily(daily));
+        assert!(policy.wants(&webhook, Outcome::Updated, None, since, daily));
+        assert!(!policy.wants(&webhook, Outcome::Unchanged, None, since, daily));
+    }
+
+    #[test]
+    fn only_wants_failures_if_asked() {
+        let webhook = Webhook::new("https://example.com/hook");
+        assert!(webhook.wants(Outcome::Updated));
+        assert!(!webhook.wants(Outcome::Unchanged));
+        assert!(!webhook.wants(Outcome::Failed));
+        assert!(webhook.with_on_failure(true).wants(Outcome::Failed));
+    }
+}
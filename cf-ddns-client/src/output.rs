@@ -0,0 +1,83 @@
+//! Formatting command output for people and for scripts.
+
+use std::fmt;
+
+/// The format in which commands print their results.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// An aligned, human-readable table, or lines describing each change for an update.
+    #[default]
+    Table,
+
+    /// A JSON array, or a single object describing the result of an update.
+    Json,
+}
+
+/// A table of text cells whose columns are aligned when displayed.
+pub struct Table {
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Create an empty table with the given column headers.
+    pub fn new<const N: usize>(header: [&str; N]) -> Self {
+        Self {
+            header: header.iter().map(|cell| cell.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Append a row to the table. The row should have as many cells as the header.
+    pub fn push<const N: usize>(&mut self, row: [String; N]) {
+        self.rows.push(row.into());
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut widths: Vec<usize> = self
+            .header
+            .iter()
+            .map(|cell| cell.chars().count())
+            .collect();
+
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        for row in std::iter::once(&self.header).chain(&self.rows) {
+            let mut line = String::new();
+            for (i, (cell, width)) in row.iter().zip(&widths).enumerate() {
+                if i > 0 {
+                    line.push_str("  ");
+                }
+
+                line.push_str(cell);
+                line.extend(std::iter::repeat(' ').take(width - cell.chars().count()));
+            }
+
+            writeln!(f, "{}", line.trim_end())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aligns_columns() {
+        let mut table = Table::new(["NAME", "TYPE"]);
+        table.push(["home.example.com".to_string(), "A".to_string()]);
+        table.push(["a".to_string(), "AAAA".to_string()]);
+        assert_eq!(
+            table.to_string(),
+            "NAME              TYPE\nhome.example.com  A\na                 AAAA\n"
+        );
+    }
+}
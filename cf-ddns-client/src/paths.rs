@@ -0,0 +1,84 @@
+//! Default locations of the files cf-ddns reads and writes, following the XDG base directory conventions.
+
+use std::path::PathBuf;
+
+/// The name of the subdirectory used within each base directory.
+const APP_DIR: &str = "cf-ddns";
+
+/// The directory for configuration written by the user, such as the [configuration file](crate::config).
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_DIR))
+}
+
+/// The directory for cached data that can be regenerated, such as resolved IDs.
+pub fn cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join(APP_DIR))
+}
+
+/// The directory for state that persists between runs but isn't worth backing up, such as API usage.
+///
+/// Falls back to the local data directory on platforms without a state directory.
+pub fn state_dir() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|dir| dir.join(APP_DIR))
+}
+
+/// The directory for runtime files that only matter while cf-ddns is running, such as locks.
+///
+/// Falls back to the state directory on platforms or sessions without a runtime directory.
+pub fn runtime_dir() -> Option<PathBuf> {
+    dirs::runtime_dir()
+        .map(|dir| dir.join(APP_DIR))
+        .or_else(state_dir)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+
+    /// A directory of the files of a test, named after it and this process, and removed with them when dropped, for
+    /// the tests of every module.
+    pub(crate) struct TempDir(PathBuf);
+
+    impl TempDir {
+        /// Create an empty directory for the test `name`.
+        pub(crate) fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("cf-ddns-{name}-test-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        /// The path of the directory.
+        pub(crate) fn path(&self) -> &Path {
+            &self.0
+        }
+
+        /// The path of the file `name` in the directory.
+        pub(crate) fn join(&self, name: &str) -> PathBuf {
+            self.0.join(name)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn removes_temp_dirs() {
+        let dir = TempDir::new("temp-dir");
+        let path = dir.path().to_path_buf();
+        fs::write(dir.join("file"), b"").unwrap();
+        assert!(path.is_dir());
+
+        drop(dir);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,128 @@
+//! Interactive prompts on the terminal.
+
+use std::io::{self, BufRead, Write};
+
+/// Ask a yes/no `question` on stderr and read the answer from stdin. Anything but `y` or `yes` is a no.
+pub fn confirm(question: &str) -> io::Result<bool> {
+    eprint!("{question} [y/N] ");
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Ask for a line of text on stderr and read it from stdin, without surrounding whitespace.
+pub fn ask(question: &str) -> io::Result<String> {
+    eprint!("{question} ");
+    io::stderr().flush()?;
+    read_line()
+}
+
+/// Ask for a secret like [`ask`], without echoing what is typed if stdin is a terminal.
+pub fn secret(question: &str) -> io::Result<String> {
+    eprint!("{question} ");
+    io::stderr().flush()?;
+
+    let _echo = Echo::disable();
+    let answer = read_line();
+    eprintln!();
+    answer
+}
+
+/// Ask to choose some of `count` numbered options, answered with their numbers separated by commas or spaces, or
+/// nothing at all. Asks again until the answer is valid. Returns the indices of the chosen options, in order.
+pub fn choose(question: &str, count: usize) -> io::Result<Vec<usize>> {
+    loop {
+        match parse_choices(&ask(question)?, count) {
+            Some(choices) => return Ok(choices),
+            None => eprintln!("Answer with numbers from 1 to {count}, separated by commas."),
+        }
+    }
+}
+
+/// Parse the numbers, counting from one, of options chosen among `count`, into their indices.
+pub fn parse_choices(answer: &str, count: usize) -> Option<Vec<usize>> {
+    let mut choices = Vec::new();
+    for choice in answer.split([',', ' ']).filter(|choice| !choice.is_empty()) {
+        let index = choice.parse::<usize>().ok()?.checked_sub(1)?;
+        if index >= count {
+            return None;
+        }
+
+        if !choices.contains(&index) {
+            choices.push(index);
+        }
+    }
+
+    Some(choices)
+}
+
+fn read_line() -> io::Result<String> {
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "stdin was closed",
+        ));
+    }
+
+    Ok(answer.trim().to_string())
+}
+
+/// Turns off echoing on the terminal connected to stdin until dropped.
+struct Echo {
+    #[cfg(unix)]
+    original: Option<libc::termios>,
+}
+
+impl Echo {
+    #[cfg(unix)]
+    fn disable() -> Self {
+        // SAFETY: termios is plain data that tcgetattr fills in, and is only used if it succeeded.
+        let original = unsafe {
+            let mut termios = std::mem::zeroed::<libc::termios>();
+            (libc::tcgetattr(libc::STDIN_FILENO, &mut termios) == 0).then_some(termios)
+        };
+
+        if let Some(original) = original {
+            let mut silent = original;
+            silent.c_lflag &= !libc::ECHO;
+            // SAFETY: silent is a valid termios obtained from tcgetattr.
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &silent) };
+        }
+
+        Self { original }
+    }
+
+    #[cfg(not(unix))]
+    fn disable() -> Self {
+        Self {}
+    }
+}
+
+impl Drop for Echo {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(original) = &self.original {
+            // SAFETY: original is the valid termios obtained from tcgetattr.
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, original) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_choices() {
+        assert_eq!(parse_choices("", 3), Some(vec![]));
+        assert_eq!(parse_choices("2", 3), Some(vec![1]));
+        assert_eq!(parse_choices("3, 1,3", 3), Some(vec![2, 0]));
+        assert_eq!(parse_choices("1 2", 3), Some(vec![0, 1]));
+        assert_eq!(parse_choices("0", 3), None);
+        assert_eq!(parse_choices("4", 3), None);
+        assert_eq!(parse_choices("home", 3), None);
+    }
+}
@@ -0,0 +1,115 @@
+//! A persistent ledger of Cloudflare API requests, shared by every run on this machine.
+//!
+//! The Cloudflare client only knows about the requests it has sent itself and what the last response reported. The
+//! ledger remembers when previous runs sent their requests so that a run can start with a budget of what's left of
+//! the [rate limit window](cf_ddns_cloudflare::ratelimit::WINDOW), even before its first response.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use cf_ddns_cloudflare::ratelimit::WINDOW;
+use serde::{Deserialize, Serialize};
+
+/// The file name of the ledger within the [state directory](crate::paths::state_dir).
+pub const FILE_NAME: &str = "ratelimit.json";
+
+/// Held while a run records its requests, for runs updating other records at the same time.
+static RECORDING: Mutex<()> = Mutex::new(());
+
+/// When recent requests were sent, in seconds since the Unix epoch.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Ledger {
+    #[serde(default)]
+    calls: Vec<u64>,
+}
+
+impl Ledger {
+    /// Load the ledger from `path`. A missing or unreadable ledger is empty.
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save the ledger to `path`, creating its parent directories if needed.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, serde_json::to_string(self)?)
+    }
+
+    /// The number of requests sent within the current window.
+    pub fn recent(&self) -> u32 {
+        self.recent_at(now())
+    }
+
+    /// Record that `count` requests were just sent, forgetting those that have left the window.
+    pub fn record(&mut self, count: u32) {
+        self.record_at(count, now());
+    }
+
+    /// Record that `count` requests were just sent in the ledger at `path`, including those recorded there since
+    /// this run started.
+    pub fn record_in(path: &Path, count: u32) -> io::Result<()> {
+        let _recording = RECORDING.lock().unwrap_or_else(|e| e.into_inner());
+        let mut ledger = Self::load(path);
+        ledger.record(count);
+        ledger.save(path)
+    }
+
+    fn recent_at(&self, now: u64) -> u32 {
+        let start = now.saturating_sub(WINDOW.as_secs());
+        self.calls
+            .iter()
+            .filter(|&&at| at > start)
+            .count()
+            .try_into()
+            .unwrap_or(u32::MAX)
+    }
+
+    fn record_at(&mut self, count: u32, now: u64) {
+        let start = now.saturating_sub(WINDOW.as_secs());
+        self.calls.retain(|&at| at > start);
+        self.calls
+            .extend(std::iter::repeat(now).take(count as usize));
+    }
+}
+
+/// The default path of the ledger, if a state directory can be determined.
+pub fn default_path() -> Option<PathBuf> {
+    crate::paths::state_dir().map(|dir| dir.join(FILE_NAME))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn old_calls_leave_the_window() {
+        let mut ledger = Ledger::default();
+        ledger.record_at(3, 1000);
+        assert_eq!(ledger.recent_at(1000), 3);
+        assert_eq!(ledger.recent_at(1000 + WINDOW.as_secs() - 1), 3);
+        assert_eq!(ledger.recent_at(1000 + WINDOW.as_secs()), 0);
+    }
+
+    #[test]
+    fn recording_prunes_old_calls() {
+        let mut ledger = Ledger::default();
+        ledger.record_at(3, 1000);
+        ledger.record_at(2, 1000 + WINDOW.as_secs());
+        assert_eq!(ledger.calls.len(), 2);
+    }
+}
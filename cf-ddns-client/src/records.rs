@@ -0,0 +1,469 @@
+//! Selecting which DNS records to update.
+
+use std::error;
+use std::fmt;
+use std::net::IpAddr;
+
+use cf_ddns_cloudflare::{Record, RecordContent};
+use serde::Deserialize;
+
+use crate::idn;
+
+/// What to do when several records match the name being updated, such as a round-robin set of `A` records.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnMultiple {
+    /// Refuse to update any record.
+    #[default]
+    Error,
+
+    /// Update only the first matching record.
+    First,
+
+    /// Update every matching record.
+    All,
+}
+
+/// Errors that can occur when selecting records to update.
+#[derive(Debug, PartialEq)]
+pub enum SelectError {
+    /// No record with the name and a type matching the address family was found.
+    NotFound(String),
+
+    /// Several records matched and the policy was [`OnMultiple::Error`]. Contains the name and the number of matches.
+    Multiple(String, usize),
+}
+
+impl fmt::Display for SelectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotFound(name) => write!(f, "No records found for {name}."),
+            Self::Multiple(name, count) => write!(
+                f,
+                "Found {count} records for {name}. Use --on-multiple to choose which to update."
+            ),
+        }
+    }
+}
+
+impl error::Error for SelectError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// The record name referring to the zone apex.
+pub const APEX: &str = "@";
+
+/// Normalize a DNS name for comparing it with the names the Cloudflare API returns: lowercase, in [ASCII
+/// form](crate::idn), and without the trailing dot of an absolute name, so that `Home.Example.com.` becomes
+/// `home.example.com` and `bücher.example` becomes `xn--bcher-kva.example`.
+pub fn normalize(name: &str) -> String {
+    idn::to_ascii(name.trim_end_matches('.')).to_ascii_lowercase()
+}
+
+/// Get the fully-qualified name of `record_name` in the zone `zone_name`.
+///
+/// [`APEX`] refers to the zone itself. A name that already ends with the zone, such as `home.example.com` or
+/// `home.example.com.` in `example.com`, is taken as fully-qualified, so that it isn't suffixed twice. Wildcards such
+/// as `*` and `*.sub` are relative names like any other, so they become `*.example.com` and `*.sub.example.com`.
+pub fn fqdn(record_name: &str, zone_name: &str) -> String {
+    let record_name = normalize(record_name);
+    let zone_name = normalize(zone_name);
+    if record_name == APEX || record_name.is_empty() || record_name == zone_name {
+        zone_name
+    } else if in_zone(&record_name, &zone_name) {
+        record_name
+    } else {
+        format!("{record_name}.{zone_name}")
+    }
+}
+
+/// Get the name of `fqdn` relative to the zone `zone_name`, the inverse of [`fqdn`]. Names outside the zone are
+/// returned unchanged.
+pub fn relative_name(fqdn: &str, zone_name: &str) -> String {
+    let name = normalize(fqdn);
+    let zone_name = normalize(zone_name);
+    if name == zone_name {
+        APEX.to_string()
+    } else if in_zone(&name, &zone_name) {
+        name[..name.len() - zone_name.len() - 1].to_string()
+    } else {
+        fqdn.to_string()
+    }
+}
+
+/// Whether the normalized `name` is a subdomain of the normalized `zone_name`.
+fn in_zone(name: &str, zone_name: &str) -> bool {
+    name.strip_suffix(zone_name)
+        .is_some_and(|name| name.ends_with('.'))
+}
+
+/// A way of discovering the records to update, instead of naming them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Discover {
+    /// Records carrying a tag, either exactly or as the name of a `name:value` tag.
+    Tag(String),
+
+    /// Records whose comment contains some text.
+    Comment(String),
+}
+
+impl Discover {
+    /// Whether `record` is matched by this discovery method.
+    pub fn matches(&self, record: &Record) -> bool {
+        match self {
+            Self::Tag(tag) => record.tags.iter().any(|t| {
+                t == tag
+                    || t.strip_prefix(tag.as_str())
+                        .is_some_and(|rest| rest.starts_with(':'))
+            }),
+            Self::Comment(text) => record
+                .comment
+                .as_deref()
+                .is_some_and(|comment| comment.contains(text.as_str())),
+        }
+    }
+}
+
+/// How the records of a zone are selected.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Selector {
+    /// The record with an ID.
+    Id(String),
+
+    /// The records with a name, relative to the zone.
+    Name(String),
+
+    /// The records found by a [discovery method](Discover).
+    Discover(Discover),
+}
+
+/// Discover the records among `records` to update to `ip`: those matched by `discover` whose type matches the
+/// family of `ip`.
+pub fn discover(ip: IpAddr, records: Vec<Record>, discover: &Discover) -> Vec<Record> {
+    records
+        .into_iter()
+        .filter(|record| matches_family(record, ip) && discover.matches(record))
+        .collect()
+}
+
+/// The record type for the family of `ip`, i.e. `A` for IPv4 and `AAAA` for IPv6.
+pub fn record_type(ip: IpAddr) -> &'static str {
+    match ip {
+        IpAddr::V4(_) => "A",
+        IpAddr::V6(_) => "AAAA",
+    }
+}
+
+/// Whether a record's type matches the family of `ip`, i.e. `A` for IPv4 and `AAAA` for IPv6.
+pub fn matches_family(record: &Record, ip: IpAddr) -> bool {
+    matches!(
+        (&record.content, ip),
+        (RecordContent::A { .. }, IpAddr::V4(_)) | (RecordContent::AAAA { .. }, IpAddr::V6(_))
+    )
+}
+
+/// Select the records named `name` to update to `ip` according to `policy`.
+///
+/// Only records whose type matches the family of `ip` are considered, so that the `A` and `AAAA` records of a
+/// dual-stack name are not counted as duplicates of each other.
+pub fn select(
+    name: &str,
+    ip: IpAddr,
+    records: Vec<Record>,
+    policy: OnMultiple,
+) -> Result<Vec<Record>, SelectError> {
+    let mut records: Vec<Record> = records
+        .into_iter()
+        .filter(|record| matches_family(record, ip))
+        .collect();
+
+    match (records.len(), policy) {
+        (0, _) => Err(SelectError::NotFound(name.to_string())),
+        (1, _) | (_, OnMultiple::All) => Ok(records),
+        (_, OnMultiple::First) => {
+            records.truncate(1);
+            Ok(records)
+        }
+        (count, OnMultiple::Error) => Err(SelectError::Multiple(name.to_string(), count)),
+    }
+}
+
+/// The changes that make the records of a name have exactly a set of addresses of one family.
+#[derive(Debug, Default, PartialEq)]
+pub struct Reconciliation {
+    /// The records that already have one of the addresses.
+    pub keep: Vec<Record>,
+
+    /// The records that have none of the addresses, each with a missing address to change it to.
+    pub change: Vec<(Record, IpAddr)>,
+
+    /// The missing addresses left once every record has been reused, which need new records.
+    pub create: Vec<IpAddr>,
+
+    /// The records that have none of the addresses and aren't needed for a missing one.
+    pub delete: Vec<Record>,
+}
+
+impl Reconciliation {
+    /// Whether the records already have exactly the addresses.
+    pub fn is_noop(&self) -> bool {
+        self.change.is_empty() && self.create.is_empty() && self.delete.is_empty()
+    }
+}
+
+/// Plan how to make the records among `records` whose type matches the family of `ips` have exactly `ips`, which
+/// must all be of the same family.
+///
+/// Records that have none of the addresses are changed to a missing address rather than deleted while there are
+/// missing addresses, so that as few records as possible are created and deleted. Of several records with the same
+/// address, all but one are surplus.
+pub fn reconcile(ips: &[IpAddr], records: Vec<Record>) -> Reconciliation {
+    let mut missing: Vec<IpAddr> = Vec::new();
+    for &ip in ips {
+        if !missing.contains(&ip) {
+            missing.push(ip);
+        }
+    }
+
+    let mut reconciliation = Reconciliation::default();
+    let mut surplus = Vec::new();
+    let records = records
+        .into_iter()
+        .filter(|record| ips.first().is_some_and(|&ip| matches_family(record, ip)));
+
+    for record in records {
+        let found = record
+            .content
+            .ip()
+            .and_then(|ip| missing.iter().position(|&missing| missing == ip));
+
+        match found {
+            Some(i) => {
+                missing.remove(i);
+                reconciliation.keep.push(record);
+            }
+            None => surplus.push(record),
+        }
+    }
+
+    let mut missing = missing.into_iter();
+    for record in surplus {
+        match missing.next() {
+            Some(ip) => reconciliation.change.push((record, ip)),
+            None => reconciliation.delete.push(record),
+        }
+    }
+
+    reconciliation.create = missing.collect();
+    reconciliation
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use super::*;
+
+    /// A record named `name` in `example.com`, with the defaults of Cloudflare, for the tests of every module.
+    pub(crate) fn record(id: &str, name: &str, content: RecordContent) -> Record {
+        Record {
+            id: id.to_string(),
+            zone_name: "example.com".to_string(),
+            name: name.to_string(),
+            content,
+            ttl: 1,
+            proxied: false,
+            priority: None,
+            comment: None,
+            tags: Vec::new(),
+            modified_on: None,
+        }
+    }
+
+    fn records() -> Vec<Record> {
+        vec![
+            record(
+                "1",
+                "home.example.com",
+                RecordContent::A {
+                    content: Ipv4Addr::new(10, 0, 0, 1),
+                },
+            ),
+            record(
+                "2",
+                "home.example.com",
+                RecordContent::A {
+                    content: Ipv4Addr::new(10, 0, 0, 2),
+                },
+            ),
+            record(
+                "3",
+                "home.example.com",
+                RecordContent::AAAA {
+                    content: Ipv6Addr::LOCALHOST,
+                },
+            ),
+        ]
+    }
+
+    fn ids(records: Vec<Record>) -> Vec<String> {
+        records.into_iter().map(|record| record.id).collect()
+    }
+
+    const V4: IpAddr = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+    const V6: IpAddr = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+
+    #[test]
+    fn single_match_of_family() {
+        let selected = select("home", V6, records(), OnMultiple::Error).unwrap();
+        assert_eq!(ids(selected), ["3"]);
+    }
+
+    #[test]
+    fn multiple_is_error_by_default() {
+        let err = select("home", V4, records(), OnMultiple::default()).unwrap_err();
+        assert_eq!(err, SelectError::Multiple("home".to_string(), 2));
+    }
+
+    #[test]
+    fn multiple_first() {
+        let selected = select("home", V4, records(), OnMultiple::First).unwrap();
+        assert_eq!(ids(selected), ["1"]);
+    }
+
+    #[test]
+    fn multiple_all() {
+        let selected = select("home", V4, records(), OnMultiple::All).unwrap();
+        assert_eq!(ids(selected), ["1", "2"]);
+    }
+
+    #[test]
+    fn fqdn_of_relative_name() {
+        assert_eq!(fqdn("home", "example.com"), "home.example.com");
+    }
+
+    #[test]
+    fn fqdn_of_apex() {
+        assert_eq!(fqdn("@", "example.com"), "example.com");
+    }
+
+    #[test]
+    fn fqdn_of_wildcards() {
+        assert_eq!(fqdn("*", "example.com"), "*.example.com");
+        assert_eq!(fqdn("*.sub", "example.com"), "*.sub.example.com");
+    }
+
+    #[test]
+    fn fqdn_of_absolute_names() {
+        assert_eq!(fqdn("home.example.com", "example.com"), "home.example.com");
+        assert_eq!(
+            fqdn("home.example.com.", "example.com."),
+            "home.example.com"
+        );
+        assert_eq!(fqdn("Home.Example.com", "example.com"), "home.example.com");
+        assert_eq!(fqdn("example.com.", "example.com"), "example.com");
+        assert_eq!(fqdn("home.", "example.com"), "home.example.com");
+        assert_eq!(
+            fqdn("badexample.com", "example.com"),
+            "badexample.com.example.com"
+        );
+    }
+
+    #[test]
+    fn relative_names() {
+        assert_eq!(relative_name("home.example.com", "example.com"), "home");
+        assert_eq!(relative_name("example.com", "example.com"), "@");
+        assert_eq!(relative_name("*.sub.example.com", "example.com"), "*.sub");
+        assert_eq!(relative_name("Home.Example.com.", "example.com"), "home");
+        assert_eq!(
+            relative_name("badexample.com", "example.com"),
+            "badexample.com"
+        );
+    }
+
+    #[test]
+    fn discover_by_tag() {
+        let mut records = records();
+        records[0].tags = vec!["ddns".to_string()];
+        records[1].tags = vec!["ddns:home".to_string()];
+        records[2].tags = vec!["ddns".to_string()];
+        let discovered = discover(V4, records.clone(), &Discover::Tag("ddns".to_string()));
+        assert_eq!(ids(discovered), ["1", "2"]);
+
+        let discovered = discover(V4, records, &Discover::Tag("dd".to_string()));
+        assert!(discovered.is_empty());
+    }
+
+    #[test]
+    fn discover_by_comment() {
+        let mut records = records();
+        records[1].comment = Some("points home".to_string());
+        let discovered = discover(V4, records, &Discover::Comment("home".to_string()));
+        assert_eq!(ids(discovered), ["2"]);
+    }
+
+    #[test]
+    fn none_of_family() {
+        let err = select("home", V6, records()[..2].to_vec(), OnMultiple::All).unwrap_err();
+        assert_eq!(err, SelectError::NotFound("home".to_string()));
+    }
+
+    #[test]
+    fn reconcile_keeps_matching_records() {
+        let ips = [
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+        ];
+
+        let reconciliation = reconcile(&ips, records());
+        assert!(reconciliation.is_noop());
+        assert_eq!(ids(reconciliation.keep), ["1", "2"]);
+    }
+
+    #[test]
+    fn reconcile_changes_surplus_records_before_creating() {
+        let ips = [IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), V4, V4];
+        let reconciliation = reconcile(&ips, records());
+        assert_eq!(ids(reconciliation.keep), ["1"]);
+        assert_eq!(reconciliation.change.len(), 1);
+        assert_eq!(reconciliation.change[0].0.id, "2");
+        assert_eq!(reconciliation.change[0].1, V4);
+        assert!(reconciliation.create.is_empty());
+        assert!(reconciliation.delete.is_empty());
+
+        let other = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 8));
+        let reconciliation = reconcile(
+            &[V4, other, IpAddr::V4(Ipv4Addr::new(10, 0, 0, 9))],
+            records(),
+        );
+        assert!(reconciliation.keep.is_empty());
+        assert_eq!(reconciliation.change.len(), 2);
+        assert_eq!(
+            reconciliation.create,
+            [IpAddr::V4(Ipv4Addr::new(10, 0, 0, 9))]
+        );
+    }
+
+    #[test]
+    fn reconcile_deletes_surplus_records() {
+        let mut duplicated = records();
+        duplicated.push(record(
+            "4",
+            "home.example.com",
+            RecordContent::A {
+                content: Ipv4Addr::new(10, 0, 0, 1),
+            },
+        ));
+
+        let reconciliation = reconcile(&[IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))], duplicated);
+        assert_eq!(ids(reconciliation.keep), ["1"]);
+        assert!(reconciliation.change.is_empty());
+        assert_eq!(ids(reconciliation.delete), ["2", "4"]);
+
+        let reconciliation = reconcile(&[V6], records());
+        assert_eq!(reconciliation.change[0].0.id, "3");
+        assert!(reconciliation.delete.is_empty());
+    }
+}
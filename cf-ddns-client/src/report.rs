@@ -0,0 +1,196 @@
+//! Collecting the result of an update, so that `--output json` can print it as a single JSON object for scripts
+//! instead of the human-readable lines.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::exit::Exit;
+
+static REPORT: Mutex<Option<Report>> = Mutex::new(None);
+
+/// The result of an update.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Report {
+    /// The records that were looked at.
+    pub records: Vec<RecordResult>,
+
+    /// Whether any record was changed, or would have been by a dry run.
+    pub changed: bool,
+
+    /// Whether this was a dry run, which changes nothing.
+    pub dry_run: bool,
+
+    /// How long the run took, in milliseconds.
+    pub duration_ms: u64,
+
+    /// The errors that occurred, in order.
+    pub errors: Vec<ErrorReport>,
+
+    /// The warnings about things that went wrong without failing the run, in order.
+    pub warnings: Vec<String>,
+
+    /// The exit code of the run.
+    pub exit_code: u8,
+}
+
+/// What happened to one record.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RecordResult {
+    /// The fully-qualified name of the record.
+    pub name: String,
+
+    /// The type of the record, `A` or `AAAA`.
+    #[serde(rename = "type")]
+    pub record_type: String,
+
+    /// The content of the record before the run, or `None` if it was created.
+    pub old_ip: Option<String>,
+
+    /// The content of the record after the run, or `None` if it was deleted.
+    pub new_ip: Option<String>,
+
+    /// Whether the record was changed, or would have been by a dry run.
+    pub changed: bool,
+}
+
+/// An error that occurred during the run.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ErrorReport {
+    /// The class of the error, named after its [`Exit`], e.g. `auth`.
+    pub class: &'static str,
+
+    /// The exit code of the class.
+    pub code: u8,
+
+    /// What went wrong, as printed without `--output json`.
+    pub message: String,
+
+    /// Whether a later run may succeed without changing the setup, e.g. after a network failure.
+    pub retryable: bool,
+}
+
+impl ErrorReport {
+    /// An error of the class `exit`, retryable if [the class is](Exit::is_retryable).
+    pub fn new(exit: Exit, message: String) -> Self {
+        Self {
+            class: exit.name(),
+            code: exit as u8,
+            message,
+            retryable: exit.is_retryable(),
+        }
+    }
+
+    /// Overrides whether the error is retryable.
+    pub fn with_retryable(self, retryable: bool) -> Self {
+        Self { retryable, ..self }
+    }
+}
+
+/// Start collecting the result of the run.
+pub fn start(dry_run: bool) {
+    *lock() = Some(Report {
+        dry_run,
+        ..Report::default()
+    });
+}
+
+/// Whether the result of the run is being collected, in which case the human-readable lines aren't printed.
+pub fn is_collecting() -> bool {
+    lock().is_some()
+}
+
+/// Add a record to the result, if it's being collected.
+pub fn record(result: RecordResult) {
+    if let Some(report) = lock().as_mut() {
+        report.changed |= result.changed;
+        report.records.push(result);
+    }
+}
+
+/// Add an error to the result, if it's being collected.
+pub fn error(error: ErrorReport) {
+    if let Some(report) = lock().as_mut() {
+        report.errors.push(error);
+    }
+}
+
+/// Add a warning to the result, if it's being collected.
+pub fn warning(message: String) {
+    if let Some(report) = lock().as_mut() {
+        report.warnings.push(message);
+    }
+}
+
+/// Stop collecting, and return the result of a run that took `duration` and exited with `exit`, if it was collected.
+pub fn finish(duration: Duration, exit: Exit) -> Option<Report> {
+    lock().take().map(|report| Report {
+        duration_ms: duration.as_millis() as u64,
+        exit_code: exit as u8,
+        ..report
+    })
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Report>> {
+    // A panic while holding the lock leaves a consistent report behind.
+    REPORT.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_records_and_errors() {
+        record(RecordResult {
+            name: "ignored.example.com".to_string(),
+            record_type: "A".to_string(),
+            old_ip: None,
+            new_ip: None,
+            changed: true,
+        });
+
+        start(false);
+        assert!(is_collecting());
+        record(RecordResult {
+            name: "home.example.com".to_string(),
+            record_type: "A".to_string(),
+            old_ip: Some("203.0.113.7".to_string()),
+            new_ip: Some("203.0.113.8".to_string()),
+            changed: true,
+        });
+        error(
+            ErrorReport::new(
+                Exit::Api,
+                "Could not update record 123: conflict".to_string(),
+            )
+            .with_retryable(true),
+        );
+        warning("Could not write the state file: read-only".to_string());
+
+        let report = finish(Duration::from_millis(1500), Exit::Api).unwrap();
+        assert!(!is_collecting());
+        assert!(report.changed);
+        assert_eq!(report.records.len(), 1);
+        assert_eq!(report.duration_ms, 1500);
+        assert_eq!(report.exit_code, 8);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["records"][0]["type"], "A");
+        assert_eq!(json["records"][0]["old_ip"], "203.0.113.7");
+        assert_eq!(
+            json["errors"][0],
+            serde_json::json!({
+                "class": "api",
+                "code": 8,
+                "message": "Could not update record 123: conflict",
+                "retryable": true,
+            })
+        );
+        assert_eq!(
+            json["warnings"][0],
+            "Could not write the state file: read-only"
+        );
+    }
+}
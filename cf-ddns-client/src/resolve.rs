@@ -0,0 +1,187 @@
+//! Resolving host names, with the system's resolver, or with hickory-resolver with the `hickory-dns` feature.
+//!
+//! The system's resolver is that of the C library, which behaves differently on static musl builds: older versions
+//! of musl don't retry over TCP, and none reads `nsswitch.conf`. hickory-resolver reads `/etc/resolv.conf` itself, or asks the servers given with
+//! `set_servers` instead, and resolves the hosts of every client built with [`crate::http::client_builder`] as well
+//! as those passed to [`lookup`].
+
+use std::io;
+#[cfg(not(feature = "hickory-dns"))]
+use std::net::ToSocketAddrs;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "hickory-dns")]
+pub use hickory::{parse_server, set_servers, HickoryResolver};
+
+/// The addresses of `host`, with `port`. An address written literally, like `192.0.2.1` or `[2001:db8::1]`, is
+/// returned as is.
+pub fn lookup(host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+    let literal = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = literal.parse::<IpAddr>() {
+        return Ok(vec![SocketAddr::new(ip, port)]);
+    }
+
+    #[cfg(feature = "hickory-dns")]
+    return hickory::lookup(host, port);
+
+    #[cfg(not(feature = "hickory-dns"))]
+    (host, port).to_socket_addrs().map(Iterator::collect)
+}
+
+/// Like [`lookup`], but failing if the addresses take longer than `timeout`, as the system's resolver can't be given
+/// one. A lookup that times out is left to finish on a thread of its own.
+pub fn lookup_timeout(host: &str, port: u16, timeout: Duration) -> io::Result<Vec<SocketAddr>> {
+    let literal = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = literal.parse::<IpAddr>() {
+        return Ok(vec![SocketAddr::new(ip, port)]);
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let name = host.to_string();
+    thread::spawn(move || sender.send(lookup(&name, port)));
+    receiver.recv_timeout(timeout).unwrap_or_else(|_| {
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("timed out after {}", humantime::format_duration(timeout)),
+        ))
+    })
+}
+
+/// The addresses of `address`, written like `host:port`.
+pub fn lookup_address(address: &str) -> io::Result<Vec<SocketAddr>> {
+    address
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host, port.parse().ok()?)))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "expected HOST:PORT"))
+        .and_then(|(host, port)| lookup(host, port))
+}
+
+#[cfg(feature = "hickory-dns")]
+mod hickory {
+    use std::io;
+    use std::net::{IpAddr, SocketAddr};
+    use std::sync::Mutex;
+
+    use hickory_resolver::config::{
+        LookupIpStrategy, NameServerConfig, NameServerConfigGroup, ResolverConfig,
+    };
+    use hickory_resolver::name_server::TokioConnectionProvider;
+    use hickory_resolver::proto::xfer::Protocol;
+    use hickory_resolver::{ResolveError, TokioResolver};
+    use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+
+    /// The port of DNS servers given without one.
+    const DNS_PORT: u16 = 53;
+
+    /// The servers to ask instead of those of `/etc/resolv.conf`, if any.
+    static SERVERS: Mutex<Vec<SocketAddr>> = Mutex::new(Vec::new());
+
+    /// Ask `servers`, over UDP and TCP, instead of the servers of `/etc/resolv.conf`, or those again if empty.
+    pub fn set_servers(servers: Vec<SocketAddr>) {
+        *SERVERS.lock().unwrap_or_else(|e| e.into_inner()) = servers;
+    }
+
+    /// Parse the address of a DNS server, with port 53 unless it's given like `192.0.2.53:5353` or `[::1]:5353`.
+    pub fn parse_server(server: &str) -> Result<SocketAddr, String> {
+        server
+            .parse::<SocketAddr>()
+            .or_else(|_| {
+                server
+                    .parse::<IpAddr>()
+                    .map(|ip| SocketAddr::new(ip, DNS_PORT))
+            })
+            .map_err(|_| format!("expected an IP address, optionally with a port, not {server}"))
+    }
+
+    fn resolver() -> Result<TokioResolver, ResolveError> {
+        let servers = SERVERS.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let mut builder = match servers.as_slice() {
+            [] => TokioResolver::builder_tokio()?,
+            servers => {
+                let group: NameServerConfigGroup = servers
+                    .iter()
+                    .flat_map(|&server| {
+                        [Protocol::Udp, Protocol::Tcp]
+                            .map(|protocol| NameServerConfig::new(server, protocol))
+                    })
+                    .collect::<Vec<_>>()
+                    .into();
+
+                let config = ResolverConfig::from_parts(None, Vec::new(), group);
+                TokioResolver::builder_with_config(config, TokioConnectionProvider::default())
+            }
+        };
+
+        // Both families are looked up, so that either can be chosen or raced.
+        builder.options_mut().ip_strategy = LookupIpStrategy::Ipv4AndIpv6;
+        Ok(builder.build())
+    }
+
+    /// Resolves the host names of requests with hickory-resolver, for [`reqwest::blocking::ClientBuilder::dns_resolver`].
+    #[derive(Debug, Default)]
+    pub struct HickoryResolver;
+
+    impl Resolve for HickoryResolver {
+        fn resolve(&self, name: Name) -> Resolving {
+            Box::pin(async move {
+                let lookup = resolver()?.lookup_ip(name.as_str()).await?;
+                let addrs: Addrs = Box::new(lookup.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+                Ok(addrs)
+            })
+        }
+    }
+
+    pub(super) fn lookup(host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        let lookup = runtime
+            .block_on(async { resolver()?.lookup_ip(host).await })
+            .map_err(io::Error::other)?;
+
+        Ok(lookup
+            .into_iter()
+            .map(|ip| SocketAddr::new(ip, port))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looks_up_literal_addresses() {
+        assert_eq!(
+            lookup("192.0.2.1", 80).unwrap(),
+            ["192.0.2.1:80".parse().unwrap()]
+        );
+        assert_eq!(
+            lookup_address("[2001:db8::1]:443").unwrap(),
+            ["[2001:db8::1]:443".parse().unwrap()]
+        );
+        assert!(lookup_address("192.0.2.1").is_err());
+        assert_eq!(
+            lookup_timeout("[2001:db8::1]", 443, Duration::ZERO).unwrap(),
+            ["[2001:db8::1]:443".parse().unwrap()]
+        );
+    }
+
+    #[cfg(feature = "hickory-dns")]
+    #[test]
+    fn parses_servers() {
+        assert_eq!(
+            parse_server("192.0.2.53"),
+            Ok("192.0.2.53:53".parse().unwrap())
+        );
+        assert_eq!(
+            parse_server("[::1]:5353"),
+            Ok("[::1]:5353".parse().unwrap())
+        );
+        assert!(parse_server("dns.example").is_err());
+    }
+}
@@ -0,0 +1,111 @@
+//! Retrying operations that fail transiently, with exponential backoff and jitter.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::thread;
+use std::time::Duration;
+
+/// How often, and how long apart, to retry an operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Retry {
+    /// How many times to retry after the first attempt fails.
+    pub retries: u32,
+
+    /// The delay before the first retry. Each further retry waits twice as long as the previous one.
+    pub delay: Duration,
+}
+
+impl Default for Retry {
+    /// Don't retry.
+    fn default() -> Self {
+        Self {
+            retries: 0,
+            delay: Duration::from_secs(1),
+        }
+    }
+}
+
+impl Retry {
+    /// Run `operation`, retrying it while it fails with an error for which `retryable` is true.
+    pub fn run<T, E>(
+        &self,
+        mut operation: impl FnMut() -> Result<T, E>,
+        retryable: impl Fn(&E) -> bool,
+    ) -> Result<T, E> {
+        let mut attempt = 0;
+        loop {
+            match operation() {
+                Err(err) if attempt < self.retries && retryable(&err) => {
+                    thread::sleep(self.delay(attempt, random()));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// The delay before retry number `attempt`, counting from zero, scaled by a jitter of between 0.5 and 1.5
+    /// depending on `random`, so that clients started at the same time don't retry in lockstep.
+    fn delay(&self, attempt: u32, random: u64) -> Duration {
+        let backoff = self.delay.saturating_mul(2u32.saturating_pow(attempt));
+        let jitter = 0.5 + (random % 1000) as f64 / 1000.0;
+        backoff.mul_f64(jitter)
+    }
+}
+
+/// A random number, from the randomly seeded hasher of the standard library.
+pub fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[test]
+    fn delay_backs_off_with_jitter() {
+        let retry = Retry {
+            retries: 3,
+            delay: Duration::from_secs(1),
+        };
+
+        assert_eq!(retry.delay(0, 0), Duration::from_millis(500));
+        assert_eq!(retry.delay(0, 500), Duration::from_secs(1));
+        assert_eq!(retry.delay(2, 500), Duration::from_secs(4));
+        assert_eq!(retry.delay(2, 999), Duration::from_millis(5996));
+    }
+
+    #[test]
+    fn retries_only_retryable_errors() {
+        let retry = Retry {
+            retries: 2,
+            delay: Duration::ZERO,
+        };
+
+        let attempts = Cell::new(0);
+        let result: Result<(), bool> = retry.run(
+            || {
+                attempts.set(attempts.get() + 1);
+                Err(true)
+            },
+            |&retryable| retryable,
+        );
+
+        assert_eq!(result, Err(true));
+        assert_eq!(attempts.get(), 3);
+
+        attempts.set(0);
+        let result: Result<(), bool> = retry.run(
+            || {
+                attempts.set(attempts.get() + 1);
+                Err(false)
+            },
+            |&retryable| retryable,
+        );
+
+        assert_eq!(result, Err(false));
+        assert_eq!(attempts.get(), 1);
+    }
+}
@@ -0,0 +1,305 @@
+//! Replacing the running binary with the one of the latest GitHub release, for `self-update` on routers and NAS
+//! devices without a package manager.
+//!
+//! Each release carries a binary per target, named like `cf-ddns-client-x86_64-unknown-linux-gnu`, and a
+//! `SHA256SUMS` file listing their checksums in the format of `sha256sum`. The binary is only installed if its
+//! checksum matches.
+
+use std::error;
+use std::fmt::{self, Write};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::build_info::{TARGET, VERSION};
+
+/// The GitHub API endpoint describing the latest release.
+pub const LATEST_RELEASE: &str = "https://api.github.com/repos/asasine/cf-ddns/releases/latest";
+
+/// The name of the release asset listing the checksums of the binaries.
+pub const CHECKSUMS: &str = "SHA256SUMS";
+
+/// Errors that can occur when updating the client.
+#[derive(Debug)]
+pub enum SelfUpdateError {
+    /// Asking GitHub for the release or downloading an asset failed.
+    RequestFailed(reqwest::Error),
+
+    /// The release has no binary for this target. Contains the tag of the release and the name of the missing asset.
+    NoAsset(String, String),
+
+    /// The checksum file of the release doesn't list the binary. Contains the name of the binary.
+    NoChecksum(String),
+
+    /// The downloaded binary doesn't have the checksum listed for it.
+    ChecksumMismatch {
+        /// The checksum listed in the release.
+        expected: String,
+
+        /// The checksum of the downloaded binary.
+        actual: String,
+    },
+
+    /// The running binary was installed by a package manager, which should update it instead. Contains the package
+    /// manager.
+    Packaged(&'static str),
+
+    /// The running binary could not be replaced. Contains its path.
+    Replace(PathBuf, io::Error),
+}
+
+impl fmt::Display for SelfUpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::RequestFailed(e) => write!(f, "Failed to send request: {e}."),
+            Self::NoAsset(tag, name) => write!(f, "Release {tag} has no {name} binary."),
+            Self::NoChecksum(name) => write!(f, "{CHECKSUMS} doesn't list {name}."),
+            Self::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "The downloaded binary has the checksum {actual} instead of {expected}, so it wasn't installed."
+            ),
+            Self::Packaged(manager) => write!(
+                f,
+                "The client was installed by {manager}, update it with {manager} instead."
+            ),
+            Self::Replace(path, e) => write!(f, "Could not replace {}: {e}", path.display()),
+        }
+    }
+}
+
+impl error::Error for SelfUpdateError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::RequestFailed(e) => Some(e),
+            Self::Replace(_, e) => Some(e),
+            Self::NoAsset(..)
+            | Self::NoChecksum(_)
+            | Self::ChecksumMismatch { .. }
+            | Self::Packaged(_) => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for SelfUpdateError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::RequestFailed(e)
+    }
+}
+
+/// A GitHub release, as described by the releases API.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Release {
+    /// The tag of the release, e.g. `v0.3.0`.
+    pub tag_name: String,
+
+    /// The files attached to the release.
+    pub assets: Vec<Asset>,
+}
+
+/// A file attached to a [`Release`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct Asset {
+    /// The name of the file.
+    pub name: String,
+
+    /// Where to download the file from.
+    pub browser_download_url: String,
+}
+
+impl Release {
+    /// Ask GitHub for the latest release.
+    pub fn latest(client: &Client) -> Result<Self, SelfUpdateError> {
+        let release = client
+            .get(LATEST_RELEASE)
+            .header("Accept", "application/vnd.github+json")
+            .send()?
+            .error_for_status()?
+            .json()?;
+        Ok(release)
+    }
+
+    /// The version of the release, its tag without the leading `v`.
+    pub fn version(&self) -> &str {
+        self.tag_name.strip_prefix('v').unwrap_or(&self.tag_name)
+    }
+
+    /// Whether the release is newer than the running client.
+    pub fn is_newer(&self) -> bool {
+        is_newer(self.version(), VERSION)
+    }
+
+    /// Download the binary for this target and check it against the release's checksums.
+    pub fn download(&self, client: &Client) -> Result<Vec<u8>, SelfUpdateError> {
+        let name = asset_name();
+        let asset = |name: &str| {
+            self.assets
+                .iter()
+                .find(|asset| asset.name == name)
+                .ok_or_else(|| SelfUpdateError::NoAsset(self.tag_name.clone(), name.to_string()))
+        };
+
+        let sums = client
+            .get(&asset(CHECKSUMS)?.browser_download_url)
+            .send()?
+            .error_for_status()?
+            .text()?;
+        let expected =
+            checksum(&sums, &name).ok_or_else(|| SelfUpdateError::NoChecksum(name.clone()))?;
+
+        let binary = client
+            .get(&asset(&name)?.browser_download_url)
+            .send()?
+            .error_for_status()?
+            .bytes()?
+            .to_vec();
+
+        let actual = sha256(&binary);
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(SelfUpdateError::ChecksumMismatch {
+                expected: expected.to_string(),
+                actual,
+            });
+        }
+
+        Ok(binary)
+    }
+}
+
+/// The name of the release asset holding the binary for this target.
+pub fn asset_name() -> String {
+    format!("cf-ddns-client-{TARGET}{}", std::env::consts::EXE_SUFFIX)
+}
+
+/// Whether the dotted version `candidate` is newer than `current`. A pre-release, such as `0.3.0-rc.1`, is older than
+/// its release.
+fn is_newer(candidate: &str, current: &str) -> bool {
+    fn parse(version: &str) -> (Vec<u64>, bool) {
+        let (release, pre) = match version.split_once('-') {
+            Some((release, _)) => (release, true),
+            None => (version, false),
+        };
+
+        let numbers = release
+            .split('.')
+            .map(|number| number.parse().unwrap_or(0))
+            .collect();
+        (numbers, !pre)
+    }
+
+    parse(candidate) > parse(current)
+}
+
+/// The checksum listed for `name` in `sums`, which has a line per file like `sha256sum` prints them.
+fn checksum<'a>(sums: &'a str, name: &str) -> Option<&'a str> {
+    sums.lines().find_map(|line| {
+        let (sum, file) = line.split_once(char::is_whitespace)?;
+        // A leading `*` marks a file checksummed in binary mode.
+        let file = file.trim_start().trim_start_matches('*');
+        (file == name).then_some(sum)
+    })
+}
+
+/// The SHA-256 of `bytes`, in lowercase hexadecimal.
+fn sha256(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+/// The package manager that installed the binary at `exe`, if any.
+pub fn package_manager(exe: &Path) -> Option<&'static str> {
+    let list = fs::read_to_string("/var/lib/dpkg/info/cf-ddns-client.list").ok()?;
+    list.lines()
+        .any(|line| Path::new(line) == exe)
+        .then_some("apt")
+}
+
+/// Replace the binary at `exe` with `binary`, keeping its permissions.
+///
+/// The new binary is written beside the old one and renamed over it, so that the running process keeps the old one
+/// and an interrupted update leaves it in place. Windows doesn't allow that while it's running, so there the old one
+/// is first moved aside to `cf-ddns-client.exe.old`.
+pub fn replace(exe: &Path, binary: &[u8]) -> Result<(), SelfUpdateError> {
+    let replace_err = |e| SelfUpdateError::Replace(exe.to_path_buf(), e);
+    let with_extension = |extension: &str| {
+        let mut path = exe.as_os_str().to_owned();
+        path.push(extension);
+        PathBuf::from(path)
+    };
+
+    let permissions = fs::metadata(exe).map_err(replace_err)?.permissions();
+    let new = with_extension(".new");
+    fs::write(&new, binary)
+        .and_then(|()| fs::set_permissions(&new, permissions))
+        .map_err(replace_err)?;
+
+    if cfg!(windows) {
+        let old = with_extension(".old");
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old).map_err(replace_err)?;
+    }
+
+    fs::rename(&new, exe).map_err(|e| {
+        let _ = fs::remove_file(&new);
+        replace_err(e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::paths::tests::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn compares_versions() {
+        assert!(is_newer("0.3.0", "0.2.0"));
+        assert!(is_newer("0.10.0", "0.9.1"));
+        assert!(is_newer("1.0", "0.9.9"));
+        assert!(is_newer("0.3.0", "0.3.0-rc.1"));
+        assert!(!is_newer("0.3.0-rc.1", "0.3.0"));
+        assert!(!is_newer("0.2.0", "0.2.0"));
+        assert!(!is_newer("0.1.9", "0.2.0"));
+    }
+
+    #[test]
+    fn finds_checksums() {
+        let sums = "\
+            0a1b  cf-ddns-client-x86_64-unknown-linux-gnu\n\
+            2c3d *cf-ddns-client-x86_64-pc-windows-msvc.exe\n";
+        assert_eq!(
+            checksum(sums, "cf-ddns-client-x86_64-unknown-linux-gnu"),
+            Some("0a1b")
+        );
+        assert_eq!(
+            checksum(sums, "cf-ddns-client-x86_64-pc-windows-msvc.exe"),
+            Some("2c3d")
+        );
+        assert_eq!(
+            checksum(sums, "cf-ddns-client-aarch64-unknown-linux-gnu"),
+            None
+        );
+        assert_eq!(
+            sha256(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn replaces_the_binary() {
+        let dir = TempDir::new("self-update");
+        let exe = dir.join("cf-ddns-client");
+        fs::write(&exe, "old").unwrap();
+
+        replace(&exe, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&exe).unwrap(), "new");
+        assert!(!dir.join("cf-ddns-client.new").exists());
+    }
+}
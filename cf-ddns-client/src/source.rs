@@ -0,0 +1,551 @@
+//! Sources of the client's public IP address.
+//!
+//! Every way of discovering the IP address implements [`IpSource`]. Sources can be combined into a [`Fallback`]
+//! chain that tries each in order until one succeeds, or a [`Consensus`] that requires several to agree.
+
+use std::error;
+use std::fmt;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde::{de, Deserialize, Deserializer};
+
+use crate::dns::DnsError;
+use crate::echo::EchoError;
+use crate::exec::ExecError;
+use crate::file::FileError;
+use crate::fritzbox::FritzBoxError;
+use crate::interface::InterfaceError;
+use crate::mikrotik::MikroTikError;
+use crate::natpmp::NatPmpError;
+use crate::unifi::UnifiError;
+use crate::upnp::UpnpError;
+use crate::worker::GetIpError;
+
+/// The kinds of [`IpSource`] that can be selected on the command line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SourceKind {
+    /// Ask the cf-ddns worker, see [`WorkerSource`](crate::worker::WorkerSource).
+    Worker,
+
+    /// Query `myip.opendns.com` against OpenDNS, see [`DnsSource`](crate::dns::DnsSource).
+    Opendns,
+
+    /// Query `whoami.cloudflare` against Cloudflare's resolver, see [`DnsSource`](crate::dns::DnsSource).
+    CloudflareDns,
+
+    /// Ask the router for its WAN address with UPnP IGD, see [`UpnpSource`](crate::upnp::UpnpSource).
+    Upnp,
+
+    /// Ask the default gateway for its external address with NAT-PMP or PCP, see
+    /// [`NatPmpSource`](crate::natpmp::NatPmpSource).
+    Natpmp,
+
+    /// Ask an echo service at a URL, written `url:<URL>`, see [`EchoSource`](crate::echo::EchoSource).
+    Url(String),
+
+    /// Read a file, written `file:<PATH>`, see [`FileSource`](crate::file::FileSource).
+    File(PathBuf),
+
+    /// Read standard input, written `-`, see [`FileSource::stdin`](crate::file::FileSource::stdin).
+    Stdin,
+
+    /// Run a command, written `exec:<COMMAND>`, see [`ExecSource`](crate::exec::ExecSource).
+    Exec(String),
+
+    /// Read the IPv6 address of a local interface, written `interface:<NAME>`, see
+    /// [`InterfaceSource`](crate::interface::InterfaceSource).
+    Interface(String),
+
+    /// Ask a Fritz!Box for its WAN address, see [`FritzBoxSource`](crate::fritzbox::FritzBoxSource).
+    FritzBox,
+
+    /// Read the WAN interface address of a MikroTik router, see [`MikroTikSource`](crate::mikrotik::MikroTikSource).
+    MikroTik,
+
+    /// Read the active WAN address of a UniFi gateway, see [`UnifiSource`](crate::unifi::UnifiSource).
+    Unifi,
+}
+
+impl FromStr for SourceKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "worker" => Ok(Self::Worker),
+            "opendns" => Ok(Self::Opendns),
+            "cloudflare-dns" => Ok(Self::CloudflareDns),
+            "upnp" => Ok(Self::Upnp),
+            "natpmp" => Ok(Self::Natpmp),
+            "fritzbox" => Ok(Self::FritzBox),
+            "mikrotik" => Ok(Self::MikroTik),
+            "unifi" => Ok(Self::Unifi),
+            "-" => Ok(Self::Stdin),
+            _ if s.starts_with("file:") => Ok(Self::File(PathBuf::from(&s["file:".len()..]))),
+            _ if s.starts_with("exec:") => Ok(Self::Exec(s["exec:".len()..].to_string())),
+            _ if s.starts_with("interface:") => {
+                Ok(Self::Interface(s["interface:".len()..].to_string()))
+            }
+            _ => match s.strip_prefix("url:") {
+                Some(url) if url.starts_with("http://") || url.starts_with("https://") => {
+                    Ok(Self::Url(url.to_string()))
+                }
+                Some(url) => Err(format!("'{url}' is not an http:// or https:// URL")),
+                None => Err(format!(
+                    "unknown source '{s}', expected one of worker, opendns, cloudflare-dns, upnp, natpmp, fritzbox, mikrotik, unifi, url:<URL>, file:<PATH>, exec:<COMMAND>, interface:<NAME>, or -"
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for SourceKind {
+    /// Write the source as on the command line.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Worker => write!(f, "worker"),
+            Self::Opendns => write!(f, "opendns"),
+            Self::CloudflareDns => write!(f, "cloudflare-dns"),
+            Self::Upnp => write!(f, "upnp"),
+            Self::Natpmp => write!(f, "natpmp"),
+            Self::Url(url) => write!(f, "url:{url}"),
+            Self::File(path) => write!(f, "file:{}", path.display()),
+            Self::Stdin => write!(f, "-"),
+            Self::Exec(command) => write!(f, "exec:{command}"),
+            Self::Interface(name) => write!(f, "interface:{name}"),
+            Self::FritzBox => write!(f, "fritzbox"),
+            Self::MikroTik => write!(f, "mikrotik"),
+            Self::Unifi => write!(f, "unifi"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SourceKind {
+    /// Deserialize a source written as on the command line, e.g. `url:https://icanhazip.com`.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// An IP address family.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Family {
+    /// IPv4.
+    #[value(name = "4")]
+    V4,
+
+    /// IPv6.
+    #[value(name = "6")]
+    V6,
+}
+
+impl Family {
+    /// Whether `ip` belongs to this family.
+    pub fn contains(self, ip: IpAddr) -> bool {
+        match self {
+            Self::V4 => ip.is_ipv4(),
+            Self::V6 => ip.is_ipv6(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Family {
+    /// Deserialize a family written `4` or `6`, either as a number or a string.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Written {
+            Number(u8),
+            Text(String),
+        }
+
+        match Written::deserialize(deserializer)? {
+            Written::Number(4) => Ok(Self::V4),
+            Written::Number(6) => Ok(Self::V6),
+            Written::Text(text) if text == "4" => Ok(Self::V4),
+            Written::Text(text) if text == "6" => Ok(Self::V6),
+            _ => Err(de::Error::custom("expected a family of 4 or 6")),
+        }
+    }
+}
+
+impl fmt::Display for Family {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::V4 => write!(f, "IPv4"),
+            Self::V6 => write!(f, "IPv6"),
+        }
+    }
+}
+
+/// A way of discovering the client's public IP address.
+pub trait IpSource {
+    /// A short name for the source, used in messages, e.g. `worker`.
+    fn name(&self) -> &str;
+
+    /// Discover the IP address.
+    fn get_ip(&self) -> Result<IpAddr, SourceError>;
+}
+
+/// Errors that can occur when discovering the IP address.
+#[derive(Debug)]
+pub enum SourceError {
+    /// The DDNS worker failed to return the IP address.
+    Worker(GetIpError),
+
+    /// A DNS query failed to return the IP address.
+    Dns(DnsError),
+
+    /// The router failed to return its WAN address with UPnP.
+    Upnp(UpnpError),
+
+    /// The gateway failed to return its external address with NAT-PMP or PCP.
+    NatPmp(NatPmpError),
+
+    /// An echo service failed to return the IP address.
+    Echo(EchoError),
+
+    /// The address could not be read from a file or standard input.
+    File(FileError),
+
+    /// A command failed to print the address.
+    Exec(ExecError),
+
+    /// The address of a local interface could not be read.
+    Interface(InterfaceError),
+
+    /// A Fritz!Box failed to return its WAN address.
+    FritzBox(FritzBoxError),
+
+    /// A MikroTik router failed to return its WAN interface address.
+    MikroTik(MikroTikError),
+
+    /// A UniFi controller failed to return the WAN address.
+    Unifi(UnifiError),
+
+    /// Every source in a [`Fallback`] chain failed. Contains the name and error of each source, in order.
+    AllFailed(Vec<(String, SourceError)>),
+
+    /// Fewer sources than required by a [`Consensus`] agreed on the address.
+    NoConsensus {
+        /// The number of sources required to agree.
+        required: usize,
+
+        /// The name and answer of each source that succeeded.
+        answers: Vec<(String, IpAddr)>,
+
+        /// The name and error of each source that failed.
+        errors: Vec<(String, SourceError)>,
+    },
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Worker(e) => write!(f, "{e}"),
+            Self::Dns(e) => write!(f, "{e}"),
+            Self::Upnp(e) => write!(f, "{e}"),
+            Self::NatPmp(e) => write!(f, "{e}"),
+            Self::Echo(e) => write!(f, "{e}"),
+            Self::File(e) => write!(f, "{e}"),
+            Self::Exec(e) => write!(f, "{e}"),
+            Self::Interface(e) => write!(f, "{e}"),
+            Self::FritzBox(e) => write!(f, "{e}"),
+            Self::MikroTik(e) => write!(f, "{e}"),
+            Self::Unifi(e) => write!(f, "{e}"),
+            Self::AllFailed(errors) => {
+                if errors.is_empty() {
+                    return write!(f, "No IP address sources were configured.");
+                }
+
+                write!(f, "Every IP address source failed:")?;
+                for (name, e) in errors {
+                    write!(f, "\n  {name}: {e}")?;
+                }
+
+                Ok(())
+            }
+            Self::NoConsensus {
+                required,
+                answers,
+                errors,
+            } => {
+                write!(
+                    f,
+                    "Fewer than {required} IP address sources agreed on the address:"
+                )?;
+                for (name, ip) in answers {
+                    write!(f, "\n  {name}: {ip}")?;
+                }
+
+                for (name, e) in errors {
+                    write!(f, "\n  {name}: {e}")?;
+                }
+
+                if answers.iter().any(|(_, ip)| ip != &answers[0].1) {
+                    write!(
+                        f,
+                        "\nWhen a router reports a different address than public services, it is usually behind \
+                         carrier-grade NAT (CGNAT) or another router."
+                    )?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl error::Error for SourceError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Worker(e) => Some(e),
+            Self::Dns(e) => Some(e),
+            Self::Upnp(e) => Some(e),
+            Self::NatPmp(e) => Some(e),
+            Self::Echo(e) => Some(e),
+            Self::File(e) => Some(e),
+            Self::Exec(e) => Some(e),
+            Self::Interface(e) => Some(e),
+            Self::FritzBox(e) => Some(e),
+            Self::MikroTik(e) => Some(e),
+            Self::Unifi(e) => Some(e),
+            Self::AllFailed(_) | Self::NoConsensus { .. } => None,
+        }
+    }
+}
+
+impl From<GetIpError> for SourceError {
+    fn from(err: GetIpError) -> Self {
+        Self::Worker(err)
+    }
+}
+
+impl From<DnsError> for SourceError {
+    fn from(err: DnsError) -> Self {
+        Self::Dns(err)
+    }
+}
+
+impl From<UpnpError> for SourceError {
+    fn from(err: UpnpError) -> Self {
+        Self::Upnp(err)
+    }
+}
+
+impl From<NatPmpError> for SourceError {
+    fn from(err: NatPmpError) -> Self {
+        Self::NatPmp(err)
+    }
+}
+
+impl From<EchoError> for SourceError {
+    fn from(err: EchoError) -> Self {
+        Self::Echo(err)
+    }
+}
+
+impl From<FileError> for SourceError {
+    fn from(err: FileError) -> Self {
+        Self::File(err)
+    }
+}
+
+impl From<ExecError> for SourceError {
+    fn from(err: ExecError) -> Self {
+        Self::Exec(err)
+    }
+}
+
+impl From<FritzBoxError> for SourceError {
+    fn from(err: FritzBoxError) -> Self {
+        Self::FritzBox(err)
+    }
+}
+
+impl From<MikroTikError> for SourceError {
+    fn from(err: MikroTikError) -> Self {
+        Self::MikroTik(err)
+    }
+}
+
+impl From<UnifiError> for SourceError {
+    fn from(err: UnifiError) -> Self {
+        Self::Unifi(err)
+    }
+}
+
+impl From<InterfaceError> for SourceError {
+    fn from(err: InterfaceError) -> Self {
+        Self::Interface(err)
+    }
+}
+
+/// An ordered chain of sources, which returns the address from the first source that succeeds.
+pub struct Fallback {
+    sources: Vec<Box<dyn IpSource>>,
+}
+
+impl Fallback {
+    /// Create a chain trying `sources` in order.
+    pub fn new(sources: Vec<Box<dyn IpSource>>) -> Self {
+        Self { sources }
+    }
+}
+
+impl IpSource for Fallback {
+    fn name(&self) -> &str {
+        "fallback"
+    }
+
+    fn get_ip(&self) -> Result<IpAddr, SourceError> {
+        let mut errors = Vec::new();
+        for source in &self.sources {
+            match source.get_ip() {
+                Ok(ip) => return Ok(ip),
+                Err(err) => errors.push((source.name().to_string(), err)),
+            }
+        }
+
+        Err(SourceError::AllFailed(errors))
+    }
+}
+
+/// A set of sources that must agree, which returns the address given by at least a required number of them.
+///
+/// Every source is asked, so a single compromised or buggy source can't decide the address on its own.
+pub struct Consensus {
+    sources: Vec<Box<dyn IpSource>>,
+    required: usize,
+}
+
+impl Consensus {
+    /// Create a set of `sources` of which at least `required` must agree.
+    pub fn new(sources: Vec<Box<dyn IpSource>>, required: usize) -> Self {
+        Self { sources, required }
+    }
+}
+
+impl IpSource for Consensus {
+    fn name(&self) -> &str {
+        "consensus"
+    }
+
+    fn get_ip(&self) -> Result<IpAddr, SourceError> {
+        let mut answers = Vec::new();
+        let mut errors = Vec::new();
+        for source in &self.sources {
+            match source.get_ip() {
+                Ok(ip) => answers.push((source.name().to_string(), ip)),
+                Err(err) => errors.push((source.name().to_string(), err)),
+            }
+        }
+
+        let agreed = answers
+            .iter()
+            .map(|(_, ip)| *ip)
+            .find(|ip| answers.iter().filter(|(_, other)| other == ip).count() >= self.required);
+
+        agreed.ok_or(SourceError::NoConsensus {
+            required: self.required,
+            answers,
+            errors,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    /// A source that always returns the same result.
+    struct Fixed(Option<IpAddr>);
+
+    impl IpSource for Fixed {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn get_ip(&self) -> Result<IpAddr, SourceError> {
+            self.0.ok_or(SourceError::AllFailed(Vec::new()))
+        }
+    }
+
+    #[test]
+    fn parses_source_kinds() {
+        assert_eq!("worker".parse(), Ok(SourceKind::Worker));
+        assert_eq!(
+            "url:https://icanhazip.com".parse(),
+            Ok(SourceKind::Url("https://icanhazip.com".to_string()))
+        );
+
+        assert_eq!(
+            "file:/run/wan_ip".parse(),
+            Ok(SourceKind::File(PathBuf::from("/run/wan_ip")))
+        );
+
+        assert_eq!("-".parse(), Ok(SourceKind::Stdin));
+        assert_eq!(
+            "exec:get-wan-ip --modem".parse(),
+            Ok(SourceKind::Exec("get-wan-ip --modem".to_string()))
+        );
+
+        assert!("url:icanhazip.com".parse::<SourceKind>().is_err());
+        assert!("carrier-pigeon".parse::<SourceKind>().is_err());
+
+        for written in [
+            "cloudflare-dns",
+            "url:https://icanhazip.com",
+            "file:/run/wan_ip",
+            "-",
+        ] {
+            assert_eq!(written.parse::<SourceKind>().unwrap().to_string(), written);
+        }
+    }
+
+    #[test]
+    fn fallback_returns_first_success() {
+        let ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+        let chain = Fallback::new(vec![Box::new(Fixed(None)), Box::new(Fixed(Some(ip)))]);
+        assert_eq!(chain.get_ip().unwrap(), ip);
+    }
+
+    #[test]
+    fn fallback_reports_every_failure() {
+        let chain = Fallback::new(vec![Box::new(Fixed(None)), Box::new(Fixed(None))]);
+        match chain.get_ip() {
+            Err(SourceError::AllFailed(errors)) => assert_eq!(errors.len(), 2),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn consensus_requires_agreement() {
+        let a = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+        let b = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1));
+        let sources = || -> Vec<Box<dyn IpSource>> {
+            vec![
+                Box::new(Fixed(Some(a))),
+                Box::new(Fixed(Some(b))),
+                Box::new(Fixed(Some(a))),
+                Box::new(Fixed(None)),
+            ]
+        };
+
+        assert_eq!(Consensus::new(sources(), 2).get_ip().unwrap(), a);
+        match Consensus::new(sources(), 3).get_ip() {
+            Err(SourceError::NoConsensus {
+                answers, errors, ..
+            }) => {
+                assert_eq!(answers.len(), 3);
+                assert_eq!(errors.len(), 1);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+}
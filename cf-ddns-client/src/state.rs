@@ -0,0 +1,419 @@
+//! A persistent record of what previous runs observed and did.
+//!
+//! After each update, the client writes the IP address it observed, when it last confirmed that the records have that
+//! address, and the outcome of the run. The next run with the same address doesn't need to call Cloudflare at all.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// The file name of the state within the [state directory](crate::paths::state_dir).
+pub const FILE_NAME: &str = "state.json";
+
+/// Held while a run saves its part of the state, for runs updating other records at the same time.
+static SAVING: Mutex<()> = Mutex::new(());
+
+/// The outcome of a run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    /// At least one record was changed to the IP address.
+    Updated,
+
+    /// Every record already had the IP address.
+    Unchanged,
+
+    /// The records could not be updated.
+    Failed,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Updated => write!(f, "updated"),
+            Self::Unchanged => write!(f, "unchanged"),
+            Self::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// What the last run updating a set of records observed and did.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Entry {
+    /// The IP address observed by the last run.
+    pub ip: IpAddr,
+
+    /// The outcome of the last run.
+    pub outcome: Outcome,
+
+    /// When the last run finished, in seconds since the Unix epoch.
+    pub checked: u64,
+
+    /// When the records were last confirmed to have [`Self::ip`], in seconds since the Unix epoch.
+    #[serde(default)]
+    pub synced: Option<u64>,
+
+    /// When a record was last changed, in seconds since the Unix epoch.
+    #[serde(default)]
+    pub updated: Option<u64>,
+
+    /// A new IP address that was observed but not yet published, see [`State::observe`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending: Option<Pending>,
+}
+
+/// A new IP address observed by consecutive checks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Pending {
+    /// The new IP address.
+    pub ip: IpAddr,
+
+    /// How many consecutive checks have observed it.
+    pub seen: u32,
+}
+
+impl Entry {
+    /// Whether the records were confirmed to have `ip` within the last `ttl`.
+    pub fn is_synced(&self, ip: IpAddr, ttl: Duration) -> bool {
+        self.is_synced_at(ip, ttl, now())
+    }
+
+    fn is_synced_at(&self, ip: IpAddr, ttl: Duration, now: u64) -> bool {
+        self.ip == ip
+            && self
+                .synced
+                .is_some_and(|synced| now.saturating_sub(synced) < ttl.as_secs())
+    }
+}
+
+/// The last answer of a worker that sent an `ETag`, for asking it whether the address changed since.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Validator {
+    /// The `ETag` of the answer.
+    pub etag: String,
+
+    /// The IP address of the answer.
+    pub ip: IpAddr,
+}
+
+/// The [entries](Entry) of every set of records updated from this machine, keyed by a description of the records.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct State {
+    #[serde(default)]
+    entries: BTreeMap<String, Entry>,
+
+    /// When the last run started for each set of records, of either family, in seconds since the Unix epoch.
+    #[serde(default)]
+    runs: BTreeMap<String, u64>,
+
+    /// The last answers of workers, keyed by the name of the source and the family it was asked over.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    validators: BTreeMap<String, Validator>,
+
+    /// When each notifier was last sent a notification about each set of records, in seconds since the Unix epoch,
+    /// keyed like `example.com/home/A telegram`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    notified: BTreeMap<String, u64>,
+
+    /// When each notifier was last sent its daily notification about each set of records, keyed like
+    /// [`Self::notified`], apart from that of the other notifications.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    daily: BTreeMap<String, u64>,
+}
+
+impl State {
+    /// Load the state from `path`. A missing or unreadable state is empty.
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save the state to `path`, creating its parent directories if needed.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Save the run and the entries of the records described by `target` to `path`, keeping those saved there since
+    /// this state was loaded by runs updating other records at the same time.
+    pub fn save_target(&self, path: &Path, target: &str) -> io::Result<()> {
+        let _saving = SAVING.lock().unwrap_or_else(|e| e.into_inner());
+        let mut saved = Self::load(path);
+        saved.merge_target(self, target);
+        saved.save(path)
+    }
+
+    fn merge_target(&mut self, other: &Self, target: &str) {
+        if let Some(&run) = other.runs.get(target) {
+            self.runs.insert(target.to_string(), run);
+        }
+
+        let prefix = format!("{target}/");
+        let entries = other
+            .entries
+            .iter()
+            .filter(|(key, _)| key.starts_with(&prefix));
+        self.entries
+            .extend(entries.map(|(key, entry)| (key.clone(), entry.clone())));
+    }
+
+    /// Save the last answer of the worker described by `key` to the state at `path`, or forget it if `None`, keeping
+    /// the rest of the state saved there.
+    pub fn save_validator(path: &Path, key: &str, validator: Option<Validator>) -> io::Result<()> {
+        let _saving = SAVING.lock().unwrap_or_else(|e| e.into_inner());
+        let mut saved = Self::load(path);
+        match validator {
+            Some(validator) => saved.validators.insert(key.to_string(), validator),
+            None => saved.validators.remove(key),
+        };
+
+        saved.save(path)
+    }
+
+    /// The last answer of the worker described by `key`, if it sent an `ETag`.
+    pub fn validator(&self, key: &str) -> Option<&Validator> {
+        self.validators.get(key)
+    }
+
+    /// Save that the notification described by `key`, the daily one if `daily`, was just sent to the state at
+    /// `path`, keeping the rest of the state saved there.
+    pub fn save_notified(path: &Path, key: &str, daily: bool) -> io::Result<()> {
+        let _saving = SAVING.lock().unwrap_or_else(|e| e.into_inner());
+        let mut saved = Self::load(path);
+        saved.notified.insert(key.to_string(), now());
+        if daily {
+            saved.daily.insert(key.to_string(), now());
+        }
+
+        saved.save(path)
+    }
+
+    /// How long ago the notification described by `key` was last sent, if ever.
+    pub fn since_notified(&self, key: &str) -> Option<Duration> {
+        self.notified
+            .get(key)
+            .map(|&notified| Duration::from_secs(now().saturating_sub(notified)))
+    }
+
+    /// How long ago the daily notification described by `key` was last sent, if ever.
+    pub fn since_daily(&self, key: &str) -> Option<Duration> {
+        self.daily
+            .get(key)
+            .map(|&daily| Duration::from_secs(now().saturating_sub(daily)))
+    }
+
+    /// The entry of the records described by `key`, if any run has updated them.
+    pub fn get(&self, key: &str) -> Option<&Entry> {
+        self.entries.get(key)
+    }
+
+    /// How long ago the last run for the records described by `target` started, if any has.
+    pub fn since_last_run(&self, target: &str) -> Option<Duration> {
+        self.since_last_run_at(target, now())
+    }
+
+    /// Record that a run for the records described by `target` is starting.
+    pub fn record_run(&mut self, target: &str) {
+        self.runs.insert(target.to_string(), now());
+    }
+
+    fn since_last_run_at(&self, target: &str, now: u64) -> Option<Duration> {
+        self.runs
+            .get(target)
+            .map(|&run| Duration::from_secs(now.saturating_sub(run)))
+    }
+
+    /// Note that a check of the records described by `key` observed `ip` without publishing it.
+    ///
+    /// Returns how many consecutive checks have observed `ip` if it differs from the IP address of the last run, or
+    /// `None` if it doesn't or no run has updated the records yet.
+    pub fn observe(&mut self, key: &str, ip: IpAddr) -> Option<u32> {
+        let entry = self.entries.get_mut(key)?;
+        if entry.ip == ip {
+            entry.pending = None;
+            return None;
+        }
+
+        let seen = match entry.pending {
+            Some(pending) if pending.ip == ip => pending.seen.saturating_add(1),
+            _ => 1,
+        };
+
+        entry.pending = Some(Pending { ip, seen });
+        Some(seen)
+    }
+
+    /// Record that a run updating the records described by `key` observed `ip`, with `outcome`.
+    pub fn record(&mut self, key: &str, ip: IpAddr, outcome: Outcome) {
+        self.record_at(key, ip, outcome, now());
+    }
+
+    fn record_at(&mut self, key: &str, ip: IpAddr, outcome: Outcome, now: u64) {
+        let previous = self.entries.get(key);
+        let (synced, updated) = match outcome {
+            Outcome::Updated => (Some(now), Some(now)),
+            Outcome::Unchanged => (Some(now), previous.and_then(|entry| entry.updated)),
+            Outcome::Failed => (None, previous.and_then(|entry| entry.updated)),
+        };
+
+        let entry = Entry {
+            ip,
+            outcome,
+            checked: now,
+            synced,
+            updated,
+            pending: None,
+        };
+
+        self.entries.insert(key.to_string(), entry);
+    }
+}
+
+/// The default path of the state, if a state directory can be determined.
+pub fn default_path() -> Option<PathBuf> {
+    crate::paths::state_dir().map(|dir| dir.join(FILE_NAME))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::paths::tests::TempDir;
+
+    use super::*;
+
+    const IP: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(203, 0, 113, 7));
+    const OTHER: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(203, 0, 113, 8));
+    const TTL: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn synced_until_ttl_or_ip_changes() {
+        let mut state = State::default();
+        state.record_at("example.com/home/A", IP, Outcome::Updated, 1000);
+        let entry = state.get("example.com/home/A").unwrap();
+        assert!(entry.is_synced_at(IP, TTL, 1059));
+        assert!(!entry.is_synced_at(IP, TTL, 1060));
+        assert!(!entry.is_synced_at(OTHER, TTL, 1000));
+        assert!(state.get("example.com/other/A").is_none());
+    }
+
+    #[test]
+    fn failure_is_not_synced() {
+        let mut state = State::default();
+        state.record_at("key", IP, Outcome::Updated, 1000);
+        state.record_at("key", IP, Outcome::Unchanged, 1010);
+        assert_eq!(state.get("key").unwrap().updated, Some(1000));
+        assert_eq!(state.get("key").unwrap().synced, Some(1010));
+
+        state.record_at("key", IP, Outcome::Failed, 1020);
+        let entry = state.get("key").unwrap();
+        assert!(!entry.is_synced_at(IP, TTL, 1020));
+        assert_eq!(entry.updated, Some(1000));
+        assert_eq!(entry.checked, 1020);
+    }
+
+    #[test]
+    fn counts_consecutive_observations() {
+        let mut state = State::default();
+        assert_eq!(state.observe("key", IP), None);
+
+        state.record_at("key", IP, Outcome::Updated, 1000);
+        assert_eq!(state.observe("key", OTHER), Some(1));
+        assert_eq!(state.observe("key", OTHER), Some(2));
+        assert_eq!(state.observe("key", IP), None);
+        assert_eq!(state.observe("key", OTHER), Some(1));
+
+        state.record_at("key", OTHER, Outcome::Updated, 1010);
+        assert_eq!(state.get("key").unwrap().pending, None);
+    }
+
+    #[test]
+    fn time_since_last_run() {
+        let mut state = State::default();
+        assert_eq!(state.since_last_run_at("example.com/home", 1000), None);
+
+        state.runs.insert("example.com/home".to_string(), 1000);
+        assert_eq!(
+            state.since_last_run_at("example.com/home", 1030),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(state.since_last_run_at("example.com/other", 1030), None);
+    }
+
+    #[test]
+    fn merges_targets() {
+        let mut saved = State::default();
+        saved.record_at("example.com/home/A", IP, Outcome::Updated, 1000);
+        saved.runs.insert("example.com/home".to_string(), 1000);
+
+        let mut other = State::default();
+        other.record_at("example.com/home/A", OTHER, Outcome::Updated, 900);
+        other.record_at("example.com/nas/A", OTHER, Outcome::Updated, 1010);
+        other.runs.insert("example.com/nas".to_string(), 1010);
+
+        saved.merge_target(&other, "example.com/nas");
+        assert_eq!(saved.get("example.com/home/A").unwrap().ip, IP);
+        assert_eq!(saved.get("example.com/nas/A").unwrap().ip, OTHER);
+        assert_eq!(saved.runs.len(), 2);
+    }
+
+    #[test]
+    fn saves_validators() {
+        let dir = TempDir::new("state");
+        let path = dir.join("state.json");
+        let validator = Validator {
+            etag: "\"abc\"".to_string(),
+            ip: IP,
+        };
+
+        State::save_validator(
+            &path,
+            "worker:https://ddns.example",
+            Some(validator.clone()),
+        )
+        .unwrap();
+        let state = State::load(&path);
+        assert_eq!(
+            state.validator("worker:https://ddns.example"),
+            Some(&validator)
+        );
+        assert_eq!(state.validator("worker:https://other.example"), None);
+
+        State::save_validator(&path, "worker:https://ddns.example", None).unwrap();
+        assert_eq!(
+            State::load(&path).validator("worker:https://ddns.example"),
+            None
+        );
+    }
+
+    #[test]
+    fn saves_daily_notifications_apart() {
+        let dir = TempDir::new("daily");
+        let path = dir.join("state.json");
+        let key = "example.com/home/A telegram";
+
+        State::save_notified(&path, key, false).unwrap();
+        let state = State::load(&path);
+        assert!(state.since_notified(key).is_some());
+        assert_eq!(state.since_daily(key), None);
+
+        State::save_notified(&path, key, true).unwrap();
+        assert!(State::load(&path).since_daily(key).is_some());
+    }
+}
@@ -0,0 +1,229 @@
+//! Reporting whether the records have the currently discovered IP address, and what the last run did.
+
+use std::net::IpAddr;
+use std::time::{Duration, UNIX_EPOCH};
+
+use cf_ddns_cloudflare::{Cloudflare, Record};
+use serde::Serialize;
+
+use crate::output::Table;
+use crate::records::{self, Selector};
+use crate::state::{Entry, Outcome};
+
+/// The status of a set of records.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Status {
+    /// A description of the records, e.g. `example.com/home`.
+    pub records: String,
+
+    /// The currently discovered IP address, if it could be discovered.
+    pub ip: Option<IpAddr>,
+
+    /// The content of each record of the address's type.
+    pub content: Vec<String>,
+
+    /// Whether there is at least one record and every record has [`Self::ip`].
+    pub in_sync: bool,
+
+    /// Why the IP address or the records couldn't be looked up.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// When the last run finished, as an RFC 3339 timestamp.
+    pub last_checked: Option<String>,
+
+    /// When the last run changed a record, as an RFC 3339 timestamp.
+    pub last_updated: Option<String>,
+
+    /// The outcome of the last run.
+    pub last_outcome: Option<Outcome>,
+}
+
+impl Status {
+    /// The status of the records described by `records`, given the discovered `ip`, the `content` of the records,
+    /// and the state `entry` of the last run, if any.
+    pub fn new(
+        records: String,
+        ip: Result<IpAddr, String>,
+        content: Result<Vec<String>, String>,
+        entry: Option<&Entry>,
+    ) -> Self {
+        let (ip, ip_error) = match ip {
+            Ok(ip) => (Some(ip), None),
+            Err(err) => (None, Some(err)),
+        };
+
+        let (content, content_error) = match content {
+            Ok(content) => (content, None),
+            Err(err) => (Vec::new(), Some(err)),
+        };
+
+        let in_sync = ip.is_some_and(|ip| {
+            let ip = ip.to_string();
+            !content.is_empty() && content.iter().all(|content| *content == ip)
+        });
+
+        Self {
+            records,
+            ip,
+            content,
+            in_sync,
+            error: ip_error.or(content_error),
+            last_checked: entry.map(|entry| rfc3339(entry.checked)),
+            last_updated: entry.and_then(|entry| entry.updated).map(rfc3339),
+            last_outcome: entry.map(|entry| entry.outcome),
+        }
+    }
+}
+
+/// A table of `statuses`, one row each.
+pub fn table(statuses: &[Status]) -> Table {
+    let mut table = Table::new(["RECORDS", "IP", "CONTENT", "IN SYNC", "LAST UPDATED"]);
+    for status in statuses {
+        table.push([
+            status.records.clone(),
+            status.ip.map_or("-".to_string(), |ip| ip.to_string()),
+            match status.content.as_slice() {
+                [] => "-".to_string(),
+                content => content.join(","),
+            },
+            status.in_sync.to_string(),
+            status.last_updated.clone().unwrap_or("-".to_string()),
+        ]);
+    }
+
+    table
+}
+
+/// The records of the zone with `zone_id` and `zone_name` selected by `selector`, of the type of `ip`, looked up from
+/// Cloudflare rather than a cache.
+pub async fn current_records(
+    client: &Cloudflare,
+    zone_id: &str,
+    zone_name: &str,
+    selector: &Selector,
+    ip: IpAddr,
+) -> Result<Vec<Record>, String> {
+    match selector {
+        Selector::Id(record_id) => client
+            .get_record(zone_id, record_id)
+            .await
+            .map(|record| vec![record])
+            .map_err(|err| format!("Could not get record {record_id}: {err}")),
+        Selector::Name(record_name) => {
+            let full_record_name = records::fqdn(record_name, zone_name);
+            client
+                .get_records(zone_id, &full_record_name)
+                .await
+                .map(|found| {
+                    found
+                        .into_iter()
+                        .filter(|record| record.content.record_type() == records::record_type(ip))
+                        .collect()
+                })
+                .map_err(|err| format!("Could not get {full_record_name}: {err}"))
+        }
+        Selector::Discover(discover) => client
+            .list_records(zone_id, Some(records::record_type(ip)))
+            .await
+            .map(|found| records::discover(ip, found, discover))
+            .map_err(|err| format!("Could not list records: {err}")),
+    }
+}
+
+/// Format seconds since the Unix epoch as an RFC 3339 timestamp.
+fn rfc3339(seconds: u64) -> String {
+    humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_secs(seconds)).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    const IP: IpAddr = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+
+    #[test]
+    fn in_sync_when_every_record_has_the_address() {
+        let content = |content: &[&str]| Ok(content.iter().map(|c| c.to_string()).collect());
+        let status = |content| Status::new("example.com/home".to_string(), Ok(IP), content, None);
+
+        assert!(status(content(&["203.0.113.7"])).in_sync);
+        assert!(!status(content(&["203.0.113.7", "203.0.113.8"])).in_sync);
+        assert!(!status(content(&[])).in_sync);
+
+        let failed = status(Err("Could not list records".to_string()));
+        assert!(!failed.in_sync);
+        assert_eq!(failed.error.as_deref(), Some("Could not list records"));
+
+        let undiscovered = Status::new(
+            "example.com/home".to_string(),
+            Err("Timed out".to_string()),
+            content(&["203.0.113.7"]),
+            None,
+        );
+        assert!(!undiscovered.in_sync);
+        assert_eq!(undiscovered.error.as_deref(), Some("Timed out"));
+    }
+
+    #[test]
+    fn tabulates_statuses() {
+        let synced = Status::new(
+            "example.com/home".to_string(),
+            Ok(IP),
+            Ok(vec![IP.to_string()]),
+            None,
+        );
+        let failed = Status::new(
+            "example.com/nas".to_string(),
+            Err("Timed out".to_string()),
+            Err("Could not list records".to_string()),
+            None,
+        );
+
+        let table = table(&[synced, failed]).to_string();
+        let rows: Vec<Vec<&str>> = table
+            .lines()
+            .map(|line| line.split_whitespace().collect())
+            .collect();
+        assert_eq!(
+            rows[0],
+            ["RECORDS", "IP", "CONTENT", "IN", "SYNC", "LAST", "UPDATED"]
+        );
+        assert_eq!(
+            rows[1],
+            [
+                "example.com/home",
+                "203.0.113.7",
+                "203.0.113.7",
+                "true",
+                "-"
+            ]
+        );
+        assert_eq!(rows[2], ["example.com/nas", "-", "-", "false", "-"]);
+    }
+
+    #[test]
+    fn reports_the_last_run() {
+        let entry = Entry {
+            ip: IP,
+            outcome: Outcome::Unchanged,
+            checked: 86400,
+            synced: Some(86400),
+            updated: Some(3600),
+            pending: None,
+        };
+
+        let status = Status::new(
+            "example.com/home".to_string(),
+            Ok(IP),
+            Ok(vec![IP.to_string()]),
+            Some(&entry),
+        );
+
+        assert_eq!(status.last_checked.as_deref(), Some("1970-01-02T00:00:00Z"));
+        assert_eq!(status.last_updated.as_deref(), Some("1970-01-01T01:00:00Z"));
+        assert_eq!(status.last_outcome, Some(Outcome::Unchanged));
+    }
+}
@@ -0,0 +1,129 @@
+//! Sending the log to the local syslog daemon over `/dev/log`, as messages in the format of RFC 3164.
+//!
+//! Each event becomes one message, tagged `cf-ddns` with the process ID, with the priority of its level in the
+//! `daemon` facility.
+
+use std::io::{self, Write};
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+
+use tracing::{Level, Metadata};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// The socket of the local syslog daemon.
+pub const SOCKET: &str = "/dev/log";
+
+/// The tag of the messages.
+pub const TAG: &str = "cf-ddns";
+
+/// The `daemon` facility.
+const FACILITY: u8 = 3;
+
+/// A connection to the syslog daemon, which makes a [`Message`] per event.
+#[derive(Debug)]
+pub struct Syslog {
+    socket: UnixDatagram,
+}
+
+impl Syslog {
+    /// Connect to the syslog daemon listening on `path`, usually [`SOCKET`].
+    pub fn connect(path: &Path) -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        Ok(Self { socket })
+    }
+}
+
+impl<'a> MakeWriter<'a> for Syslog {
+    type Writer = Message<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        Message::new(&self.socket, Level::INFO)
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        Message::new(&self.socket, *meta.level())
+    }
+}
+
+/// A message being written, sent to the syslog daemon when dropped.
+#[derive(Debug)]
+pub struct Message<'a> {
+    socket: &'a UnixDatagram,
+    buf: Vec<u8>,
+}
+
+impl<'a> Message<'a> {
+    fn new(socket: &'a UnixDatagram, level: Level) -> Self {
+        let mut buf = Vec::with_capacity(256);
+        buf.extend_from_slice(header(level).as_bytes());
+        Self { socket, buf }
+    }
+}
+
+impl Write for Message<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for Message<'_> {
+    fn drop(&mut self) {
+        while self.buf.last() == Some(&b'\n') {
+            self.buf.pop();
+        }
+
+        // A lost message can't be reported anywhere else.
+        let _ = self.socket.send(&self.buf);
+    }
+}
+
+/// The start of a message at `level`, e.g. `<27>cf-ddns[1234]: ` for an error.
+fn header(level: Level) -> String {
+    let severity = match level {
+        Level::ERROR => 3,
+        Level::WARN => 4,
+        Level::INFO => 6,
+        Level::DEBUG | Level::TRACE => 7,
+    };
+
+    format!(
+        "<{}>{TAG}[{}]: ",
+        FACILITY * 8 + severity,
+        std::process::id()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::paths::tests::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn sends_a_datagram_per_message() {
+        let dir = TempDir::new("syslog");
+        let path = dir.join("log");
+        let daemon = UnixDatagram::bind(&path).unwrap();
+        let syslog = Syslog::connect(&path).unwrap();
+
+        let mut message = syslog.make_writer();
+        message
+            .write_all(b"home.example.com => 203.0.113.7\n")
+            .unwrap();
+        drop(message);
+
+        let mut buf = [0; 256];
+        let len = daemon.recv(&mut buf).unwrap();
+        let expected = format!(
+            "<30>cf-ddns[{}]: home.example.com => 203.0.113.7",
+            std::process::id()
+        );
+        assert_eq!(String::from_utf8_lossy(&buf[..len]), expected);
+    }
+}
@@ -0,0 +1,96 @@
+//! Notifying a Telegram chat through a bot, for `--telegram-bot-token` and `--telegram-chat-id`.
+//!
+//! The bot sends a [short message](crate::notify::summary) when the records are updated or fail to be. It must have
+//! been added to the chat, or been messaged first by the user.
+
+use serde_json::{json, Value};
+
+use crate::history::Event;
+use crate::http::Settings;
+use crate::notify::{self, Notifier, NotifyError};
+use crate::state::Outcome;
+
+/// The URL of the Bot API.
+const API_URL: &str = "https://api.telegram.org";
+
+/// A Telegram bot sending messages to a chat.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Telegram {
+    token: String,
+    chat_id: String,
+    unchanged: bool,
+}
+
+impl Telegram {
+    /// A bot authenticated with `token`, as given by @BotFather, sending messages to `chat_id`, which is numeric or
+    /// the `@username` of a public channel.
+    pub fn new(token: impl Into<String>, chat_id: impl Into<String>) -> Self {
+        Self {
+            token: token.into(),
+            chat_id: chat_id.into(),
+            unchanged: false,
+        }
+    }
+
+    /// Whether to also send a message when the records are unchanged, silently so that the chat doesn't ring for it.
+    pub fn with_unchanged(self, unchanged: bool) -> Self {
+        Self { unchanged, ..self }
+    }
+
+    /// The body of the `sendMessage` request for `event`.
+    fn message(&self, event: &Event) -> Value {
+        json!({
+            "chat_id": self.chat_id,
+            "text": notify::summary(event),
+            "disable_notification": event.result == Outcome::Unchanged,
+        })
+    }
+}
+
+impl Notifier for Telegram {
+    fn name(&self) -> &str {
+        "Telegram"
+    }
+
+    fn wants(&self, outcome: Outcome) -> bool {
+        match outcome {
+            Outcome::Updated | Outcome::Failed => true,
+            Outcome::Unchanged => self.unchanged,
+        }
+    }
+
+    fn send(&self, event: &Event, settings: &Settings) -> Result<(), NotifyError> {
+        let response = settings
+            .builder()
+            .build()?
+            .post(format!("{API_URL}/bot{}/sendMessage", self.token))
+            .json(&self.message(event))
+            .send()?;
+
+        notify::check(response, "description")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::*;
+
+    #[test]
+    fn sends_unchanged_runs_silently() {
+        let telegram = Telegram::new("123:abc", "-100123");
+        assert!(telegram.wants(Outcome::Failed));
+        assert!(!telegram.wants(Outcome::Unchanged));
+
+        let telegram = telegram.with_unchanged(true);
+        assert!(telegram.wants(Outcome::Unchanged));
+
+        let ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+        let updated = Event::new("example.com/home/A", None, ip, Outcome::Updated);
+        let unchanged = Event::new("example.com/home/A", Some(ip), ip, Outcome::Unchanged);
+        assert_eq!(telegram.message(&updated)["disable_notification"], false);
+        assert_eq!(telegram.message(&unchanged)["disable_notification"], true);
+        assert_eq!(telegram.message(&unchanged)["chat_id"], "-100123");
+    }
+}
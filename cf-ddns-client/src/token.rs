@@ -0,0 +1,170 @@
+//! Reading the API token from where it's kept: given directly, in a file, printed by a command such as a password
+//! manager, in [Vault](crate::vault), or in the platform [keyring](crate::keyring).
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process::{ExitStatus, Stdio};
+
+use crate::exec;
+use crate::http::Settings;
+
+/// Where to create API tokens.
+pub const API_TOKENS_URL: &str = "https://dash.cloudflare.com/profile/api-tokens";
+
+/// Where the API token is kept.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TokenSource {
+    /// The token itself.
+    Literal(String),
+
+    /// A file containing the token.
+    File(PathBuf),
+
+    /// A command run with the system shell that prints the token, e.g. `pass show cloudflare/ddns`.
+    Command(String),
+
+    /// A field of a secret in HashiCorp Vault.
+    #[cfg(feature = "vault")]
+    Vault(crate::vault::VaultPath),
+
+    /// The platform keyring, used when no other token is given.
+    #[cfg(feature = "keyring")]
+    Keyring,
+}
+
+/// Errors that can occur when reading the API token.
+#[derive(Debug)]
+pub enum TokenError {
+    /// The token file could not be read. Contains its path.
+    Read(PathBuf, io::Error),
+
+    /// The token command could not be started. Contains the command.
+    Spawn(String, io::Error),
+
+    /// The token command exited unsuccessfully. Contains the command and its exit status.
+    Failed(String, ExitStatus),
+
+    /// The token is empty. Contains a description of where it was read from.
+    Empty(String),
+
+    /// The token could not be read from Vault.
+    #[cfg(feature = "vault")]
+    Vault(crate::vault::VaultError),
+
+    /// The token could not be read from the keyring.
+    #[cfg(feature = "keyring")]
+    Keyring(crate::keyring::KeyringError),
+}
+
+impl fmt::Display for TokenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Read(path, e) => {
+                write!(f, "Could not read the token file {}: {e}", path.display())
+            }
+            Self::Spawn(command, e) => write!(f, "Could not run the token command {command}: {e}"),
+            Self::Failed(command, status) => {
+                write!(f, "The token command {command} exited with {status}.")
+            }
+            Self::Empty(source) => write!(f, "The token {source} is empty."),
+            #[cfg(feature = "vault")]
+            Self::Vault(e) => write!(f, "Could not read the token from Vault: {e}"),
+            #[cfg(feature = "keyring")]
+            Self::Keyring(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl error::Error for TokenError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Read(_, e) | Self::Spawn(_, e) => Some(e),
+            Self::Failed(..) | Self::Empty(_) => None,
+            #[cfg(feature = "vault")]
+            Self::Vault(e) => Some(e),
+            #[cfg(feature = "keyring")]
+            Self::Keyring(e) => Some(e),
+        }
+    }
+}
+
+impl TokenSource {
+    /// Read the token, without surrounding whitespace. The requests to Vault are sent with `settings`.
+    #[cfg_attr(not(feature = "vault"), allow(unused_variables))]
+    pub fn read(&self, settings: &Settings) -> Result<String, TokenError> {
+        let (token, source) = match self {
+            Self::Literal(token) => (token.clone(), "given".to_string()),
+            Self::File(path) => (
+                fs::read_to_string(path).map_err(|e| TokenError::Read(path.clone(), e))?,
+                format!("in {}", path.display()),
+            ),
+            Self::Command(command) => (run(command)?, format!("printed by {command}")),
+            #[cfg(feature = "vault")]
+            Self::Vault(path) => (
+                crate::vault::read(path, settings).map_err(TokenError::Vault)?,
+                format!("in Vault at {path}"),
+            ),
+            #[cfg(feature = "keyring")]
+            Self::Keyring => (
+                crate::keyring::get().map_err(TokenError::Keyring)?,
+                "in the keyring".to_string(),
+            ),
+        };
+
+        match token.trim() {
+            "" => Err(TokenError::Empty(source)),
+            token => Ok(token.to_string()),
+        }
+    }
+}
+
+/// Where the token is read from when none is given: the keyring, if the client was built with it.
+pub fn fallback() -> Option<TokenSource> {
+    #[cfg(feature = "keyring")]
+    return Some(TokenSource::Keyring);
+
+    #[cfg(not(feature = "keyring"))]
+    None
+}
+
+/// Run `command` and return its standard output. Its standard input and error are left connected, so that it can
+/// ask for a passphrase and report its own errors.
+fn run(command: &str) -> Result<String, TokenError> {
+    let output = exec::shell(command)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| TokenError::Spawn(command.to_string(), e))?;
+
+    if !output.status.success() {
+        return Err(TokenError::Failed(command.to_string(), output.status));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_from_a_command() {
+        let token = TokenSource::Command("printf ' secret\\n'".to_string());
+        assert_eq!(token.read(&Settings::default()).unwrap(), "secret");
+
+        let empty = TokenSource::Command("true".to_string());
+        assert!(matches!(
+            empty.read(&Settings::default()),
+            Err(TokenError::Empty(_))
+        ));
+
+        let failing = TokenSource::Command("exit 2".to_string());
+        match failing.read(&Settings::default()) {
+            Err(TokenError::Failed(_, status)) => assert_eq!(status.code(), Some(2)),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,331 @@
+//! Reading the WAN addresses from a [UniFi](https://ui.com/) gateway or Network controller.
+//!
+//! Both UniFi OS consoles, such as the UDM, and standalone Network controllers report the state of each WAN link in
+//! the `stat/health` endpoint of a site. On dual-WAN gateways, the second link is reported as `wan2`.
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::header::{COOKIE, SET_COOKIE};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::http;
+use crate::source::{IpSource, SourceError};
+
+/// How long to wait for the controller to answer.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Errors that can occur when reading the WAN addresses from a UniFi controller.
+#[derive(Debug)]
+pub enum UnifiError {
+    /// The controller's URL or credentials weren't configured. Contains the name of the missing option.
+    NotConfigured(&'static str),
+
+    /// The password file could not be read.
+    Password(io::Error),
+
+    /// The request failed or the controller answered with an error, e.g. because the credentials are wrong.
+    RequestFailed(reqwest::Error),
+
+    /// No WAN link reported an address, or the requested one doesn't exist. Contains the requested link, if any.
+    NoAddress(Option<String>),
+}
+
+impl fmt::Display for UnifiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotConfigured(option) => write!(f, "The unifi source requires {option}."),
+            Self::Password(e) => write!(f, "Failed to read the UniFi password file: {e}."),
+            Self::RequestFailed(e) => write!(f, "Failed to query the UniFi controller: {e}."),
+            Self::NoAddress(Some(wan)) => {
+                write!(
+                    f,
+                    "The UniFi WAN link {wan} doesn't exist or has no address."
+                )
+            }
+            Self::NoAddress(None) => write!(f, "No UniFi WAN link is up with an address."),
+        }
+    }
+}
+
+impl error::Error for UnifiError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Password(e) => Some(e),
+            Self::RequestFailed(e) => Some(e),
+            Self::NotConfigured(_) | Self::NoAddress(_) => None,
+        }
+    }
+}
+
+/// A WAN link of a UniFi gateway.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Wan {
+    /// The name of the link, `wan` or `wan2`.
+    pub name: String,
+
+    /// The address of the link.
+    pub ip: IpAddr,
+
+    /// Whether the link is up and carrying traffic.
+    pub active: bool,
+}
+
+/// The connection details of a UniFi controller.
+#[derive(Clone, Debug, Default)]
+pub struct UnifiConfig {
+    /// The base URL of the controller, e.g. `https://192.168.1.1` for a UniFi OS console or
+    /// `https://unifi.example.com:8443` for a Network controller.
+    pub url: Option<String>,
+
+    /// The site whose gateway is used.
+    pub site: String,
+
+    /// An API key, which UniFi OS consoles accept instead of a user and password.
+    pub api_key: Option<String>,
+
+    /// The user to log in as.
+    pub user: Option<String>,
+
+    /// The password of the user.
+    pub password: Option<String>,
+
+    /// A file containing the password of the user.
+    pub password_file: Option<PathBuf>,
+
+    /// The WAN link whose address is used, e.g. `wan2`, instead of the first active one.
+    pub wan: Option<String>,
+
+    /// Accept invalid TLS certificates, such as the controller's self-signed one.
+    pub insecure: bool,
+}
+
+/// The envelope of UniFi Network API responses.
+#[derive(Deserialize)]
+struct Response<T> {
+    data: Vec<T>,
+}
+
+/// A subsystem entry of `stat/health`.
+#[derive(Deserialize)]
+struct Health {
+    subsystem: String,
+    #[serde(default)]
+    status: String,
+    wan_ip: Option<String>,
+}
+
+/// An [`IpSource`] that reads the address of the active WAN link of a UniFi gateway.
+pub struct UnifiSource {
+    config: UnifiConfig,
+}
+
+/// An authenticated session with a controller.
+struct Session {
+    client: Client,
+
+    /// The prefix of Network API paths: `/proxy/network` on UniFi OS and empty on standalone controllers.
+    prefix: &'static str,
+
+    /// The headers authenticating requests.
+    headers: Vec<(&'static str, String)>,
+}
+
+impl Session {
+    fn get(&self, url: &str, path: &str) -> RequestBuilder {
+        self.headers.iter().fold(
+            self.client.get(format!("{url}{}{path}", self.prefix)),
+            |request, (name, value)| request.header(*name, value),
+        )
+    }
+}
+
+impl UnifiSource {
+    /// Create a source reading the WAN address from the controller described by `config`.
+    pub fn new(config: UnifiConfig) -> Self {
+        Self { config }
+    }
+
+    /// Every WAN link of the gateway with an address.
+    pub fn wans(&self) -> Result<Vec<Wan>, UnifiError> {
+        let url = self.url()?;
+        let session = self.login(url)?;
+        let health: Response<Health> = session
+            .get(url, &format!("/api/s/{}/stat/health", self.config.site))
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.json())
+            .map_err(UnifiError::RequestFailed)?;
+
+        Ok(wans(health.data))
+    }
+
+    fn url(&self) -> Result<&str, UnifiError> {
+        self.config
+            .url
+            .as_deref()
+            .map(|url| url.trim_end_matches('/'))
+            .ok_or(UnifiError::NotConfigured("--unifi-url"))
+    }
+
+    /// Authenticate with the API key, or log in with the user and password, detecting the kind of controller.
+    fn login(&self, url: &str) -> Result<Session, UnifiError> {
+        let config = &self.config;
+        let builder = http::client_builder().timeout(TIMEOUT);
+        #[cfg(any(feature = "native-tls", feature = "rustls"))]
+        let builder = builder.danger_accept_invalid_certs(config.insecure);
+        let client = builder.build().map_err(UnifiError::RequestFailed)?;
+
+        if let Some(api_key) = &config.api_key {
+            return Ok(Session {
+                client,
+                prefix: "/proxy/network",
+                headers: vec![("X-API-KEY", api_key.clone())],
+            });
+        }
+
+        let user = config
+            .user
+            .as_deref()
+            .ok_or(UnifiError::NotConfigured("--unifi-api-key or --unifi-user"))?;
+
+        let password = match (&config.password, &config.password_file) {
+            (Some(password), _) => password.clone(),
+            (None, Some(path)) => fs::read_to_string(path)
+                .map_err(UnifiError::Password)?
+                .trim()
+                .to_string(),
+            (None, None) => String::new(),
+        };
+
+        let body = json!({ "username": user, "password": password });
+
+        // UniFi OS consoles log in at /api/auth/login, standalone controllers at /api/login.
+        let mut prefix = "/proxy/network";
+        let mut response = client
+            .post(format!("{url}/api/auth/login"))
+            .json(&body)
+            .send()
+            .map_err(UnifiError::RequestFailed)?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            prefix = "";
+            response = client
+                .post(format!("{url}/api/login"))
+                .json(&body)
+                .send()
+                .map_err(UnifiError::RequestFailed)?;
+        }
+
+        let response = response
+            .error_for_status()
+            .map_err(UnifiError::RequestFailed)?;
+
+        let cookies: Vec<&str> = response
+            .headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok()?.split(';').next())
+            .collect();
+
+        let mut headers = vec![(COOKIE.as_str(), cookies.join("; "))];
+        if let Some(csrf) = response
+            .headers()
+            .get("x-csrf-token")
+            .and_then(|value| value.to_str().ok())
+        {
+            headers.push(("X-CSRF-Token", csrf.to_string()));
+        }
+
+        Ok(Session {
+            client,
+            prefix,
+            headers,
+        })
+    }
+}
+
+impl IpSource for UnifiSource {
+    fn name(&self) -> &str {
+        "unifi"
+    }
+
+    fn get_ip(&self) -> Result<IpAddr, SourceError> {
+        let wans = self.wans()?;
+        let wanted = self.config.wan.as_deref();
+        select(&wans, wanted)
+            .ok_or_else(|| UnifiError::NoAddress(wanted.map(str::to_string)).into())
+    }
+}
+
+/// The WAN links among the subsystems reported by `stat/health`.
+fn wans(health: Vec<Health>) -> Vec<Wan> {
+    health
+        .into_iter()
+        .filter(|health| health.subsystem.starts_with("wan"))
+        .filter_map(|health| {
+            Some(Wan {
+                ip: health.wan_ip?.parse().ok()?,
+                active: health.status == "ok",
+                name: health.subsystem,
+            })
+        })
+        .collect()
+}
+
+/// The address of the link named `wanted`, or of the first active link.
+fn select(wans: &[Wan], wanted: Option<&str>) -> Option<IpAddr> {
+    wans.iter()
+        .find(|wan| match wanted {
+            Some(wanted) => wan.name == wanted,
+            None => wan.active,
+        })
+        .map(|wan| wan.ip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dual_wan() -> Vec<Wan> {
+        let health: Response<Health> = serde_json::from_str(
+            r#"{"meta":{"rc":"ok"},"data":[
+                {"subsystem":"wlan","status":"ok"},
+                {"subsystem":"wan","status":"error","wan_ip":"198.51.100.1"},
+                {"subsystem":"wan2","status":"ok","wan_ip":"203.0.113.7"},
+                {"subsystem":"lan","status":"ok"}
+            ]}"#,
+        )
+        .unwrap();
+
+        wans(health.data)
+    }
+
+    #[test]
+    fn parses_wan_links() {
+        let wans = dual_wan();
+        assert_eq!(wans.len(), 2);
+        assert_eq!(wans[0].name, "wan");
+        assert!(!wans[0].active);
+        assert!(wans[1].active);
+    }
+
+    #[test]
+    fn selects_active_link() {
+        let wans = dual_wan();
+        assert_eq!(select(&wans, None), Some("203.0.113.7".parse().unwrap()));
+        assert_eq!(
+            select(&wans, Some("wan")),
+            Some("198.51.100.1".parse().unwrap())
+        );
+        assert_eq!(select(&wans, Some("wan3")), None);
+    }
+}
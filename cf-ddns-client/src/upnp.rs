@@ -0,0 +1,279 @@
+//! Asking the home router for its WAN address with [UPnP IGD](https://openconnectivity.org/developer/specifications/upnp-resources/upnp/internet-gateway-device-igd-v-2-0/).
+//!
+//! The router is discovered with an SSDP multicast search, its device description is fetched to find the WAN
+//! connection service, and the service's `GetExternalIPAddress` action returns the address. No request leaves the
+//! local network.
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use reqwest::Url;
+
+use crate::http;
+use crate::ip;
+use crate::source::{IpSource, SourceError};
+
+/// The SSDP multicast address.
+const SSDP: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(239, 255, 255, 250)), 1900);
+
+/// The device type searched for.
+const IGD: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+
+/// The services that provide `GetExternalIPAddress`, in order of preference.
+const WAN_SERVICES: [&str; 3] = [
+    "urn:schemas-upnp-org:service:WANIPConnection:2",
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+
+/// How long to wait for the router to answer each step.
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Errors that can occur when asking the router for its WAN address.
+#[derive(Debug)]
+pub enum UpnpError {
+    /// The SSDP search could not be sent or no router answered in time.
+    Discovery(io::Error),
+
+    /// The router's SSDP answer had no valid `LOCATION` header.
+    NoLocation,
+
+    /// The router's device description or SOAP action could not be fetched.
+    RequestFailed(reqwest::Error),
+
+    /// The router doesn't offer a WAN connection service.
+    NoWanService,
+
+    /// The router's answer did not contain a valid IP address.
+    NoAddress,
+
+    /// The router's WAN address is itself a private address, so there is another NAT between it and the internet.
+    /// Contains the address.
+    DoubleNat(IpAddr),
+}
+
+impl fmt::Display for UpnpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Discovery(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                write!(f, "No UPnP router answered within {} seconds.", TIMEOUT.as_secs())
+            }
+            Self::Discovery(e) => write!(f, "Failed to search for a UPnP router: {e}."),
+            Self::NoLocation => write!(f, "The UPnP router did not say where its description is."),
+            Self::RequestFailed(e) => write!(f, "Failed to query the UPnP router: {e}."),
+            Self::NoWanService => write!(f, "The UPnP router has no WAN connection service."),
+            Self::NoAddress => write!(f, "The UPnP router did not return its WAN address."),
+            Self::DoubleNat(ip) => write!(
+                f,
+                "The UPnP router's WAN address {ip} is private, so it is behind another NAT (double NAT) and its address isn't the public one."
+            ),
+        }
+    }
+}
+
+impl error::Error for UpnpError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Discovery(e) => Some(e),
+            Self::RequestFailed(e) => Some(e),
+            Self::NoLocation | Self::NoWanService | Self::NoAddress | Self::DoubleNat(_) => None,
+        }
+    }
+}
+
+/// An [`IpSource`] that asks the router for its WAN address with UPnP IGD.
+#[derive(Default)]
+pub struct UpnpSource;
+
+impl UpnpSource {
+    fn query(&self) -> Result<IpAddr, UpnpError> {
+        let location = discover()?;
+        let client = http::client_builder()
+            .timeout(TIMEOUT)
+            .build()
+            .map_err(UpnpError::RequestFailed)?;
+
+        let description = client
+            .get(location.clone())
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.text())
+            .map_err(UpnpError::RequestFailed)?;
+
+        let (service, control) = wan_service(&description).ok_or(UpnpError::NoWanService)?;
+        let control = location
+            .join(control)
+            .map_err(|_| UpnpError::NoWanService)?;
+
+        let response = soap(&client, control, service, "GetExternalIPAddress")
+            .map_err(UpnpError::RequestFailed)?;
+
+        let ip: IpAddr = element(&response, "NewExternalIPAddress")
+            .and_then(|ip| ip.trim().parse().ok())
+            .ok_or(UpnpError::NoAddress)?;
+
+        if is_private(ip) {
+            return Err(UpnpError::DoubleNat(ip));
+        }
+
+        Ok(ip)
+    }
+}
+
+impl IpSource for UpnpSource {
+    fn name(&self) -> &str {
+        "upnp"
+    }
+
+    fn get_ip(&self) -> Result<IpAddr, SourceError> {
+        Ok(self.query()?)
+    }
+}
+
+/// Invoke the argument-less SOAP `action` of `service` at its `control` URL, returning the response body.
+pub(crate) fn soap(
+    client: &Client,
+    control: Url,
+    service: &str,
+    action: &str,
+) -> reqwest::Result<String> {
+    let body = format!(
+        r#"<?xml version="1.0"?><s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/" s:encodingStyle="http://schemas.xmlsoap.org/soap/encoding/"><s:Body><u:{action} xmlns:u="{service}"/></s:Body></s:Envelope>"#
+    );
+
+    client
+        .post(control)
+        .header("Content-Type", "text/xml; charset=\"utf-8\"")
+        .header("SOAPAction", format!("\"{service}#{action}\""))
+        .body(body)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+}
+
+/// Search for an Internet Gateway Device, returning the location of its device description.
+fn discover() -> Result<Url, UpnpError> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).map_err(UpnpError::Discovery)?;
+    socket
+        .set_read_timeout(Some(TIMEOUT))
+        .map_err(UpnpError::Discovery)?;
+
+    let search = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {SSDP}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {IGD}\r\n\r\n"
+    );
+
+    socket
+        .send_to(search.as_bytes(), SSDP)
+        .map_err(UpnpError::Discovery)?;
+
+    let mut buf = [0; 2048];
+    let (len, _) = socket.recv_from(&mut buf).map_err(UpnpError::Discovery)?;
+    let answer = String::from_utf8_lossy(&buf[..len]);
+    location(&answer)
+        .and_then(|location| Url::parse(location).ok())
+        .ok_or(UpnpError::NoLocation)
+}
+
+/// The value of the `LOCATION` header of an SSDP answer.
+fn location(answer: &str) -> Option<&str> {
+    answer.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("location")
+            .then(|| value.trim())
+    })
+}
+
+/// The type and control URL of the preferred WAN connection service in a device description.
+fn wan_service(description: &str) -> Option<(&'static str, &str)> {
+    WAN_SERVICES.iter().find_map(|&wanted| {
+        description.split("<service>").skip(1).find_map(|service| {
+            (element(service, "serviceType")?.trim() == wanted)
+                .then(|| element(service, "controlURL"))
+                .flatten()
+                .map(|control| (wanted, control.trim()))
+        })
+    })
+}
+
+/// The text of the first element named `name` in `xml`, ignoring any namespace prefix on the element.
+pub(crate) fn element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = xml;
+    loop {
+        let start = rest.find('<')?;
+        rest = &rest[start + 1..];
+        let end = rest.find('>')?;
+        let tag = &rest[..end];
+        let local = tag.rsplit(':').next().unwrap_or(tag);
+        rest = &rest[end + 1..];
+        if local == name {
+            let close = rest.find("</")?;
+            return Some(&rest[..close]);
+        }
+    }
+}
+
+/// Whether `ip` is a private or shared address that can't be the public address of the network.
+pub(crate) fn is_private(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip::is_cgnat(ip)
+        }
+        IpAddr::V6(ip) => ip.is_loopback() || ip.is_unspecified(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_location() {
+        let answer = "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=120\r\nLocation: http://192.168.1.1:5000/rootDesc.xml\r\n\r\n";
+        assert_eq!(
+            location(answer),
+            Some("http://192.168.1.1:5000/rootDesc.xml")
+        );
+    }
+
+    #[test]
+    fn finds_wan_service() {
+        let description = r#"<root><device><serviceList>
+            <service><serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType><controlURL>/ctl/L3F</controlURL></service>
+            <service><serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType><controlURL>/ctl/IPConn</controlURL></service>
+        </serviceList></device></root>"#;
+
+        assert_eq!(
+            wan_service(description),
+            Some((
+                "urn:schemas-upnp-org:service:WANIPConnection:1",
+                "/ctl/IPConn"
+            ))
+        );
+    }
+
+    #[test]
+    fn parses_soap_response() {
+        let response = r#"<?xml version="1.0"?><s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/"><s:Body><u:GetExternalIPAddressResponse xmlns:u="urn:schemas-upnp-org:service:WANIPConnection:1"><NewExternalIPAddress>203.0.113.7</NewExternalIPAddress></u:GetExternalIPAddressResponse></s:Body></s:Envelope>"#;
+        assert_eq!(
+            element(response, "NewExternalIPAddress"),
+            Some("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn detects_double_nat() {
+        assert!(is_private("10.0.0.1".parse().unwrap()));
+        assert!(is_private("100.64.0.1".parse().unwrap()));
+        assert!(!is_private("100.128.0.1".parse().unwrap()));
+        assert!(!is_private("203.0.113.7".parse().unwrap()));
+    }
+}
@@ -0,0 +1,208 @@
+//! Reading the API token from a secret of a HashiCorp Vault KV engine, for `--token-vault`.
+//!
+//! The server is read from `VAULT_ADDR`, like the `vault` CLI does, and its certificate may be signed by the CA
+//! certificates at `VAULT_CACERT`. The client authenticates with `VAULT_TOKEN`, or logs in with AppRole when
+//! `VAULT_ROLE_ID` and `VAULT_SECRET_ID` are set instead. `VAULT_NAMESPACE` selects a namespace of Vault Enterprise.
+
+use std::env;
+use std::error;
+use std::fmt;
+
+use serde_json::{json, Value};
+
+use crate::http::{self, Settings};
+
+/// The field of the secret holding the token, unless the path names another.
+pub const DEFAULT_FIELD: &str = "token";
+
+/// Errors that can occur when reading the token from Vault.
+#[derive(Debug)]
+pub enum VaultError {
+    /// `VAULT_ADDR` isn't set.
+    NoAddress,
+
+    /// Neither `VAULT_TOKEN` nor `VAULT_ROLE_ID` and `VAULT_SECRET_ID` are set.
+    NoCredentials,
+
+    /// The CA certificates at `VAULT_CACERT` could not be read. Contains why.
+    CaCert(String),
+
+    /// The answer to the AppRole login has no client token.
+    NoClientToken,
+
+    /// Logging in or reading the secret failed.
+    RequestFailed(reqwest::Error),
+
+    /// The secret has no such field. Contains the path.
+    NoField(VaultPath),
+}
+
+impl fmt::Display for VaultError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoAddress => write!(f, "VAULT_ADDR isn't set."),
+            Self::NoCredentials => write!(
+                f,
+                "Set VAULT_TOKEN, or VAULT_ROLE_ID and VAULT_SECRET_ID to log in with AppRole."
+            ),
+            Self::CaCert(e) => write!(f, "Could not read VAULT_CACERT: {e}."),
+            Self::NoClientToken => {
+                write!(f, "Vault logged in with AppRole without a client token.")
+            }
+            Self::RequestFailed(e) => write!(f, "Failed to send request to Vault: {e}."),
+            Self::NoField(path) => {
+                write!(f, "The secret {} has no field {}.", path.path, path.field)
+            }
+        }
+    }
+}
+
+impl error::Error for VaultError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::RequestFailed(e) => Some(e),
+            Self::NoAddress
+            | Self::NoCredentials
+            | Self::CaCert(_)
+            | Self::NoClientToken
+            | Self::NoField(_) => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for VaultError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::RequestFailed(e)
+    }
+}
+
+/// A field of a secret, written like `secret/data/cloudflare#token`. The path is that of the API, which for version
+/// 2 of the KV engine includes `data/` after the mount. Without a `#`, the field is [`DEFAULT_FIELD`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VaultPath {
+    /// The path of the secret, without the leading `/v1/`.
+    pub path: String,
+
+    /// The field of the secret holding the token.
+    pub field: String,
+}
+
+impl From<&str> for VaultPath {
+    fn from(value: &str) -> Self {
+        let (path, field) = value.split_once('#').unwrap_or((value, DEFAULT_FIELD));
+        Self {
+            path: path.trim_matches('/').to_string(),
+            field: field.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for VaultPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}#{}", self.path, self.field)
+    }
+}
+
+/// Read the field at `path` from the Vault at `VAULT_ADDR`, sending the requests with `settings`.
+pub fn read(path: &VaultPath, settings: &Settings) -> Result<String, VaultError> {
+    let address = env::var("VAULT_ADDR").map_err(|_| VaultError::NoAddress)?;
+    let address = address.trim_end_matches('/');
+    let namespace = env::var("VAULT_NAMESPACE").ok();
+    let client = match env::var("VAULT_CACERT") {
+        Ok(path) => with_ca_cert(settings, &path)?.builder(),
+        Err(_) => settings.builder(),
+    }
+    .build()?;
+    let request = |request: reqwest::blocking::RequestBuilder| match &namespace {
+        Some(namespace) => request.header("X-Vault-Namespace", namespace),
+        None => request,
+    };
+
+    let token = match env::var("VAULT_TOKEN") {
+        Ok(token) => token,
+        Err(_) => {
+            let (Ok(role_id), Ok(secret_id)) =
+                (env::var("VAULT_ROLE_ID"), env::var("VAULT_SECRET_ID"))
+            else {
+                return Err(VaultError::NoCredentials);
+            };
+
+            let login: Value = request(client.post(format!("{address}/v1/auth/approle/login")))
+                .json(&json!({ "role_id": role_id, "secret_id": secret_id }))
+                .send()?
+                .error_for_status()?
+                .json()?;
+            login["auth"]["client_token"]
+                .as_str()
+                .filter(|token| !token.is_empty())
+                .ok_or(VaultError::NoClientToken)?
+                .to_string()
+        }
+    };
+
+    let secret: Value = request(client.get(format!("{address}/v1/{}", path.path)))
+        .header("X-Vault-Token", token)
+        .send()?
+        .error_for_status()?
+        .json()?;
+
+    field(&secret, &path.field)
+        .map(str::to_string)
+        .ok_or_else(|| VaultError::NoField(path.clone()))
+}
+
+/// `settings`, also trusting the CA certificates at `path`.
+fn with_ca_cert(settings: &Settings, path: &str) -> Result<Settings, VaultError> {
+    let pem = http::read_ca_cert(path).map_err(VaultError::CaCert)?;
+    let mut settings = settings.clone();
+    let ca_cert = settings.tls.ca_cert.get_or_insert_with(Vec::new);
+    if !ca_cert.is_empty() && !ca_cert.ends_with(b"\n") {
+        ca_cert.push(b'\n');
+    }
+
+    ca_cert.extend(pem);
+    Ok(settings)
+}
+
+/// The string `field` of the `secret` read from a KV engine of either version. Version 2 nests the fields in another
+/// `data` object, beside the `metadata` of the version.
+fn field<'a>(secret: &'a Value, field: &str) -> Option<&'a str> {
+    let data = &secret["data"];
+    let fields = match data.get("metadata") {
+        Some(_) => &data["data"],
+        None => data,
+    };
+
+    fields.get(field)?.as_str()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_fields_of_both_versions() {
+        assert_eq!(
+            VaultPath::from("/secret/data/cloudflare#api-token"),
+            VaultPath {
+                path: "secret/data/cloudflare".to_string(),
+                field: "api-token".to_string(),
+            }
+        );
+        assert_eq!(VaultPath::from("kv/cloudflare").field, DEFAULT_FIELD);
+
+        let v1 = json!({ "data": { "token": "one" } });
+        let v2 = json!({ "data": { "data": { "token": "two" }, "metadata": { "version": 3 } } });
+        assert_eq!(field(&v1, "token"), Some("one"));
+        assert_eq!(field(&v2, "token"), Some("two"));
+        assert_eq!(field(&v2, "other"), None);
+    }
+
+    #[test]
+    fn reads_ca_cert() {
+        assert!(matches!(
+            with_ca_cert(&Settings::default(), "/nonexistent/vault-ca.pem"),
+            Err(VaultError::CaCert(_))
+        ));
+    }
+}
@@ -0,0 +1,62 @@
+//! How much the client prints, from `-q` for errors only to `-vvv` for every HTTP request.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+static LEVEL: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+/// A level of output, each including the ones before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only errors and warnings, selected by `-q`.
+    Quiet,
+
+    /// What the run changed, or why it didn't.
+    Normal,
+
+    /// The decisions taken, such as the address each source answered and the address published, selected by `-v`.
+    Verbose,
+
+    /// The IDs looked up and the API requests counted, selected by `-vv`.
+    Debug,
+
+    /// Every request sent to Cloudflare with its status and duration, selected by `-vvv`.
+    Trace,
+}
+
+impl Verbosity {
+    /// The level selected by giving `-v` `verbose` times, or `-q` if `quiet`.
+    pub fn new(verbose: u8, quiet: bool) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Self::Quiet,
+            (false, 0) => Self::Normal,
+            (false, 1) => Self::Verbose,
+            (false, 2) => Self::Debug,
+            (false, _) => Self::Trace,
+        }
+    }
+}
+
+/// Set the level of output for the rest of the run.
+pub fn init(verbosity: Verbosity) {
+    LEVEL.store(verbosity as u8, Ordering::Relaxed);
+}
+
+/// Whether output at `verbosity` is printed.
+pub fn enabled(verbosity: Verbosity) -> bool {
+    verbosity as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_flags() {
+        assert_eq!(Verbosity::new(0, false), Verbosity::Normal);
+        assert_eq!(Verbosity::new(1, false), Verbosity::Verbose);
+        assert_eq!(Verbosity::new(2, false), Verbosity::Debug);
+        assert_eq!(Verbosity::new(3, false), Verbosity::Trace);
+        assert_eq!(Verbosity::new(7, false), Verbosity::Trace);
+        assert_eq!(Verbosity::new(0, true), Verbosity::Quiet);
+    }
+}
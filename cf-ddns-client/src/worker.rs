@@ -3,9 +3,18 @@
 use std::error;
 use std::fmt;
 use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use cf_ddns::Response;
 use reqwest::blocking::get;
+use reqwest::header::{HeaderMap, HeaderValue, IF_NONE_MATCH};
+
+use crate::echo::{self, EchoError};
+use crate::http::{Settings, Tls};
+use crate::retry::Retry;
+use crate::source::{Family, IpSource, SourceError};
+use crate::state::{State, Validator};
 
 /// Errors that can occur when getting the IP address from the DDNS worker.
 #[derive(Debug)]
@@ -55,3 +64,164 @@ pub fn get_ip(url: &str) -> Result<IpAddr, GetIpError> {
         Err(GetIpError::UnsuccessfulResponse(response))
     }
 }
+
+/// An [`IpSource`] that asks the DDNS worker at a URL for the IP address.
+///
+/// The URL may also point at any other echo service, whose response [format](crate::echo::Format) is detected.
+#[derive(Clone)]
+pub struct WorkerSource {
+    name: String,
+    url: String,
+    http: Settings,
+    headers: HeaderMap,
+    family: Option<Family>,
+    retry: Retry,
+    state: Option<PathBuf>,
+}
+
+impl WorkerSource {
+    /// Create a source using the DDNS worker at `url`.
+    pub fn new(url: impl Into<String>) -> Self {
+        let url = url.into();
+        Self {
+            name: format!("worker:{url}"),
+            url,
+            http: Settings::default(),
+            headers: HeaderMap::new(),
+            family: None,
+            retry: Retry::default(),
+            state: None,
+        }
+    }
+
+    /// The URL of the worker.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Give up on the worker if it hasn't answered within `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.http.timeout = timeout;
+        self
+    }
+
+    /// Give up on connecting to the worker if it hasn't connected within `timeout`, or only limit connecting by
+    /// [`Self::with_timeout`] if `None`.
+    pub fn with_connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.http.connect = timeout;
+        self
+    }
+
+    /// Identify the client to the worker with `user_agent`, instead of the [default](crate::http::DEFAULT_USER_AGENT).
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.http.user_agent = user_agent.into();
+        self
+    }
+
+    /// Send the requests to the worker through the proxy at `proxy`, checked with [`parse_proxy`](crate::http::parse_proxy),
+    /// or through those of the environment if `None`.
+    pub fn with_proxy(mut self, proxy: Option<String>) -> Self {
+        self.http.proxy = proxy;
+        self
+    }
+
+    /// Verify the certificate of the worker as `tls` describes, instead of only against the system's CAs.
+    pub fn with_tls(mut self, tls: Tls) -> Self {
+        self.http.tls = tls;
+        self
+    }
+
+    /// Send `headers` with each request to the worker, such as an `Authorization` header for a worker requiring
+    /// authentication, or the `CF-Access-Client-Id` and `CF-Access-Client-Secret` of a Cloudflare Access service
+    /// token.
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Send the requests to the worker over HTTP/3 if `http3`, sending them again over HTTP/2 or HTTP/1.1 if that
+    /// fails.
+    #[cfg(feature = "http3")]
+    pub fn with_http3(mut self, http3: bool) -> Self {
+        self.http.http3 = http3;
+        self
+    }
+
+    /// Only contact the worker over `family`, or over either family if `None`.
+    ///
+    /// The worker answers with the address the request came from, so this selects whether it answers with the public
+    /// IPv4 or IPv6 address of a dual-stack host.
+    pub fn with_family(mut self, family: Option<Family>) -> Self {
+        self.family = family;
+        self
+    }
+
+    /// Retry [transient](EchoError::is_transient) failures, such as network errors and server errors, according to
+    /// `retry`.
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Remember the `ETag` of the worker's answers in the [state](crate::state) at `path`, and ask the worker with
+    /// `If-None-Match` whether the address changed since, which it answers with an empty `304 Not Modified` if not.
+    pub fn with_state(mut self, path: Option<PathBuf>) -> Self {
+        self.state = path;
+        self
+    }
+
+    /// The key of the last answer in the state, which depends on the family the worker is asked over.
+    fn validator_key(&self) -> String {
+        match self.family {
+            Some(family) => format!("{} over {family}", self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Save the last answer of the worker in the state at `path`, if it changed, warning if it can't be written.
+    fn save_validator(&self, path: &Path, last: Option<&Validator>, validator: Option<Validator>) {
+        if last == validator.as_ref() {
+            return;
+        }
+
+        if let Err(err) = State::save_validator(path, &self.validator_key(), validator) {
+            tracing::warn!("Could not write the state file {}: {err}", path.display());
+        }
+    }
+}
+
+impl IpSource for WorkerSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_ip(&self) -> Result<IpAddr, SourceError> {
+        let Some(path) = &self.state else {
+            let fetch = || echo::fetch(&self.url, &self.http, &self.headers, self.family);
+            return Ok(self.retry.run(fetch, EchoError::is_transient)?);
+        };
+
+        let last = State::load(path).validator(&self.validator_key()).cloned();
+        let mut headers = self.headers.clone();
+        if let Some(etag) = last
+            .as_ref()
+            .and_then(|last| HeaderValue::try_from(&last.etag).ok())
+        {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
+
+        let fetch = || echo::fetch_tagged(&self.url, &self.http, &headers, self.family);
+        match (self.retry.run(fetch, EchoError::is_transient), &last) {
+            (Ok((ip, etag)), _) => {
+                let validator = etag.map(|etag| Validator { etag, ip });
+                self.save_validator(path, last.as_ref(), validator);
+                Ok(ip)
+            }
+            (Err(EchoError::NotModified), Some(last)) => {
+                tracing::debug!(ip = %last.ip, "the worker answered that the address is unchanged");
+                Ok(last.ip)
+            }
+            (Err(err), _) => Err(err.into()),
+        }
+    }
+}
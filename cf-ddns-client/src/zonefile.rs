@@ -0,0 +1,219 @@
+//! Writing DNS records as a [BIND zone file](https://datatracker.ietf.org/doc/html/rfc1035#section-5).
+
+use std::fmt::Write;
+
+use cf_ddns_cloudflare::{Record, Zone};
+
+/// The TTL Cloudflare uses for records with an automatic TTL, in seconds.
+const AUTOMATIC_TTL: u32 = 300;
+
+/// The TTL of the generated `SOA` and `NS` records, in seconds.
+const ZONE_TTL: u32 = 86400;
+
+/// The longest string allowed in a single quoted `TXT` segment.
+const TXT_SEGMENT: usize = 255;
+
+/// Write `records` of `zone` as a BIND zone file, with an `SOA` record using `serial`.
+///
+/// Names are written as absolute names with a trailing dot. Proxied records are annotated with the
+/// `cf_tags=cf-proxied:true` comment that Cloudflare's importer understands.
+pub fn write(zone: &Zone, records: &[Record], serial: u32) -> String {
+    let origin = absolute(&zone.name);
+    let primary = zone
+        .name_servers
+        .first()
+        .map(|ns| absolute(ns))
+        .unwrap_or_else(|| origin.clone());
+
+    let mut out = String::new();
+    writeln!(out, ";; Zone file for {} exported by cf-ddns", zone.name).unwrap();
+    writeln!(out, "$ORIGIN {origin}").unwrap();
+    writeln!(out, "$TTL {AUTOMATIC_TTL}").unwrap();
+    writeln!(
+        out,
+        "{origin}\t{ZONE_TTL}\tIN\tSOA\t{primary} dns.cloudflare.com. {serial} 10000 2400 604800 3600"
+    )
+    .unwrap();
+
+    for ns in &zone.name_servers {
+        writeln!(out, "{origin}\t{ZONE_TTL}\tIN\tNS\t{}", absolute(ns)).unwrap();
+    }
+
+    for record in records {
+        writeln!(out, "{}", line(record)).unwrap();
+    }
+
+    out
+}
+
+/// Format a single record as a zone file line.
+fn line(record: &Record) -> String {
+    let record_type = record.content.record_type();
+    let ttl = match record.ttl {
+        1 => AUTOMATIC_TTL,
+        ttl => ttl,
+    };
+
+    let content = record.content.to_string();
+    let data = match record_type {
+        "CNAME" | "NS" | "PTR" | "DNAME" => absolute(&content),
+        "MX" => format!("{} {}", record.priority.unwrap_or(0), absolute(&content)),
+        "SRV" => format!("{} {}", record.priority.unwrap_or(0), srv(&content)),
+        "TXT" | "SPF" => txt(&content),
+        _ => content,
+    };
+
+    let mut line = format!(
+        "{}\t{ttl}\tIN\t{record_type}\t{data}",
+        absolute(&record.name)
+    );
+    if record.proxied {
+        line.push_str(" ; cf_tags=cf-proxied:true");
+    }
+
+    line
+}
+
+/// Make `name` absolute by appending a trailing dot if it doesn't have one.
+fn absolute(name: &str) -> String {
+    if name.ends_with('.') {
+        name.to_string()
+    } else {
+        format!("{name}.")
+    }
+}
+
+/// Make the target of an SRV record's `weight port target` content absolute.
+fn srv(content: &str) -> String {
+    match content.rsplit_once(' ') {
+        Some((rest, target)) => format!("{rest} {}", absolute(target)),
+        None => content.to_string(),
+    }
+}
+
+/// Quote the content of a TXT record, splitting it into segments of at most 255 characters.
+///
+/// Content that is already quoted, as Cloudflare returns for some records, is left as-is.
+fn txt(content: &str) -> String {
+    if content.starts_with('"') {
+        return content.to_string();
+    }
+
+    let escaped: Vec<String> = content
+        .chars()
+        .collect::<Vec<_>>()
+        .chunks(TXT_SEGMENT)
+        .map(|chunk| {
+            let segment: String = chunk.iter().collect();
+            format!("\"{}\"", segment.replace('\\', "\\\\").replace('"', "\\\""))
+        })
+        .collect();
+
+    if escaped.is_empty() {
+        "\"\"".to_string()
+    } else {
+        escaped.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use cf_ddns_cloudflare::RecordContent;
+
+    use super::*;
+    use crate::records;
+
+    fn record(name: &str, content: RecordContent) -> Record {
+        records::tests::record("1", name, content)
+    }
+
+    fn other(record_type: &str, content: &str) -> RecordContent {
+        RecordContent::Other {
+            record_type: record_type.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn a_record() {
+        let mut record = record(
+            "home.example.com",
+            RecordContent::A {
+                content: Ipv4Addr::new(203, 0, 113, 7),
+            },
+        );
+
+        record.proxied = true;
+        assert_eq!(
+            line(&record),
+            "home.example.com.\t300\tIN\tA\t203.0.113.7 ; cf_tags=cf-proxied:true"
+        );
+    }
+
+    #[test]
+    fn mx_record() {
+        let mut record = record("example.com", other("MX", "mail.example.com"));
+        record.ttl = 3600;
+        record.priority = Some(10);
+        assert_eq!(
+            line(&record),
+            "example.com.\t3600\tIN\tMX\t10 mail.example.com."
+        );
+    }
+
+    #[test]
+    fn srv_record() {
+        let mut record = record(
+            "_sip._tcp.example.com",
+            other("SRV", "5 5060 sip.example.com"),
+        );
+        record.priority = Some(10);
+        assert_eq!(
+            line(&record),
+            "_sip._tcp.example.com.\t300\tIN\tSRV\t10 5 5060 sip.example.com."
+        );
+    }
+
+    #[test]
+    fn txt_record_is_quoted_and_escaped() {
+        let record = record("example.com", other("TXT", r#"say "hi""#));
+        assert_eq!(
+            line(&record),
+            "example.com.\t300\tIN\tTXT\t\"say \\\"hi\\\"\""
+        );
+    }
+
+    #[test]
+    fn long_txt_record_is_split() {
+        let content = "a".repeat(300);
+        let quoted = txt(&content);
+        assert_eq!(
+            quoted,
+            format!("\"{}\" \"{}\"", "a".repeat(255), "a".repeat(45))
+        );
+    }
+
+    #[test]
+    fn zone_header() {
+        let zone = Zone {
+            id: "z".to_string(),
+            name: "example.com".to_string(),
+            status: "active".to_string(),
+            name_servers: vec!["ns1.cloudflare.com".to_string()],
+        };
+
+        let file = write(&zone, &[], 1234);
+        let lines: Vec<&str> = file.lines().collect();
+        assert_eq!(
+            lines[1..],
+            [
+                "$ORIGIN example.com.",
+                "$TTL 300",
+                "example.com.\t86400\tIN\tSOA\tns1.cloudflare.com. dns.cloudflare.com. 1234 10000 2400 604800 3600",
+                "example.com.\t86400\tIN\tNS\tns1.cloudflare.com.",
+            ]
+        );
+    }
+}
@@ -0,0 +1,206 @@
+//! A blocking [`Cloudflare`] client, for programs without an async runtime.
+//!
+//! The client wraps the async [`crate::Cloudflare`] client and runs each call to completion on a runtime of its own,
+//! so it must not be used from within an async runtime.
+
+use std::net::IpAddr;
+
+use reqwest::ClientBuilder;
+use tokio::runtime::{self, Runtime};
+
+use crate::{
+    ClientError, CloudflareError, ConflictError, GetRecordIdError, Hook, NewRecord, NoApiSpecific,
+    RateLimitStatus, Record, RecordPatch, TokenStatus, Zone,
+};
+
+/// A blocking client for interacting with the Cloudflare API.
+pub struct Cloudflare {
+    inner: crate::Cloudflare,
+    runtime: Runtime,
+}
+
+impl Cloudflare {
+    /// Create a new Cloudflare client.
+    pub fn try_new(token: &str) -> Result<Self, ClientError> {
+        Self::try_with_builder(token, reqwest::Client::builder())
+    }
+
+    /// Create a new Cloudflare client whose HTTP client is built by `builder`, e.g. to configure timeouts.
+    pub fn try_with_builder(token: &str, builder: ClientBuilder) -> Result<Self, ClientError> {
+        let inner = crate::Cloudflare::try_with_builder(token, builder)?;
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(ClientError::Runtime)?;
+
+        Ok(Self { inner, runtime })
+    }
+
+    /// Add a [`Hook`] that is called before every request and after every response.
+    ///
+    /// Hooks are called in the order they are added.
+    pub fn with_hook(mut self, hook: impl Hook + 'static) -> Self {
+        self.inner = self.inner.with_hook(hook);
+        self
+    }
+
+    /// Limit the client to sending at most `budget` requests, like [`crate::Cloudflare::with_budget`].
+    pub fn with_budget(mut self, budget: u32) -> Self {
+        self.inner = self.inner.with_budget(budget);
+        self
+    }
+
+    /// The rate limit state observed so far, including the number of requests sent.
+    pub fn rate_limit(&self) -> RateLimitStatus {
+        self.inner.rate_limit()
+    }
+
+    /// Verify the API token the client authenticates with.
+    pub fn verify_token(&self) -> Result<TokenStatus, CloudflareError<NoApiSpecific>> {
+        self.runtime.block_on(self.inner.verify_token())
+    }
+
+    /// List all zones the API token has access to.
+    pub fn list_zones(&self) -> Result<Vec<Zone>, CloudflareError<NoApiSpecific>> {
+        self.runtime.block_on(self.inner.list_zones())
+    }
+
+    /// Get a Cloudflare DNS zone by name.
+    pub fn get_zone(&self, name: &str) -> Result<Zone, CloudflareError<NoApiSpecific>> {
+        self.runtime.block_on(self.inner.get_zone(name))
+    }
+
+    /// Get a Cloudflare DNS zone by its ID.
+    pub fn get_zone_by_id(&self, zone_id: &str) -> Result<Zone, CloudflareError<NoApiSpecific>> {
+        self.runtime.block_on(self.inner.get_zone_by_id(zone_id))
+    }
+
+    /// Get the zone ID of a Cloudflare DNS zone by name.
+    pub fn get_zone_id(&self, name: &str) -> Result<String, CloudflareError<NoApiSpecific>> {
+        self.runtime.block_on(self.inner.get_zone_id(name))
+    }
+
+    /// Get all DNS records in a zone with the given fully-qualified name.
+    pub fn get_records(
+        &self,
+        zone_id: &str,
+        name: &str,
+    ) -> Result<Vec<Record>, CloudflareError<NoApiSpecific>> {
+        self.runtime.block_on(self.inner.get_records(zone_id, name))
+    }
+
+    /// List all DNS records in a zone, optionally only those of type `record_type`, e.g. `A`.
+    pub fn list_records(
+        &self,
+        zone_id: &str,
+        record_type: Option<&str>,
+    ) -> Result<Vec<Record>, CloudflareError<NoApiSpecific>> {
+        self.runtime
+            .block_on(self.inner.list_records(zone_id, record_type))
+    }
+
+    /// Export all DNS records in a zone as a BIND zone file, using Cloudflare's export endpoint.
+    pub fn export_records(&self, zone_id: &str) -> Result<String, CloudflareError<NoApiSpecific>> {
+        self.runtime.block_on(self.inner.export_records(zone_id))
+    }
+
+    /// Get the record ID of a Cloudflare DNS record by name.
+    pub fn get_record_id(
+        &self,
+        zone_id: &str,
+        name: &str,
+    ) -> Result<String, CloudflareError<GetRecordIdError>> {
+        self.runtime
+            .block_on(self.inner.get_record_id(zone_id, name))
+    }
+
+    /// Get a DNS record by its ID.
+    pub fn get_record(
+        &self,
+        zone_id: &str,
+        record_id: &str,
+    ) -> Result<Record, CloudflareError<NoApiSpecific>> {
+        self.runtime
+            .block_on(self.inner.get_record(zone_id, record_id))
+    }
+
+    /// Create a new DNS record in a zone.
+    pub fn create_record(
+        &self,
+        zone_id: &str,
+        record: &NewRecord,
+    ) -> Result<Record, CloudflareError<NoApiSpecific>> {
+        self.runtime
+            .block_on(self.inner.create_record(zone_id, record))
+    }
+
+    /// Update a DNS record with the new content from the given `content`, like
+    /// [`crate::Cloudflare::update_record`].
+    pub fn update_record(
+        &self,
+        zone_id: &str,
+        record_id: &str,
+        content: IpAddr,
+    ) -> Result<Record, CloudflareError<NoApiSpecific>> {
+        self.runtime
+            .block_on(self.inner.update_record(zone_id, record_id, content))
+    }
+
+    /// Apply a [`RecordPatch`] to a DNS record, leaving any fields it doesn't set unchanged.
+    pub fn patch_record(
+        &self,
+        zone_id: &str,
+        record_id: &str,
+        patch: &RecordPatch,
+    ) -> Result<Record, CloudflareError<NoApiSpecific>> {
+        self.runtime
+            .block_on(self.inner.patch_record(zone_id, record_id, patch))
+    }
+
+    /// Apply a [`RecordPatch`] to `record`, but only if it hasn't been modified since it was read, like
+    /// [`crate::Cloudflare::patch_record_if_unmodified`].
+    pub fn patch_record_if_unmodified(
+        &self,
+        zone_id: &str,
+        record: &Record,
+        patch: &RecordPatch,
+    ) -> Result<Record, CloudflareError<ConflictError>> {
+        self.runtime.block_on(
+            self.inner
+                .patch_record_if_unmodified(zone_id, record, patch),
+        )
+    }
+
+    /// Like [`Self::patch_record_if_unmodified`], but returns `None` without patching if the record, as read again,
+    /// already matches `patch`.
+    pub fn patch_record_if_changed(
+        &self,
+        zone_id: &str,
+        record: &Record,
+        patch: &RecordPatch,
+    ) -> Result<Option<Record>, CloudflareError<ConflictError>> {
+        self.runtime
+            .block_on(self.inner.patch_record_if_changed(zone_id, record, patch))
+    }
+
+    /// Replace every field of a DNS record with those of `record`.
+    pub fn overwrite_record(
+        &self,
+        zone_id: &str,
+        record_id: &str,
+        record: &NewRecord,
+    ) -> Result<Record, CloudflareError<NoApiSpecific>> {
+        self.runtime
+            .block_on(self.inner.overwrite_record(zone_id, record_id, record))
+    }
+
+    /// Delete a DNS record, returning the ID of the deleted record.
+    pub fn delete_record(
+        &self,
+        zone_id: &str,
+        record_id: &str,
+    ) -> Result<String, CloudflareError<NoApiSpecific>> {
+        self.runtime
+            .block_on(self.inner.delete_record(zone_id, record_id))
+    }
+}
@@ -0,0 +1,257 @@
+//! Errors returned by the Cloudflare API client.
+
+use std::error;
+use std::fmt;
+use std::io;
+
+use reqwest::header::InvalidHeaderValue;
+
+use crate::ratelimit::RateLimitError;
+use crate::CfResponseInfo;
+
+/// Errors that can occur when interacting with the Cloudflare API.
+#[derive(Debug)]
+pub enum CloudflareError<T> {
+    /// An error occurred while sending a request to the Cloudflare API.
+    RequestFailed(reqwest::Error),
+
+    /// An error occurred while parsing the JSON response from the Cloudflare API.
+    ResponseNotJson(reqwest::Error),
+
+    /// The Cloudflare API returned an error.
+    Error(Vec<CfResponseInfo>),
+
+    /// The response from the Cloudflare API was empty.
+    EmptyResult,
+
+    /// The request was not sent, or was rejected, because of the API rate limit.
+    RateLimited(RateLimitError),
+
+    /// An error occurred that is specific to the Cloudflare API being called.
+    ApiSpecific(T),
+}
+
+impl<T: fmt::Display> fmt::Display for CloudflareError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::RequestFailed(e) => write!(f, "Failed to send request: {e}."),
+            Self::ResponseNotJson(e) => write!(f, "Failed to parse JSON response: {e}."),
+            Self::Error(errors) => {
+                if errors.is_empty() {
+                    write!(f, "The Cloudflare API returned an unknown error.")
+                } else if errors.len() == 1 {
+                    write!(f, "The Cloudflare API returned an error: {errors:?}")
+                } else {
+                    write!(f, "The Cloudflare API returned multiple errors: {errors:?}")
+                }
+            }
+            Self::EmptyResult => write!(f, "No results found."),
+            Self::RateLimited(e) => write!(f, "{e}"),
+            Self::ApiSpecific(inner) => write!(f, "{inner}"),
+        }
+    }
+}
+
+impl<T: fmt::Display + fmt::Debug> error::Error for CloudflareError<T> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::RequestFailed(e) | Self::ResponseNotJson(e) => Some(e),
+            Self::RateLimited(e) => Some(e),
+            Self::Error(_) | Self::EmptyResult | Self::ApiSpecific(_) => None,
+        }
+    }
+}
+
+/// The Cloudflare API error code for a DNS record that doesn't exist.
+pub const RECORD_NOT_FOUND: i32 = 81044;
+
+/// The Cloudflare API error codes for a missing, malformed, invalid, or insufficiently privileged token.
+const AUTH_ERRORS: [i32; 5] = [6003, 6111, 9103, 9109, 10000];
+
+impl<T> CloudflareError<T> {
+    /// Whether the Cloudflare API returned an error with the given `code`.
+    pub fn has_code(&self, code: i32) -> bool {
+        matches!(self, Self::Error(errors) if errors.iter().any(|error| error.code == code))
+    }
+
+    /// Whether the Cloudflare API reported that the DNS record doesn't exist, e.g. because its ID is stale.
+    pub fn is_record_not_found(&self) -> bool {
+        self.has_code(RECORD_NOT_FOUND)
+    }
+
+    /// Whether the Cloudflare API rejected the API token, or the token lacks permission for the request.
+    pub fn is_auth_error(&self) -> bool {
+        AUTH_ERRORS.iter().any(|&code| self.has_code(code))
+    }
+
+    /// Whether the same request may succeed if it's retried later: network failures, unreadable responses such as
+    /// error pages of an overloaded server, and rate limiting. Errors returned by the API and errors specific to the
+    /// API being called aren't, since they depend on the request.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::RequestFailed(_) | Self::ResponseNotJson(_) | Self::RateLimited(_)
+        )
+    }
+}
+
+impl CloudflareError<NoApiSpecific> {
+    /// Convert an error without API-specific errors into one whose API-specific errors are of type `T`.
+    pub fn with_api_specific<T>(self) -> CloudflareError<T> {
+        match self {
+            Self::RequestFailed(e) => CloudflareError::RequestFailed(e),
+            Self::ResponseNotJson(e) => CloudflareError::ResponseNotJson(e),
+            Self::Error(errors) => CloudflareError::Error(errors),
+            Self::EmptyResult => CloudflareError::EmptyResult,
+            Self::RateLimited(e) => CloudflareError::RateLimited(e),
+            Self::ApiSpecific(NoApiSpecific) => unreachable!("NoApiSpecific is never returned."),
+        }
+    }
+}
+
+/// No additional API-specific errors.
+#[derive(Debug)]
+pub struct NoApiSpecific;
+
+impl fmt::Display for NoApiSpecific {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "An unknown error occurred.")
+    }
+}
+
+impl error::Error for NoApiSpecific {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// Errors that can occur when creating a [`Cloudflare`](crate::Cloudflare) or [blocking](crate::blocking::Cloudflare)
+/// client.
+#[derive(Debug)]
+pub enum ClientError {
+    /// The API token can't be sent in an HTTP header, e.g. because it contains control characters.
+    InvalidToken(InvalidHeaderValue),
+
+    /// The HTTP client could not be built, e.g. because the TLS backend could not be initialized.
+    Build(reqwest::Error),
+
+    /// The runtime of the blocking client could not be created.
+    Runtime(io::Error),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidToken(_) => {
+                write!(f, "The API token contains characters that are not allowed.")
+            }
+            Self::Build(e) => write!(f, "Failed to create the HTTP client: {e}."),
+            Self::Runtime(e) => write!(f, "Failed to create the runtime of the client: {e}."),
+        }
+    }
+}
+
+impl error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::InvalidToken(e) => Some(e),
+            Self::Build(e) => Some(e),
+            Self::Runtime(e) => Some(e),
+        }
+    }
+}
+
+/// Errors that can occur when getting a record ID.
+#[derive(Debug)]
+pub enum GetRecordIdError {
+    /// The record type is not supported.
+    InvalidRecordType(String),
+}
+
+impl fmt::Display for GetRecordIdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidRecordType(record_type) => {
+                write!(f, "The record type '{}' is not supported.", record_type)
+            }
+        }
+    }
+}
+
+impl error::Error for GetRecordIdError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// The error returned when a record was modified by someone else since it was read.
+#[derive(Debug)]
+pub struct ConflictError {
+    /// The ID of the record.
+    pub record_id: String,
+
+    /// When the record was last modified according to the copy that was read.
+    pub expected_modified_on: Option<String>,
+
+    /// When the record was actually last modified.
+    pub actual_modified_on: Option<String>,
+}
+
+impl fmt::Display for ConflictError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unknown = "an unknown time";
+        write!(
+            f,
+            "The record {} was modified concurrently at {}, after it was read at {}.",
+            self.record_id,
+            self.actual_modified_on.as_deref().unwrap_or(unknown),
+            self.expected_modified_on.as_deref().unwrap_or(unknown),
+        )
+    }
+}
+
+impl error::Error for ConflictError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_not_found() {
+        let err = CloudflareError::<NoApiSpecific>::Error(vec![CfResponseInfo {
+            code: RECORD_NOT_FOUND,
+            message: "Record does not exist.".to_string(),
+        }]);
+
+        assert!(err.is_record_not_found());
+        assert!(!CloudflareError::<NoApiSpecific>::EmptyResult.is_record_not_found());
+    }
+
+    #[test]
+    fn auth_error() {
+        let err = CloudflareError::<NoApiSpecific>::Error(vec![CfResponseInfo {
+            code: 10000,
+            message: "Authentication error".to_string(),
+        }]);
+
+        assert!(err.is_auth_error());
+        assert!(!err.is_record_not_found());
+        assert!(!err.is_retryable());
+    }
+
+    #[test]
+    fn invalid_token() {
+        assert!(matches!(
+            crate::Cloudflare::try_new("abc\u{1}def"),
+            Err(ClientError::InvalidToken(_))
+        ));
+        assert!(matches!(
+            crate::blocking::Cloudflare::try_new("abc\u{1}def"),
+            Err(ClientError::InvalidToken(_))
+        ));
+    }
+}
@@ -0,0 +1,65 @@
+//! Observing the requests sent by the [`Cloudflare`](crate::Cloudflare) client.
+//!
+//! A [`Hook`] is called before every request is sent and after every response is received, wherever in the client
+//! the request comes from. Hooks are added with [`Cloudflare::with_hook`](crate::Cloudflare::with_hook) and are useful
+//! for logging, metrics, and tracing.
+
+use std::time::Duration;
+
+use reqwest::{Method, StatusCode};
+
+/// A request about to be sent to the Cloudflare API.
+#[derive(Debug)]
+pub struct RequestEvent<'a> {
+    /// The HTTP method of the request.
+    pub method: &'a Method,
+
+    /// The path of the request relative to the API's base URL, e.g. `/zones`, excluding the query.
+    pub path: &'a str,
+}
+
+/// A response received from the Cloudflare API, or the failure to receive one.
+#[derive(Debug)]
+pub struct ResponseEvent<'a> {
+    /// The HTTP method of the request.
+    pub method: &'a Method,
+
+    /// The path of the request relative to the API's base URL, e.g. `/zones`, excluding the query.
+    pub path: &'a str,
+
+    /// The HTTP status of the response, or `None` if the request failed before a response was received.
+    pub status: Option<StatusCode>,
+
+    /// How long the request took, from sending it to receiving the response headers.
+    pub duration: Duration,
+}
+
+/// An observer of the requests sent by the [`Cloudflare`](crate::Cloudflare) client.
+///
+/// Both methods do nothing by default, so implementations only need to override the ones they care about.
+pub trait Hook: Send + Sync {
+    /// Called before a request is sent.
+    fn on_request(&self, _request: &RequestEvent) {}
+
+    /// Called after a response is received, or the request failed.
+    fn on_response(&self, _response: &ResponseEvent) {}
+}
+
+/// Strip the API's base `path` prefix, e.g. `/client/v4`, from the path of a request `url`.
+pub(crate) fn api_path<'a>(url: &'a reqwest::Url, base: &str) -> &'a str {
+    let path = url.path();
+    path.strip_prefix(base).unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_base_path() {
+        let url =
+            reqwest::Url::parse("https://api.cloudflare.com/client/v4/zones/z?page=1").unwrap();
+        assert_eq!(api_path(&url, "/client/v4"), "/zones/z");
+        assert_eq!(api_path(&url, "/other"), "/client/v4/zones/z");
+    }
+}
@@ -0,0 +1,501 @@
+//! A client for the subset of the [Cloudflare API](https://developers.cloudflare.com/api/) used by cf-ddns.
+//!
+//! The API is organized around the async [`Cloudflare`] client, which can verify its [token](TokenStatus), look up
+//! [zones](Zone), and create, read, update, and delete DNS [records](Record). Every call returns a
+//! [`CloudflareError`] on failure, parameterized by any errors specific to that call. Programs without an async
+//! runtime use the [`blocking`] client instead, which has the same calls.
+#![deny(missing_docs)]
+
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use cf_ddns::ResponseInfo;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER},
+    Client, ClientBuilder, RequestBuilder, StatusCode,
+};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+pub mod blocking;
+pub mod error;
+pub mod hooks;
+pub mod ratelimit;
+pub mod record;
+pub mod token;
+pub mod zone;
+
+pub use error::{ClientError, CloudflareError, ConflictError, GetRecordIdError, NoApiSpecific};
+pub use hooks::{Hook, RequestEvent, ResponseEvent};
+pub use ratelimit::{RateLimitError, RateLimitStatus};
+pub use record::{NewRecord, Record, RecordContent, RecordPatch};
+pub use token::TokenStatus;
+pub use zone::Zone;
+
+/// The base URL of the Cloudflare API.
+const API_URL: &str = "https://api.cloudflare.com/client/v4";
+
+/// The path of [`API_URL`], which is stripped from the paths passed to [hooks](Hook).
+const API_PATH: &str = "/client/v4";
+
+/// A message or error returned by the Cloudflare API, identified by a numeric code.
+pub type CfResponseInfo = ResponseInfo<i32>;
+
+/// The number of results to request per page from list endpoints.
+const PER_PAGE: u32 = 50;
+
+#[derive(Deserialize)]
+struct Response<T> {
+    success: bool,
+    errors: Vec<CfResponseInfo>,
+    result: Option<T>,
+    result_info: Option<ResultInfo>,
+}
+
+/// Pagination details included in responses from list endpoints.
+#[derive(Deserialize)]
+struct ResultInfo {
+    page: u32,
+    total_pages: u32,
+}
+
+impl<T> Response<T> {
+    /// Convert the response into its result, or the errors returned by the API.
+    fn into_result<E>(self) -> Result<T, CloudflareError<E>> {
+        if !self.success {
+            return Err(CloudflareError::Error(self.errors));
+        }
+
+        self.result.ok_or(CloudflareError::EmptyResult)
+    }
+}
+
+#[derive(Deserialize)]
+struct Id {
+    id: String,
+}
+
+/// A client for interacting with the Cloudflare API.
+pub struct Cloudflare {
+    client: Client,
+    rate_limit: Mutex<RateLimitStatus>,
+    budget: Option<u32>,
+    hooks: Vec<Box<dyn Hook>>,
+}
+
+impl Cloudflare {
+    /// Create a new Cloudflare client.
+    pub fn try_new(token: &str) -> Result<Self, ClientError> {
+        Self::try_with_builder(token, Client::builder())
+    }
+
+    /// Create a new Cloudflare client whose HTTP client is built by `builder`, e.g. to configure timeouts.
+    pub fn try_with_builder(token: &str, builder: ClientBuilder) -> Result<Self, ClientError> {
+        let authorization =
+            HeaderValue::try_from(format!("Bearer {token}")).map_err(ClientError::InvalidToken)?;
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, authorization);
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        let client = builder
+            .default_headers(headers)
+            .build()
+            .map_err(ClientError::Build)?;
+        Ok(Self {
+            client,
+            rate_limit: Mutex::default(),
+            budget: None,
+            hooks: Vec::new(),
+        })
+    }
+
+    /// Add a [`Hook`] that is called before every request and after every response.
+    ///
+    /// Hooks are called in the order they are added.
+    pub fn with_hook(mut self, hook: impl Hook + 'static) -> Self {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Limit the client to sending at most `budget` requests, after which requests fail with
+    /// [`RateLimitError::BudgetExhausted`].
+    ///
+    /// This is useful to leave some of the [rate limit](ratelimit) for other clients sharing the token.
+    pub fn with_budget(mut self, budget: u32) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// The rate limit state observed so far, including the number of requests sent.
+    pub fn rate_limit(&self) -> RateLimitStatus {
+        *self.rate_limit.lock().unwrap()
+    }
+
+    /// Send a request, respecting the rate limit and the budget.
+    ///
+    /// When few requests remain in the current window, this sleeps to spread the remaining requests over the rest of
+    /// the window rather than exhausting them.
+    async fn execute<E>(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<reqwest::Response, CloudflareError<E>> {
+        let delay = self
+            .rate_limit()
+            .check(self.budget)
+            .map_err(CloudflareError::RateLimited)?;
+
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+
+        let request = request.build().map_err(CloudflareError::RequestFailed)?;
+        let method = request.method().clone();
+        let path = hooks::api_path(request.url(), API_PATH).to_string();
+        let event = RequestEvent {
+            method: &method,
+            path: &path,
+        };
+
+        for hook in &self.hooks {
+            hook.on_request(&event);
+        }
+
+        self.rate_limit.lock().unwrap().calls += 1;
+        let start = Instant::now();
+        let response = self.client.execute(request).await;
+        let event = ResponseEvent {
+            method: &method,
+            path: &path,
+            status: response.as_ref().ok().map(|response| response.status()),
+            duration: start.elapsed(),
+        };
+
+        for hook in &self.hooks {
+            hook.on_response(&event);
+        }
+
+        let response = response.map_err(CloudflareError::RequestFailed)?;
+        self.rate_limit.lock().unwrap().observe(response.headers());
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok())
+                .map(Duration::from_secs);
+
+            return Err(CloudflareError::RateLimited(
+                RateLimitError::TooManyRequests(retry_after),
+            ));
+        }
+
+        Ok(response)
+    }
+
+    /// Send a request and parse the Cloudflare API response envelope.
+    async fn send<T: DeserializeOwned, E>(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<T, CloudflareError<E>> {
+        self.execute(request)
+            .await?
+            .json::<Response<T>>()
+            .await
+            .map_err(CloudflareError::ResponseNotJson)?
+            .into_result()
+    }
+
+    /// Send a request to a list endpoint, following pagination until every page has been fetched.
+    ///
+    /// `request` is called once per page to build the request, to which the page parameters are added.
+    async fn send_all<T: DeserializeOwned, E>(
+        &self,
+        request: impl Fn() -> RequestBuilder,
+    ) -> Result<Vec<T>, CloudflareError<E>> {
+        let mut results = Vec::new();
+        let mut page = 1;
+        loop {
+            let response = self
+                .execute(request().query(&[("page", page), ("per_page", PER_PAGE)]))
+                .await?
+                .json::<Response<Vec<T>>>()
+                .await
+                .map_err(CloudflareError::ResponseNotJson)?;
+
+            let info = response
+                .result_info
+                .as_ref()
+                .map(|info| (info.page, info.total_pages));
+            results.extend(response.into_result()?);
+            match info {
+                Some((current, total)) if current < total => page = current + 1,
+                _ => return Ok(results),
+            }
+        }
+    }
+
+    /// Verify the API token the client authenticates with.
+    pub async fn verify_token(&self) -> Result<TokenStatus, CloudflareError<NoApiSpecific>> {
+        self.send(self.client.get(format!("{API_URL}/user/tokens/verify")))
+            .await
+    }
+
+    /// List all zones the API token has access to.
+    pub async fn list_zones(&self) -> Result<Vec<Zone>, CloudflareError<NoApiSpecific>> {
+        self.send_all(|| self.client.get(format!("{API_URL}/zones")))
+            .await
+    }
+
+    /// Get a Cloudflare DNS zone by name.
+    pub async fn get_zone(&self, name: &str) -> Result<Zone, CloudflareError<NoApiSpecific>> {
+        let request = self
+            .client
+            .get(format!("{API_URL}/zones"))
+            .query(&[("name", name)]);
+        self.send::<Vec<Zone>, _>(request)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(CloudflareError::EmptyResult)
+    }
+
+    /// Get a Cloudflare DNS zone by its ID.
+    pub async fn get_zone_by_id(
+        &self,
+        zone_id: &str,
+    ) -> Result<Zone, CloudflareError<NoApiSpecific>> {
+        self.send(self.client.get(format!("{API_URL}/zones/{zone_id}")))
+            .await
+    }
+
+    /// Get the zone ID of a Cloudflare DNS zone by name.
+    pub async fn get_zone_id(&self, name: &str) -> Result<String, CloudflareError<NoApiSpecific>> {
+        Ok(self.get_zone(name).await?.id)
+    }
+
+    /// Get all DNS records in a zone with the given fully-qualified name.
+    pub async fn get_records(
+        &self,
+        zone_id: &str,
+        name: &str,
+    ) -> Result<Vec<Record>, CloudflareError<NoApiSpecific>> {
+        let request = self
+            .client
+            .get(format!("{API_URL}/zones/{zone_id}/dns_records"))
+            .query(&[("name.exact", name)]);
+
+        self.send(request).await
+    }
+
+    /// List all DNS records in a zone, optionally only those of type `record_type`, e.g. `A`.
+    pub async fn list_records(
+        &self,
+        zone_id: &str,
+        record_type: Option<&str>,
+    ) -> Result<Vec<Record>, CloudflareError<NoApiSpecific>> {
+        self.send_all(|| {
+            let request = self
+                .client
+                .get(format!("{API_URL}/zones/{zone_id}/dns_records"));
+            match record_type {
+                Some(record_type) => request.query(&[("type", record_type)]),
+                None => request,
+            }
+        })
+        .await
+    }
+
+    /// Export all DNS records in a zone as a BIND zone file, using Cloudflare's export endpoint.
+    pub async fn export_records(
+        &self,
+        zone_id: &str,
+    ) -> Result<String, CloudflareError<NoApiSpecific>> {
+        let response = self
+            .execute(
+                self.client
+                    .get(format!("{API_URL}/zones/{zone_id}/dns_records/export")),
+            )
+            .await?;
+
+        if response.status().is_success() {
+            return response
+                .text()
+                .await
+                .map_err(CloudflareError::RequestFailed);
+        }
+
+        // Errors are still reported in the usual JSON envelope.
+        Err(response
+            .json::<Response<()>>()
+            .await
+            .map_err(CloudflareError::ResponseNotJson)?
+            .into_result::<NoApiSpecific>()
+            .err()
+            .unwrap_or(CloudflareError::EmptyResult))
+    }
+
+    /// Get the record ID of a Cloudflare DNS record by name.
+    pub async fn get_record_id(
+        &self,
+        zone_id: &str,
+        name: &str,
+    ) -> Result<String, CloudflareError<GetRecordIdError>> {
+        let request = self
+            .client
+            .get(format!("{API_URL}/zones/{zone_id}/dns_records"))
+            .query(&[("name.exact", name)]);
+
+        Ok(self
+            .send::<Vec<Record>, _>(request)
+            .await?
+            .first()
+            .ok_or(CloudflareError::EmptyResult)?
+            .id
+            .clone())
+    }
+
+    /// Get a DNS record by its ID.
+    pub async fn get_record(
+        &self,
+        zone_id: &str,
+        record_id: &str,
+    ) -> Result<Record, CloudflareError<NoApiSpecific>> {
+        let request = self
+            .client
+            .get(format!("{API_URL}/zones/{zone_id}/dns_records/{record_id}"));
+
+        self.send(request).await
+    }
+
+    /// Create a new DNS record in a zone.
+    pub async fn create_record(
+        &self,
+        zone_id: &str,
+        record: &NewRecord,
+    ) -> Result<Record, CloudflareError<NoApiSpecific>> {
+        let request = self
+            .client
+            .post(format!("{API_URL}/zones/{zone_id}/dns_records"))
+            .json(record);
+
+        self.send(request).await
+    }
+
+    /// Update a DNS record with the new content from the given `content`.
+    ///
+    /// This performs a `PATCH` request to the Cloudflare API. Only the type and value of the record is updated.
+    pub async fn update_record(
+        &self,
+        zone_id: &str,
+        record_id: &str,
+        content: IpAddr,
+    ) -> Result<Record, CloudflareError<NoApiSpecific>> {
+        self.patch_record(zone_id, record_id, &RecordPatch::from(content))
+            .await
+    }
+
+    /// Apply a [`RecordPatch`] to a DNS record, leaving any fields it doesn't set unchanged.
+    pub async fn patch_record(
+        &self,
+        zone_id: &str,
+        record_id: &str,
+        patch: &RecordPatch,
+    ) -> Result<Record, CloudflareError<NoApiSpecific>> {
+        let request = self
+            .client
+            .patch(format!("{API_URL}/zones/{zone_id}/dns_records/{record_id}"))
+            .json(patch);
+
+        self.send(request).await
+    }
+
+    /// Apply a [`RecordPatch`] to `record`, but only if it hasn't been modified since it was read.
+    ///
+    /// The Cloudflare API has no conditional updates, so the record is read again immediately before patching and
+    /// its `modified_on` compared with that of `record`.
+    pub async fn patch_record_if_unmodified(
+        &self,
+        zone_id: &str,
+        record: &Record,
+        patch: &RecordPatch,
+    ) -> Result<Record, CloudflareError<ConflictError>> {
+        let current = self
+            .get_record(zone_id, &record.id)
+            .await
+            .map_err(CloudflareError::with_api_specific)?;
+
+        if current.modified_on != record.modified_on {
+            return Err(CloudflareError::ApiSpecific(ConflictError {
+                record_id: record.id.clone(),
+                expected_modified_on: record.modified_on.clone(),
+                actual_modified_on: current.modified_on,
+            }));
+        }
+
+        self.patch_record(zone_id, &record.id, patch)
+            .await
+            .map_err(CloudflareError::with_api_specific)
+    }
+
+    /// Like [`Self::patch_record_if_unmodified`], but returns `None` without patching if the record, as read again,
+    /// already matches `patch`.
+    pub async fn patch_record_if_changed(
+        &self,
+        zone_id: &str,
+        record: &Record,
+        patch: &RecordPatch,
+    ) -> Result<Option<Record>, CloudflareError<ConflictError>> {
+        let current = self
+            .get_record(zone_id, &record.id)
+            .await
+            .map_err(CloudflareError::with_api_specific)?;
+
+        if patch.is_noop(&current) {
+            return Ok(None);
+        }
+
+        if current.modified_on != record.modified_on {
+            return Err(CloudflareError::ApiSpecific(ConflictError {
+                record_id: record.id.clone(),
+                expected_modified_on: record.modified_on.clone(),
+                actual_modified_on: current.modified_on,
+            }));
+        }
+
+        self.patch_record(zone_id, &record.id, patch)
+            .await
+            .map(Some)
+            .map_err(CloudflareError::with_api_specific)
+    }
+
+    /// Replace every field of a DNS record with those of `record`.
+    ///
+    /// This performs a `PUT` request to the Cloudflare API, unlike [`Self::update_record`] which only patches the
+    /// content.
+    pub async fn overwrite_record(
+        &self,
+        zone_id: &str,
+        record_id: &str,
+        record: &NewRecord,
+    ) -> Result<Record, CloudflareError<NoApiSpecific>> {
+        let request = self
+            .client
+            .put(format!("{API_URL}/zones/{zone_id}/dns_records/{record_id}"))
+            .json(record);
+
+        self.send(request).await
+    }
+
+    /// Delete a DNS record, returning the ID of the deleted record.
+    pub async fn delete_record(
+        &self,
+        zone_id: &str,
+        record_id: &str,
+    ) -> Result<String, CloudflareError<NoApiSpecific>> {
+        let request = self
+            .client
+            .delete(format!("{API_URL}/zones/{zone_id}/dns_records/{record_id}"));
+
+        Ok(self.send::<Id, _>(request).await?.id)
+    }
+}
@@ -0,0 +1,204 @@
+//! Tracking the Cloudflare API rate limit.
+//!
+//! Cloudflare allows [1200 requests per five minutes](https://developers.cloudflare.com/fundamentals/api/reference/limits/)
+//! per user, shared by every client using the same token. The [`Cloudflare`](crate::Cloudflare) client counts the
+//! requests it makes, reads the remaining allowance from the `Ratelimit` response header, and refuses to send requests
+//! that would exceed either the allowance or an optional local budget.
+
+use std::error;
+use std::fmt;
+use std::time::Duration;
+
+use reqwest::header::HeaderMap;
+
+/// The number of requests Cloudflare allows per [`WINDOW`].
+pub const LIMIT: u32 = 1200;
+
+/// The window over which Cloudflare counts requests.
+pub const WINDOW: Duration = Duration::from_secs(300);
+
+/// When fewer requests than this remain, requests are spread out over the rest of the window.
+const LOW_WATER_MARK: u32 = 50;
+
+/// The rate limit state observed by a client.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RateLimitStatus {
+    /// The number of requests sent by the client.
+    pub calls: u32,
+
+    /// The number of requests remaining in the current window, according to the last response.
+    pub remaining: Option<u32>,
+
+    /// The time until the current window resets, according to the last response.
+    pub reset: Option<Duration>,
+}
+
+impl RateLimitStatus {
+    /// Update the status from the headers of a response.
+    pub(crate) fn observe(&mut self, headers: &HeaderMap) {
+        let (remaining, reset) = headers
+            .get("ratelimit")
+            .and_then(|value| value.to_str().ok())
+            .map(parse_ratelimit)
+            .unwrap_or_default();
+
+        let legacy_remaining = headers
+            .get("x-ratelimit-remaining")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok());
+
+        if let Some(remaining) = remaining.or(legacy_remaining) {
+            self.remaining = Some(remaining);
+        }
+
+        if let Some(reset) = reset {
+            self.reset = Some(reset);
+        }
+    }
+
+    /// Check whether another request may be sent within `budget`, returning how long to wait before sending it.
+    pub(crate) fn check(&self, budget: Option<u32>) -> Result<Duration, RateLimitError> {
+        if let Some(budget) = budget {
+            if self.calls >= budget {
+                return Err(RateLimitError::BudgetExhausted(budget));
+            }
+        }
+
+        match (self.remaining, self.reset) {
+            (Some(0), reset) => Err(RateLimitError::Exhausted(reset)),
+            (Some(remaining), Some(reset)) if remaining < LOW_WATER_MARK => {
+                Ok(reset / (remaining + 1))
+            }
+            _ => Ok(Duration::ZERO),
+        }
+    }
+}
+
+/// Parse the `Ratelimit` header, e.g. `"default";r=1199;t=299`, into the remaining requests and the time until reset.
+fn parse_ratelimit(value: &str) -> (Option<u32>, Option<Duration>) {
+    let mut remaining = None;
+    let mut reset = None;
+    for param in value.split(';') {
+        match param.trim().split_once('=') {
+            Some(("r", r)) => remaining = r.parse().ok(),
+            Some(("t", t)) => reset = t.parse().ok().map(Duration::from_secs),
+            _ => {}
+        }
+    }
+
+    (remaining, reset)
+}
+
+/// Errors raised to avoid exceeding the Cloudflare API rate limit.
+#[derive(Debug, PartialEq)]
+pub enum RateLimitError {
+    /// The client has already sent as many requests as its budget allows. Contains the budget.
+    BudgetExhausted(u32),
+
+    /// Cloudflare reported that no requests remain in the current window. Contains the time until it resets, if
+    /// known.
+    Exhausted(Option<Duration>),
+
+    /// Cloudflare rejected a request with `429 Too Many Requests`. Contains the `Retry-After` delay, if any.
+    TooManyRequests(Option<Duration>),
+}
+
+impl fmt::Display for RateLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let limit = format!(
+            "Cloudflare allows {LIMIT} requests per {} minutes across every client using the same token",
+            WINDOW.as_secs() / 60
+        );
+
+        match self {
+            Self::BudgetExhausted(budget) => write!(
+                f,
+                "Stopped after {budget} Cloudflare API requests to stay within the rate limit. {limit}."
+            ),
+            Self::Exhausted(reset) => {
+                write!(f, "The Cloudflare API rate limit has been reached. {limit}")?;
+                match reset {
+                    Some(reset) => write!(f, "; try again in {} seconds.", reset.as_secs()),
+                    None => write!(f, "."),
+                }
+            }
+            Self::TooManyRequests(retry_after) => {
+                write!(f, "The Cloudflare API rejected the request as rate limited. {limit}")?;
+                match retry_after {
+                    Some(retry_after) => write!(f, "; try again in {} seconds.", retry_after.as_secs()),
+                    None => write!(f, "."),
+                }
+            }
+        }
+    }
+}
+
+impl error::Error for RateLimitError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ratelimit_header() {
+        assert_eq!(
+            parse_ratelimit("\"default\";r=1199;t=299"),
+            (Some(1199), Some(Duration::from_secs(299)))
+        );
+
+        assert_eq!(parse_ratelimit("garbage"), (None, None));
+    }
+
+    #[test]
+    fn observes_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("ratelimit", "\"default\";r=10;t=120".parse().unwrap());
+        let mut status = RateLimitStatus::default();
+        status.observe(&headers);
+        assert_eq!(status.remaining, Some(10));
+        assert_eq!(status.reset, Some(Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn budget_is_enforced() {
+        let status = RateLimitStatus {
+            calls: 5,
+            ..Default::default()
+        };
+
+        assert_eq!(status.check(Some(6)), Ok(Duration::ZERO));
+        assert_eq!(
+            status.check(Some(5)),
+            Err(RateLimitError::BudgetExhausted(5))
+        );
+    }
+
+    #[test]
+    fn slows_down_when_low() {
+        let status = RateLimitStatus {
+            calls: 0,
+            remaining: Some(9),
+            reset: Some(Duration::from_secs(100)),
+        };
+
+        assert_eq!(status.check(None), Ok(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn aborts_when_exhausted() {
+        let status = RateLimitStatus {
+            calls: 0,
+            remaining: Some(0),
+            reset: Some(Duration::from_secs(100)),
+        };
+
+        assert_eq!(
+            status.check(None),
+            Err(RateLimitError::Exhausted(Some(Duration::from_secs(100))))
+        );
+    }
+}
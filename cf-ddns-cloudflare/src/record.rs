@@ -0,0 +1,373 @@
+//! Types describing Cloudflare DNS records.
+
+use std::fmt;
+use std::net::{AddrParseError, IpAddr, Ipv4Addr, Ipv6Addr};
+
+use serde::{Deserialize, Serialize};
+
+/// A DNS record.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Record {
+    /// The record ID.
+    pub id: String,
+
+    /// The name of the zone.
+    pub zone_name: String,
+
+    /// The name of the record.
+    pub name: String,
+
+    /// The content of the record.
+    #[serde(flatten)]
+    pub content: RecordContent,
+
+    /// The time to live of the record, in seconds. A value of `1` means automatic.
+    #[serde(default = "default_ttl")]
+    pub ttl: u32,
+
+    /// Whether the record is proxied through Cloudflare.
+    #[serde(default)]
+    pub proxied: bool,
+
+    /// The priority of the record, for types that have one such as `MX` and `SRV`.
+    #[serde(default)]
+    pub priority: Option<u16>,
+
+    /// A free-form comment attached to the record.
+    #[serde(default)]
+    pub comment: Option<String>,
+
+    /// The tags attached to the record, each formatted as `name:value`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// When the record was last modified, in RFC 3339 format.
+    #[serde(default)]
+    pub modified_on: Option<String>,
+}
+
+/// The content of a DNS record.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(try_from = "RawContent", into = "RawContent")]
+pub enum RecordContent {
+    /// An `A` record.
+    A {
+        /// The IPv4 address of the record.
+        content: Ipv4Addr,
+    },
+
+    /// An `AAAA` record.
+    AAAA {
+        /// The IPv6 address of the record.
+        content: Ipv6Addr,
+    },
+
+    /// Another type of record.
+    Other {
+        /// The type of the record, e.g. `CNAME` or `TXT`.
+        record_type: String,
+
+        /// The content of the record, as formatted by the Cloudflare API.
+        content: String,
+    },
+}
+
+impl RecordContent {
+    /// The type of the record, e.g. `A`, `AAAA`, or `TXT`.
+    pub fn record_type(&self) -> &str {
+        match self {
+            Self::A { .. } => "A",
+            Self::AAAA { .. } => "AAAA",
+            Self::Other { record_type, .. } => record_type,
+        }
+    }
+
+    /// The IP address of an `A` or `AAAA` record.
+    pub fn ip(&self) -> Option<IpAddr> {
+        match self {
+            Self::A { content } => Some(IpAddr::V4(*content)),
+            Self::AAAA { content } => Some(IpAddr::V6(*content)),
+            Self::Other { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for RecordContent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::A { content } => write!(f, "{content}"),
+            Self::AAAA { content } => write!(f, "{content}"),
+            Self::Other { content, .. } => write!(f, "{content}"),
+        }
+    }
+}
+
+/// The wire representation of [`RecordContent`], which keeps the content of record types that aren't modeled.
+#[derive(Deserialize, Serialize)]
+struct RawContent {
+    r#type: String,
+
+    #[serde(default)]
+    content: String,
+}
+
+impl TryFrom<RawContent> for RecordContent {
+    type Error = AddrParseError;
+
+    fn try_from(raw: RawContent) -> Result<Self, Self::Error> {
+        Ok(match raw.r#type.as_str() {
+            "A" => Self::A {
+                content: raw.content.parse()?,
+            },
+            "AAAA" => Self::AAAA {
+                content: raw.content.parse()?,
+            },
+            _ => Self::Other {
+                record_type: raw.r#type,
+                content: raw.content,
+            },
+        })
+    }
+}
+
+impl From<RecordContent> for RawContent {
+    fn from(content: RecordContent) -> Self {
+        Self {
+            r#type: content.record_type().to_string(),
+            content: content.to_string(),
+        }
+    }
+}
+
+impl From<IpAddr> for RecordContent {
+    fn from(ip: IpAddr) -> Self {
+        match ip {
+            IpAddr::V4(content) => Self::A { content },
+            IpAddr::V6(content) => Self::AAAA { content },
+        }
+    }
+}
+
+/// A DNS record to be created.
+#[derive(Debug, Serialize, Clone)]
+pub struct NewRecord {
+    /// The fully-qualified name of the record.
+    pub name: String,
+
+    /// The content of the record.
+    #[serde(flatten)]
+    pub content: RecordContent,
+
+    /// The time to live of the record, in seconds. A value of `1` means automatic.
+    pub ttl: u32,
+
+    /// Whether the record is proxied through Cloudflare.
+    pub proxied: bool,
+
+    /// The priority of the record, for types that have one such as `MX` and `SRV`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u16>,
+
+    /// A free-form comment to attach to the record.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+
+    /// The tags to attach to the record, each formatted as `name:value`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl NewRecord {
+    /// Create a new record for `name` pointing at `ip`, with an automatic TTL and no proxying.
+    pub fn new(name: &str, ip: IpAddr) -> Self {
+        Self {
+            name: name.to_string(),
+            content: ip.into(),
+            ttl: default_ttl(),
+            proxied: false,
+            priority: None,
+            comment: None,
+            tags: Vec::new(),
+        }
+    }
+}
+
+impl From<&Record> for NewRecord {
+    fn from(record: &Record) -> Self {
+        Self {
+            name: record.name.clone(),
+            content: record.content.clone(),
+            ttl: record.ttl,
+            proxied: record.proxied,
+            priority: record.priority,
+            comment: record.comment.clone(),
+            tags: record.tags.clone(),
+        }
+    }
+}
+
+/// Changes to apply to an existing DNS record. Fields that are `None` are left unchanged.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct RecordPatch {
+    /// The new content of the record.
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub content: Option<RecordContent>,
+
+    /// The new comment of the record.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+
+    /// The new tags of the record, each formatted as `name:value`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+}
+
+impl From<IpAddr> for RecordPatch {
+    fn from(ip: IpAddr) -> Self {
+        Self {
+            content: Some(ip.into()),
+            ..Default::default()
+        }
+    }
+}
+
+impl RecordPatch {
+    /// Whether applying this patch to `record` would leave it unchanged.
+    pub fn is_noop(&self, record: &Record) -> bool {
+        self.content
+            .as_ref()
+            .map_or(true, |content| *content == record.content)
+            && self
+                .comment
+                .as_ref()
+                .map_or(true, |comment| record.comment.as_ref() == Some(comment))
+            && self.tags.as_ref().map_or(true, |tags| *tags == record.tags)
+    }
+}
+
+fn default_ttl() -> u32 {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_a_record() {
+        let json =
+            r#"{"type":"A","id":"123","name":"foo","zone_name":"zone","content":"10.0.0.0"}"#;
+        let record: Record = serde_json::from_str(json).unwrap();
+        match record.content {
+            RecordContent::A { content: ip } => assert_eq!(ip, Ipv4Addr::new(10, 0, 0, 0)),
+            _ => panic!("Expected an A record."),
+        }
+    }
+
+    #[test]
+    fn deserialize_aaaa_record() {
+        let json = r#"{"type":"AAAA","id":"123","name":"foo","zone_name":"zone","content":"::1"}"#;
+        let record: Record = serde_json::from_str(json).unwrap();
+        match record.content {
+            RecordContent::AAAA { content: ip } => {
+                assert_eq!(ip, Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1))
+            }
+            _ => panic!("Expected an AAAA record."),
+        }
+    }
+
+    #[test]
+    fn deserialize_other_record() {
+        let json =
+            r#"{"type":"TXT","id":"123","name":"foo","zone_name":"zone","content":"example"}"#;
+        let record: Record = serde_json::from_str(json).unwrap();
+        match record.content {
+            RecordContent::Other {
+                record_type,
+                content,
+            } => {
+                assert_eq!(record_type, "TXT");
+                assert_eq!(content, "example");
+            }
+            _ => panic!("Expected an other record."),
+        }
+    }
+
+    #[test]
+    fn deserialize_invalid_a_record() {
+        let json = r#"{"type":"A","id":"123","name":"foo","zone_name":"zone","content":"::1"}"#;
+        assert!(serde_json::from_str::<Record>(json).is_err());
+    }
+
+    #[test]
+    fn serialize_record_keeps_other_content() {
+        let json = r#"{"type":"CNAME","id":"123","name":"foo","zone_name":"zone","content":"bar"}"#;
+        let record: Record = serde_json::from_str(json).unwrap();
+        let value = serde_json::to_value(&record).unwrap();
+        assert_eq!(value["type"], "CNAME");
+        assert_eq!(value["content"], "bar");
+    }
+
+    #[test]
+    fn deserialize_ttl_and_proxied() {
+        let json = r#"{"type":"A","id":"123","name":"foo","zone_name":"zone","content":"10.0.0.0","ttl":300,"proxied":true}"#;
+        let record: Record = serde_json::from_str(json).unwrap();
+        assert_eq!(record.ttl, 300);
+        assert!(record.proxied);
+    }
+
+    #[test]
+    fn deserialize_comment_and_tags() {
+        let json = r#"{"type":"A","id":"123","name":"foo","zone_name":"zone","content":"10.0.0.0","comment":"hi","tags":["a:b"]}"#;
+        let record: Record = serde_json::from_str(json).unwrap();
+        assert_eq!(record.comment.as_deref(), Some("hi"));
+        assert_eq!(record.tags, ["a:b"]);
+    }
+
+    #[test]
+    fn serialize_patch() {
+        let patch = RecordPatch {
+            comment: Some("hi".to_string()),
+            ..RecordPatch::from(IpAddr::V6(Ipv6Addr::LOCALHOST))
+        };
+
+        let json = serde_json::to_value(&patch).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"type": "AAAA", "content": "::1", "comment": "hi"})
+        );
+    }
+
+    #[test]
+    fn serialize_new_record() {
+        let record = NewRecord::new("foo.example.com", IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "name": "foo.example.com",
+                "type": "A",
+                "content": "10.0.0.1",
+                "ttl": 1,
+                "proxied": false,
+            })
+        );
+    }
+
+    #[test]
+    fn patch_is_noop() {
+        let json =
+            r#"{"type":"A","id":"123","name":"foo","zone_name":"zone","content":"10.0.0.1"}"#;
+        let record: Record = serde_json::from_str(json).unwrap();
+        assert!(RecordPatch::default().is_noop(&record));
+        assert!(RecordPatch::from(IpAddr::from([10, 0, 0, 1])).is_noop(&record));
+        assert!(!RecordPatch::from(IpAddr::from([10, 0, 0, 2])).is_noop(&record));
+
+        let patch = RecordPatch {
+            comment: Some("managed-by=cf-ddns".to_string()),
+            ..RecordPatch::from(IpAddr::from([10, 0, 0, 1]))
+        };
+
+        assert!(!patch.is_noop(&record));
+    }
+}
@@ -0,0 +1,24 @@
+//! Types describing the API token the client authenticates with.
+
+use serde::{Deserialize, Serialize};
+
+/// The result of verifying an API token.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TokenStatus {
+    /// The token ID.
+    pub id: String,
+
+    /// The status of the token, e.g. `active`, `disabled`, or `expired`.
+    pub status: String,
+
+    /// When the token expires, as an RFC 3339 timestamp, if it does.
+    #[serde(default)]
+    pub expires_on: Option<String>,
+}
+
+impl TokenStatus {
+    /// Whether the token can be used.
+    pub fn is_active(&self) -> bool {
+        self.status == "active"
+    }
+}
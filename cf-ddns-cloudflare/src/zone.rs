@@ -0,0 +1,20 @@
+//! Types describing Cloudflare DNS zones.
+
+use serde::{Deserialize, Serialize};
+
+/// A Cloudflare DNS zone.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Zone {
+    /// The zone ID.
+    pub id: String,
+
+    /// The domain name of the zone.
+    pub name: String,
+
+    /// The status of the zone, e.g. `active` or `pending`.
+    pub status: String,
+
+    /// The Cloudflare name servers assigned to the zone.
+    #[serde(default)]
+    pub name_servers: Vec<String>,
+}